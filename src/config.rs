@@ -1,18 +1,49 @@
 use crate::errors::PortfolioError;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::time::Duration;
+
+// Default endpoints for every external API; each is overridable in config.toml (testnets, proxies)
+pub const DEFAULT_BINANCE_URL: &str = "https://api.binance.com";
+pub const DEFAULT_COINGECKO_URL: &str = "https://api.coingecko.com/api/v3";
+pub const DEFAULT_LUNARCRUSH_URL: &str = "https://api.lunarcrush.com/v2";
+pub const DEFAULT_TWILIO_URL: &str = "https://api.twilio.com/2010-04-01";
+pub const DEFAULT_SENDGRID_URL: &str = "https://api.sendgrid.com/v3";
 
 #[derive(Deserialize, Clone, Debug)]
 pub struct Config {
     pub environment: String, // "dev" or "prod"
+    #[serde(default)]
+    pub prod_single_process: bool, // Run all screens in one process in prod (systemd/Docker)
+    #[serde(default, deserialize_with = "deserialize_secs")]
+    pub startup_stagger_secs: u64, // Offset between each screen's start so first fetches don't coincide
+    #[serde(default = "default_refresh_mode")]
+    pub refresh_mode: String, // "interval" sleeps after each refresh; "aligned" wakes on multiples of it (e.g., on the minute)
+    #[serde(default = "default_screens")]
+    pub screens: Vec<String>, // Screens started by `dashboard`, e.g., ["portfolio", "market"]
+    #[serde(default = "default_command")]
+    pub default_command: String, // Subcommand run when none is given, e.g., "market"; "dashboard" starts every screen
     pub exchanges: Vec<ExchangeConfig>,
     pub marketprovider: ExchangeConfig,
+    #[serde(default)]
+    pub pricing: PricingConfig,
+    #[serde(default)]
+    pub http: HttpConfig,
+    #[serde(default)]
+    pub logger: LoggerConfig,
+    #[serde(default)]
+    pub server: ServerConfig,
     pub database: DatabaseConfig,
     pub redis: RedisConfig,
     pub portfolio: PortfolioConfig,
     pub sentiment: SentimentConfig,
     pub display: DisplayConfig,
     pub market: MarketConfig,
+    #[serde(default)]
+    pub watchlist: WatchlistConfig,
+    #[serde(default)]
+    pub suggest: SuggestConfig,
     pub notification: NotificationConfig,
 }
 
@@ -21,46 +52,450 @@ pub struct ExchangeConfig {
     pub name: String, // e.g., "coingecko", "binance"
     pub api_key: String,
     pub api_secret: String,
-    pub base_url: String,
+    #[serde(default)]
+    pub base_url: String, // Empty uses the exchange's public API, e.g., https://testnet.binance.vision for the testnet
+    #[serde(default)]
+    pub taker_fee_pct: f64, // e.g., 0.1 for a 0.1% fee on every market order
+    #[serde(default)]
+    pub quote_assets: Vec<String>, // Binance pairs tried as {SYMBOL}{QUOTE}, e.g., ["USDT", "BTC"]
+    #[serde(default)]
+    pub decimal_prices: bool, // Keep Binance's price strings exact for stop-loss and sell proceeds instead of rounding to f64
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct PricingConfig {
+    #[serde(default)]
+    pub sources: Vec<String>, // Price sources tried in order, e.g., ["binance", "coingecko"]
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct HttpConfig {
+    #[serde(default)]
+    pub max_concurrency: Option<usize>, // Cap on in-flight API requests; unset is unbounded
+    #[serde(default = "default_max_response_bytes")]
+    pub max_response_bytes: usize, // Larger sentiment/market bodies are rejected unread; 0 = no limit
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig {
+            max_concurrency: None,
+            max_response_bytes: default_max_response_bytes(),
+        }
+    }
+}
+
+fn default_max_response_bytes() -> usize {
+    16 * 1024 * 1024
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ServerConfig {
+    #[serde(default)]
+    pub enabled: bool, // Serve GET /ws from the portfolio screen, pushing cycle updates, trades and alerts
+    #[serde(default = "default_server_bind")]
+    pub bind: String, // e.g., "0.0.0.0:8080" to accept remote dashboards
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            enabled: false,
+            bind: default_server_bind(),
+        }
+    }
+}
+
+fn default_server_bind() -> String {
+    "127.0.0.1:8080".to_string()
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct LoggerConfig {
+    #[serde(default)]
+    pub dedup: bool, // Collapse identical consecutive log lines into "(last message repeated N times)"
 }
 
 #[derive(Deserialize, Clone, Debug)]
 pub struct DatabaseConfig {
     pub postgres_url: String,
+    #[serde(default = "default_connect_retries")]
+    pub connect_retries: u32, // Extra attempts after the first failed connect
+    #[serde(
+        default = "default_connect_retry_delay_secs",
+        deserialize_with = "deserialize_secs"
+    )]
+    pub connect_retry_delay_secs: u64, // Initial delay, doubled after each retry
+    #[serde(default = "default_true")]
+    pub required: bool, // false runs on Redis alone when Postgres is down; trades go to trade_log_path until it reconnects
+    #[serde(default = "default_trade_log_path")]
+    pub trade_log_path: String, // JSON lines, one trade each, used only while Postgres is unavailable
+}
+
+fn default_trade_log_path() -> String {
+    "trades.jsonl".to_string()
+}
+
+fn default_refresh_mode() -> String {
+    "interval".to_string()
+}
+
+fn default_command() -> String {
+    "market".to_string()
+}
+
+fn default_screens() -> Vec<String> {
+    ["portfolio", "sentiment", "market"]
+        .map(String::from)
+        .to_vec()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_connect_retries() -> u32 {
+    5
+}
+
+fn default_connect_retry_delay_secs() -> u64 {
+    2
 }
 
 #[derive(Deserialize, Clone, Debug)]
 pub struct RedisConfig {
     pub url: String,
+    #[serde(default)]
+    pub key_prefix: String, // e.g., "prod" -> "prod:price:SUI"; empty keeps bare keys
+    #[serde(default = "default_redis_op_retries")]
+    pub op_retries: u32, // Extra attempts per cache call before treating it as a miss
+    #[serde(
+        default = "default_redis_op_timeout_ms",
+        deserialize_with = "deserialize_millis"
+    )]
+    pub op_timeout_ms: u64, // Per-attempt timeout for cache calls
+    #[serde(
+        default = "default_price_ttl_secs",
+        deserialize_with = "deserialize_secs"
+    )]
+    pub price_ttl_secs: u64, // How long a fetched price is reused before the next API call
+    #[serde(default, deserialize_with = "deserialize_secs_map")]
+    pub price_ttl_per_symbol: HashMap<String, u64>, // Overrides by symbol, e.g., { PHA = "30s", USDC = "15m" }
+}
+
+fn default_price_ttl_secs() -> u64 {
+    300
+}
+
+fn default_redis_op_retries() -> u32 {
+    2
+}
+
+fn default_redis_op_timeout_ms() -> u64 {
+    500
 }
 
 #[derive(Deserialize, Clone, Debug)]
 pub struct PortfolioConfig {
+    #[serde(deserialize_with = "deserialize_secs")]
     pub check_interval_secs: u64,
     pub max_allocation: f64,       // e.g., 0.6 for 60%
     pub stop_loss_percentage: f64, // e.g., 0.2 for 20%
+    #[serde(default)]
+    pub snapshot_path: Option<String>, // JSON snapshot rewritten every cycle, e.g., "portfolio.json"
+    #[serde(default)]
+    pub min_trade_value_usd: f64, // Triggered sells worth less than this are skipped as dust
+    #[serde(default = "default_true")]
+    pub trading_enabled: bool, // false keeps the screens live but never sells; re-read every cycle
+    #[serde(default = "default_true")]
+    pub display_enabled: bool, // false runs checks, logging and notifications without printing the portfolio table
+    #[serde(default = "default_true")]
+    pub persist_baseline: bool, // Keep the last cycle's value, prices and sentiment in Redis across restarts, unless older than notification.max_data_age_secs
+    #[serde(default, deserialize_with = "deserialize_secs")]
+    pub sell_cooldown_secs: u64, // Auto-sells of a symbol are suppressed this long after its last sell
+    #[serde(default, deserialize_with = "deserialize_secs")]
+    pub stop_loss_grace_secs: u64, // A price must stay below the stop-loss this long before it sells; 0 sells on the first breach
+    #[serde(default)]
+    pub live_trading: bool, // true places real Binance market orders; false only simulates trades
+    #[serde(default)]
+    pub holdings: Vec<HoldingConfig>, // Empty keeps the built-in PHA/SUI/DUSK holdings
+    #[serde(default = "default_state_source")]
+    pub state_source: String, // "config" or "database"; database persists trades and only seeds from config once
+    #[serde(default)]
+    pub manual_approval_above_usd: f64, // Triggered sells worth more than this wait for `approve <id>`; 0 executes every sell
+    #[serde(default)]
+    pub min_cash_reserve_usd: f64, // Buys are rejected if they would leave less cash than this
+    #[serde(default)]
+    pub max_holdings: usize, // Cap on distinct symbols held; buys of a new symbol beyond it are rejected. 0 = no cap
+    #[serde(default = "default_rounding_mode")]
+    pub rounding_mode: String, // Cents rounding for fees, proceeds, costs, cash, value, realized P&L and shown amounts: "half_up", "half_even" or "down"
+}
+
+fn default_rounding_mode() -> String {
+    "half_even".to_string()
+}
+
+fn default_state_source() -> String {
+    "config".to_string()
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct HoldingConfig {
+    pub symbol: String,
+    pub quantity: f64,
+    pub purchase_price: f64,
+    pub stop_loss: f64,
+    #[serde(default = "default_true")]
+    pub stop_loss_enabled: bool, // false holds it regardless (HODL): neither stop-loss nor sentiment sells it
 }
 
 #[derive(serde::Deserialize, Clone, Debug)]
 pub struct SentimentConfig {
-    pub api_url: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool, // false (or an empty api_key) falls back to neutral sentiment
+    #[serde(default)]
+    pub api_url: String, // Empty uses the public LunarCrush API
     pub api_key: String,
+    #[serde(deserialize_with = "deserialize_secs")]
     pub cache_ttl_secs: u64,
     pub positive_threshold: f64,
     pub negative_threshold: f64,
+    #[serde(default)]
+    pub buy_threshold: Option<f64>, // Splits the middle band: Accumulate at or above, below positive_threshold
+    #[serde(default)]
+    pub watch_threshold: Option<f64>, // Reduce below it, above negative_threshold; Hold in between
+    #[serde(default)]
+    pub per_symbol_thresholds: HashMap<String, SymbolThresholds>, // Overrides keyed by symbol
+    #[serde(default = "default_sentiment_scale")]
+    pub scale: String, // Provider scale: "unit" (0..1) or "signed" (-1..1); thresholds are always 0..1
+    #[serde(default)]
+    pub network_weights: HashMap<String, f64>, // e.g., {"X" = 2.0, "Reddit" = 1.0}; empty uses current_value
+    #[serde(default)]
+    pub debug_raw: bool, // Log every raw response body at debug level (run with -v); API key masked
+    #[serde(default = "default_on_missing")]
+    pub on_missing: String, // Failed fetch: "skip" (N/A, no sentiment sell), "neutral" (0.5, never sells) or "error" (aborts the cycle)
+    #[serde(default = "default_detail_level")]
+    pub detail_level: String, // Sentiment screen: "full" (dashboard plus per-holding tables) or "summary" (dashboard only)
+    #[serde(default = "default_response_format")]
+    pub response_format: String, // "markdown" (LunarCrush summary page) or "json" (fields named as in DetailedSentiment)
+    #[serde(default = "default_min_data_quality")]
+    pub min_data_quality: f64, // Data Quality below this (0..1) is flagged on the sentiment screen
+    #[serde(default = "default_sentiment_max_concurrency")]
+    pub max_concurrency: usize, // Sentiment screen: detailed fetches in flight at once
+}
+
+fn default_sentiment_max_concurrency() -> usize {
+    4
+}
+
+fn default_min_data_quality() -> f64 {
+    0.5
+}
+
+fn default_detail_level() -> String {
+    "full".to_string()
+}
+
+fn default_response_format() -> String {
+    "markdown".to_string()
+}
+
+fn default_on_missing() -> String {
+    "skip".to_string()
+}
+
+fn default_sentiment_scale() -> String {
+    "unit".to_string()
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct SymbolThresholds {
+    pub positive_threshold: f64,
+    pub negative_threshold: f64,
+}
+
+// Recommendation cut-offs for one symbol; without buy/watch only the positive/negative split applies
+#[derive(Clone, Copy, Debug)]
+pub struct SentimentBands {
+    pub positive: f64,
+    pub buy: Option<f64>,
+    pub watch: Option<f64>,
+    pub negative: f64,
+}
+
+impl SentimentConfig {
+    // (positive, negative) thresholds for a symbol, falling back to the global ones
+    pub fn thresholds_for(&self, symbol: &str) -> (f64, f64) {
+        match self.per_symbol_thresholds.get(symbol) {
+            Some(overrides) => (overrides.positive_threshold, overrides.negative_threshold),
+            None => (self.positive_threshold, self.negative_threshold),
+        }
+    }
+
+    // Buy/watch are global, so they are kept inside a symbol's own positive/negative range
+    pub fn bands_for(&self, symbol: &str) -> SentimentBands {
+        let (positive, negative) = self.thresholds_for(symbol);
+        SentimentBands {
+            positive,
+            buy: self.buy_threshold.map(|buy| buy.min(positive)),
+            watch: self.watch_threshold.map(|watch| watch.max(negative)),
+            negative,
+        }
+    }
 }
 
 #[derive(Deserialize, Clone, Debug)]
 pub struct DisplayConfig {
+    #[serde(deserialize_with = "deserialize_secs")]
     pub sentiment_refresh_secs: u64, // Refresh rate for sentiment screen
-    pub use_colors: bool,            // Enable/disable color output
+    pub use_colors: bool, // Enable/disable color output
+    #[serde(default = "default_color_theme")]
+    pub color_theme: String, // "classic" (green/red), "colorblind" (blue/orange) or "mono"
+    #[serde(default)]
+    pub table_width: Option<u16>, // Fixed table width; defaults to the detected terminal width
+    #[serde(default)]
+    pub price_decimals: Option<usize>, // Unset adapts to the price (more places for cheap tokens)
+    #[serde(default = "default_value_decimals")]
+    pub value_decimals: usize, // Holding values, cash, totals and market caps
+    #[serde(default)]
+    pub max_network_rows: Option<usize>, // Top-N networks in the engagement table; unset shows all
+    #[serde(default = "default_table_style")]
+    pub table_style: String, // "ascii", "utf8_full", "markdown" or "borderless"
+    #[serde(default = "default_timezone")]
+    pub timezone: String, // IANA zone for displayed timestamps and log lines, e.g., "America/New_York"
+    #[serde(default = "default_true")]
+    pub show_progress: bool, // "Fetching 7/20 symbols..." spinner; always off when stdout isn't a terminal
+    #[serde(default = "default_holdings_order")]
+    pub holdings_order: String, // Portfolio table rows: "config" (as listed), "symbol" or "value" (largest first)
+    #[serde(skip)]
+    pub rounding_mode: String, // Copied from portfolio.rounding_mode at load so shown amounts round like booked ones
+}
+
+fn default_color_theme() -> String {
+    "classic".to_string()
+}
+
+fn default_holdings_order() -> String {
+    "config".to_string()
+}
+
+fn default_timezone() -> String {
+    "UTC".to_string()
+}
+
+fn default_value_decimals() -> usize {
+    2
+}
+
+fn default_table_style() -> String {
+    "ascii".to_string()
 }
 
 #[derive(Deserialize, Clone, Debug)]
 pub struct MarketConfig {
+    #[serde(deserialize_with = "deserialize_secs")]
     pub refresh_secs: u64,
-    pub sort_by: String,             // e.g., "market_cap" or "price_change_24h"
+    pub sort_by: String,             // "market_cap", "price_change_24h" or "rank"
     pub pinned_symbols: Vec<String>, // e.g., ["phala-network", "sui", "dusk-network"]
+    #[serde(default)]
+    pub show_names: bool, // Adds a Name column resolved through CoinGecko's /coins/list
+    #[serde(default)]
+    pub show_sentiment: bool, // Adds a Sentiment column for pinned symbols only
+    #[serde(default)]
+    pub show_top_movers: bool, // Top 5 gainers and losers by 24h % change above the main table
+    #[serde(default)]
+    pub show_sparkline: bool, // Adds a 7-day price sparkline column; makes the CoinGecko responses much larger
+    #[serde(default = "default_market_top_n")]
+    pub top_n: usize, // Coins requested per refresh (CoinGecko per_page); pinned coins outside it are fetched by id
+    #[serde(default)]
+    pub exchange_fallback: bool, // When CoinGecko answers with an error, show exchange prices for the requested symbols instead of failing
+    #[serde(default = "default_fallback_stats_source")]
+    pub fallback_stats_source: String, // 24h change/high/low/volume for exchange-priced rows: "exchange" (its 24h ticker) or "none" ("-")
+    #[serde(default)]
+    pub exclude_symbols: Vec<String>, // Hidden from the unpinned rows and top movers, e.g., ["wbtc", "steth"]
+    #[serde(default = "default_true")]
+    pub exclude_stablecoins: bool, // Also hides STABLECOINS; pinned symbols are never hidden
+}
+
+fn default_fallback_stats_source() -> String {
+    "exchange".to_string()
+}
+
+// Common USD-pegged coins hidden by market.exclude_stablecoins
+pub const STABLECOINS: [&str; 10] = [
+    "usdt", "usdc", "dai", "fdusd", "tusd", "usde", "usds", "pyusd", "usdd", "busd",
+];
+
+impl MarketConfig {
+    // Lowercased, as CoinGecko reports symbols
+    pub fn excluded_symbols(&self) -> HashSet<String> {
+        let mut excluded: HashSet<String> = self
+            .exclude_symbols
+            .iter()
+            .map(|symbol| symbol.to_lowercase())
+            .collect();
+        if self.exclude_stablecoins {
+            excluded.extend(STABLECOINS.iter().map(|symbol| symbol.to_string()));
+        }
+        excluded
+    }
+}
+
+fn default_market_top_n() -> usize {
+    1000
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct WatchlistConfig {
+    #[serde(default)]
+    pub symbols: Vec<String>, // Coins monitored but not held, e.g., ["BTC", "ETH"]
+}
+
+// Weights for the `suggest` score; each signal is -1..1 and the score is their weighted average
+#[derive(Deserialize, Clone, Debug)]
+pub struct SuggestConfig {
+    #[serde(default = "default_suggest_sentiment_weight")]
+    pub sentiment_weight: f64, // Blended sentiment, 0.5 maps to 0
+    #[serde(default = "default_suggest_trend_weight")]
+    pub trend_weight: f64, // One-week sentiment change
+    #[serde(default = "default_suggest_gain_weight")]
+    pub gain_weight: f64, // Unrealized gain counts towards trimming, a loss towards adding
+    #[serde(default = "default_suggest_hold_band")]
+    pub hold_band: f64, // Scores within ±this suggest holding
+    #[serde(default = "default_suggest_max_trade_pct")]
+    pub max_trade_pct: f64, // Size suggested at a score of ±1, as a percentage of the position
+}
+
+impl Default for SuggestConfig {
+    fn default() -> Self {
+        SuggestConfig {
+            sentiment_weight: default_suggest_sentiment_weight(),
+            trend_weight: default_suggest_trend_weight(),
+            gain_weight: default_suggest_gain_weight(),
+            hold_band: default_suggest_hold_band(),
+            max_trade_pct: default_suggest_max_trade_pct(),
+        }
+    }
+}
+
+fn default_suggest_sentiment_weight() -> f64 {
+    1.0
+}
+
+fn default_suggest_trend_weight() -> f64 {
+    0.5
+}
+
+fn default_suggest_gain_weight() -> f64 {
+    0.5
+}
+
+fn default_suggest_hold_band() -> f64 {
+    0.2
+}
+
+fn default_suggest_max_trade_pct() -> f64 {
+    50.0
 }
 
 #[derive(Deserialize, Clone, Debug)]
@@ -74,7 +509,26 @@ pub struct NotificationConfig {
     pub sendgrid_api_key: String,
     pub sender_email: String,
     pub recipient_email: String,
+    #[serde(default)]
+    pub twilio_api_url: String, // Empty uses the public Twilio API
+    #[serde(default)]
+    pub sendgrid_api_url: String, // Empty uses the public SendGrid API
     pub notification_thresholds: NotificationThresholds,
+    #[serde(default, deserialize_with = "deserialize_secs")]
+    pub max_data_age_secs: u64, // Alert when a holding's last live price is older; 0 disables
+    #[serde(default)]
+    pub routing: HashMap<String, Vec<String>>, // Severity ("info", "warning", "critical") -> channels, e.g., ["sms", "email"]
+    #[serde(default = "default_true")]
+    pub always_notify_trades: bool, // Every executed buy/sell notifies with its details, whatever the change thresholds
+    #[serde(
+        default = "default_allocation_alert_cooldown_secs",
+        deserialize_with = "deserialize_secs"
+    )]
+    pub allocation_alert_cooldown_secs: u64, // Repeat interval for a holding that stays above portfolio.max_allocation
+}
+
+fn default_allocation_alert_cooldown_secs() -> u64 {
+    24 * 60 * 60
 }
 
 #[derive(Deserialize, Clone, Debug)]
@@ -84,10 +538,183 @@ pub struct NotificationThresholds {
     pub sentiment_change: f64,
 }
 
+// Durations accept a bare number (in the field's unit) or a humantime string like "30s", "5m", "1h"
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawDuration {
+    Number(u64),
+    Human(String),
+}
+
+impl RawDuration {
+    // Whole multiples of `unit` only, so "500ms" can't truncate to a 0s interval
+    fn in_unit<E: serde::de::Error>(self, unit: Duration) -> Result<u64, E> {
+        match self {
+            RawDuration::Number(value) => Ok(value),
+            RawDuration::Human(text) => {
+                let duration = humantime::parse_duration(&text)
+                    .map_err(|e| E::custom(format!("invalid duration '{}': {}", text, e)))?;
+                if duration.as_nanos() % unit.as_nanos() != 0 {
+                    return Err(E::custom(format!(
+                        "duration '{}' must be a whole number of {}",
+                        text,
+                        humantime::format_duration(unit)
+                    )));
+                }
+                u64::try_from(duration.as_nanos() / unit.as_nanos())
+                    .map_err(|_| E::custom(format!("duration '{}' too large", text)))
+            }
+        }
+    }
+}
+
+fn deserialize_duration<'de, D: Deserializer<'de>>(
+    deserializer: D,
+    unit: Duration,
+) -> Result<u64, D::Error> {
+    RawDuration::deserialize(deserializer)?.in_unit(unit)
+}
+
+fn deserialize_secs<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    deserialize_duration(deserializer, Duration::from_secs(1))
+}
+
+// A table of durations in seconds, e.g., { BTC = "30s", USDC = "10m" }
+fn deserialize_secs_map<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashMap<String, u64>, D::Error> {
+    HashMap::<String, RawDuration>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(key, raw)| raw.in_unit(Duration::from_secs(1)).map(|secs| (key, secs)))
+        .collect()
+}
+
+fn deserialize_millis<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    deserialize_duration(deserializer, Duration::from_millis(1))
+}
+
+// Fully commented starting point written by `init-config`
+pub const EXAMPLE_CONFIG: &str = include_str!("../config.example.toml");
+
+// Writes EXAMPLE_CONFIG to `path`; an existing file is only replaced with `force`
+pub fn write_example_config(path: &str, force: bool) -> Result<(), PortfolioError> {
+    if !force && std::path::Path::new(path).exists() {
+        return Err(PortfolioError::ConfigError(format!(
+            "{} already exists; pass --force to overwrite it",
+            path
+        )));
+    }
+    fs::write(path, EXAMPLE_CONFIG).map_err(|e| PortfolioError::IoError(e.to_string()))
+}
+
+impl Config {
+    // Fills unset endpoints with the DEFAULT_*_URL constants; overrides are left untouched
+    fn apply_default_urls(&mut self) {
+        for exchange in self
+            .exchanges
+            .iter_mut()
+            .chain(std::iter::once(&mut self.marketprovider))
+        {
+            if exchange.base_url.is_empty() {
+                exchange.base_url = match exchange.name.as_str() {
+                    "binance" => DEFAULT_BINANCE_URL.to_string(),
+                    "coingecko" => DEFAULT_COINGECKO_URL.to_string(),
+                    _ => String::new(),
+                };
+            }
+        }
+        let defaults = [
+            (&mut self.sentiment.api_url, DEFAULT_LUNARCRUSH_URL),
+            (&mut self.notification.twilio_api_url, DEFAULT_TWILIO_URL),
+            (
+                &mut self.notification.sendgrid_api_url,
+                DEFAULT_SENDGRID_URL,
+            ),
+        ];
+        for (url, default) in defaults {
+            if url.is_empty() {
+                *url = default.to_string();
+            }
+        }
+    }
+}
+
 pub fn load_config() -> Result<Config, PortfolioError> {
     let config_str = fs::read_to_string("config.toml")
         .map_err(|e| PortfolioError::ConfigError(e.to_string()))?;
-    let config: Config =
-        toml::from_str(&config_str).map_err(|e| PortfolioError::ConfigError(e.to_string()))?;
+    parse_config(&config_str)
+}
+
+// Parses and validates config.toml's contents
+fn parse_config(config_str: &str) -> Result<Config, PortfolioError> {
+    let mut config: Config =
+        toml::from_str(config_str).map_err(|e| PortfolioError::ConfigError(e.to_string()))?;
+    config.apply_default_urls();
+    config.display.rounding_mode = config.portfolio.rounding_mode.clone();
+    let max_holdings = config.portfolio.max_holdings;
+    if max_holdings > 0 {
+        let symbols: HashSet<String> = config
+            .portfolio
+            .holdings
+            .iter()
+            .map(|holding| holding.symbol.to_uppercase())
+            .collect();
+        if symbols.len() > max_holdings {
+            return Err(PortfolioError::ConfigError(format!(
+                "portfolio.holdings lists {} symbols, more than portfolio.max_holdings ({})",
+                symbols.len(),
+                max_holdings
+            )));
+        }
+    }
     Ok(config)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Interval {
+        #[serde(deserialize_with = "deserialize_secs")]
+        secs: u64,
+    }
+
+    fn secs(value: &str) -> Result<u64, toml::de::Error> {
+        toml::from_str::<Interval>(&format!("secs = {}", value)).map(|interval| interval.secs)
+    }
+
+    #[test]
+    fn durations_accept_numbers_and_humantime() {
+        assert_eq!(secs("300").unwrap(), 300);
+        assert_eq!(secs(r#""5m""#).unwrap(), 300);
+        assert_eq!(secs(r#""1h 30s""#).unwrap(), 3630);
+    }
+
+    #[test]
+    fn sub_second_durations_are_rejected() {
+        let error = secs(r#""500ms""#).unwrap_err().to_string();
+        assert!(error.contains("whole number of 1s"), "{}", error);
+        assert!(secs(r#""1.5s""#).is_err());
+    }
+
+    #[test]
+    fn example_config_loads() {
+        let config = parse_config(EXAMPLE_CONFIG).unwrap();
+        assert_eq!(config.default_command, "market");
+    }
+
+    #[test]
+    fn example_config_needs_force_to_overwrite() {
+        let path = std::env::temp_dir().join(format!("init-config-{}.toml", std::process::id()));
+        let path = path.to_str().unwrap();
+        fs::write(path, "# mine").unwrap();
+        let error = write_example_config(path, false).unwrap_err().to_string();
+        assert!(error.contains("--force"), "{}", error);
+        assert_eq!(fs::read_to_string(path).unwrap(), "# mine");
+
+        write_example_config(path, true).unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), EXAMPLE_CONFIG);
+        fs::remove_file(path).unwrap();
+    }
+}
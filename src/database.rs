@@ -1,14 +1,39 @@
+use crate::config::{DatabaseConfig, RedisConfig};
 use crate::errors::PortfolioError;
+use crate::logger::log_action;
+use crate::portfolio::Holding;
+use crate::stats::record_cache_lookup;
 use chrono::{DateTime, Utc};
 use redis::AsyncCommands;
-use sqlx::{postgres::PgPoolOptions, Pool, Postgres};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::{PgConnectOptions, PgConnection, PgPoolOptions};
+use sqlx::{Connection, Pool, Postgres};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::future::Future;
+use std::io::{ErrorKind, Write};
+use std::sync::Mutex;
+use std::time::Instant;
+use tokio::sync::OnceCell;
+use tokio::time::{sleep, timeout, Duration};
+
+// How often an instance running without Postgres tries to reconnect
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(60);
 
 pub struct Database {
-    pg_pool: Pool<Postgres>,
+    pg_pool: OnceCell<Pool<Postgres>>, // Empty while Postgres is unreachable and not required
+    postgres_url: String,
+    last_connect_attempt: Mutex<Option<Instant>>, // Spaces out reconnects while pg_pool is empty
+    trade_log: FileTradeLog, // Takes trades while there is no Postgres; replayed into it on reconnect
     redis_client: redis::Client,
+    key_prefix: String,
+    redis_retries: u32,
+    redis_timeout: Duration,
+    price_ttl: usize,
+    price_ttl_per_symbol: HashMap<String, usize>, // Keyed by upper-cased symbol
 }
 
-#[derive(sqlx::FromRow)]
+#[derive(sqlx::FromRow, Serialize, Deserialize)]
 pub struct Trade {
     pub id: i32,
     pub symbol: String,
@@ -16,19 +41,166 @@ pub struct Trade {
     pub price: f64,
     pub action: String,
     pub timestamp: DateTime<Utc>,
+    pub fee: f64,
+    #[serde(default)]
+    pub reason: String, // Trigger tag, e.g., "stop-loss"; empty for trades logged before it existed
+}
+
+// A triggered sell above portfolio.manual_approval_above_usd, held in Redis until `approve <id>`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PendingSell {
+    pub id: u64,
+    pub symbol: String,
+    pub quantity: f64,
+    pub price: f64, // Price when the sell triggered; the order itself fills at the market
+    pub approved: bool,
+    pub queued_at: DateTime<Utc>,
+}
+
+impl PendingSell {
+    pub fn value(&self) -> f64 {
+        self.quantity * self.price
+    }
+}
+
+// What the portfolio screen compares the next cycle against, kept in Redis across restarts
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ChangeBaseline {
+    pub value: f64,
+    pub prices: HashMap<String, f64>,
+    pub sentiments: HashMap<String, f64>,
+    #[serde(default)]
+    pub saved_at: DateTime<Utc>, // Baselines saved before this field read as the epoch, i.e. too old
+}
+
+// Trades as JSON lines in database.trade_log_path, for running without Postgres
+pub struct FileTradeLog {
+    path: String,
+}
+
+impl FileTradeLog {
+    pub fn new(path: &str) -> Self {
+        FileTradeLog {
+            path: path.to_string(),
+        }
+    }
+
+    pub fn append(&self, trade: &Trade) -> Result<(), PortfolioError> {
+        let line =
+            serde_json::to_string(trade).map_err(|e| PortfolioError::IoError(e.to_string()))?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| PortfolioError::IoError(format!("Failed to open {}: {}", self.path, e)))?;
+        writeln!(file, "{}", line)
+            .map_err(|e| PortfolioError::IoError(format!("Failed to write {}: {}", self.path, e)))
+    }
+
+    // Every logged trade, oldest first; empty when nothing has been written yet
+    pub fn read(&self) -> Result<Vec<Trade>, PortfolioError> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(PortfolioError::IoError(format!(
+                    "Failed to read {}: {}",
+                    self.path, e
+                )))
+            }
+        };
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                serde_json::from_str(line).map_err(|e| {
+                    PortfolioError::IoError(format!("Invalid trade in {}: {}", self.path, e))
+                })
+            })
+            .collect()
+    }
+
+    // Called once the trades are safely in Postgres
+    pub fn truncate(&self) -> Result<(), PortfolioError> {
+        match fs::write(&self.path, "") {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(PortfolioError::IoError(format!(
+                "Failed to truncate {}: {}",
+                self.path, e
+            ))),
+            _ => Ok(()),
+        }
+    }
 }
 
 impl Database {
-    pub async fn new(postgres_url: &str, redis_url: &str) -> Result<Self, PortfolioError> {
-        let pg_pool = PgPoolOptions::new()
-            .max_connections(5)
-            .connect(postgres_url)
-            .await
-            .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+    // `needs_postgres` is false for screens that only cache in Redis; they run degraded when
+    // Postgres is down whatever database.required says
+    pub async fn new(
+        database_config: &DatabaseConfig,
+        redis_config: &RedisConfig,
+        needs_postgres: bool,
+    ) -> Result<Self, PortfolioError> {
+        let required = database_config.required && needs_postgres;
+        let redis_client = Self::with_retries("Redis", database_config, || {
+            Self::connect_redis(redis_config)
+        })
+        .await?;
+        // Only a required Postgres is waited for; otherwise trades go to the file log until
+        // `pool` reconnects
+        let postgres_url = database_config.postgres_url.as_str();
+        let pg_pool = if required {
+            Some(
+                Self::with_retries("Postgres", database_config, || {
+                    Self::connect_postgres(postgres_url)
+                })
+                .await?,
+            )
+        } else {
+            match Self::connect_postgres(postgres_url).await {
+                Ok(pg_pool) => Some(pg_pool),
+                Err(e) => {
+                    let _ = log_action(
+                        &format!(
+                            "Postgres unavailable, continuing without it (trades go to {}): {}",
+                            database_config.trade_log_path, e
+                        ),
+                        None,
+                    );
+                    None
+                }
+            }
+        };
 
-        let redis_client = redis::Client::open(redis_url)
-            .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+        let trade_log = FileTradeLog::new(&database_config.trade_log_path);
+        if let Some(pg_pool) = &pg_pool {
+            Self::create_tables(pg_pool).await?;
+            Self::replay_trade_log(pg_pool, &trade_log).await?;
+        }
 
+        Ok(Database {
+            last_connect_attempt: Mutex::new(pg_pool.is_none().then(Instant::now)),
+            pg_pool: OnceCell::new_with(pg_pool),
+            postgres_url: database_config.postgres_url.clone(),
+            trade_log,
+            redis_client,
+            key_prefix: redis_config.key_prefix.clone(),
+            redis_retries: redis_config.op_retries,
+            redis_timeout: Duration::from_millis(redis_config.op_timeout_ms),
+            price_ttl: usize::try_from(redis_config.price_ttl_secs).unwrap_or(usize::MAX),
+            price_ttl_per_symbol: redis_config
+                .price_ttl_per_symbol
+                .iter()
+                .map(|(symbol, ttl)| {
+                    (
+                        symbol.to_uppercase(),
+                        usize::try_from(*ttl).unwrap_or(usize::MAX),
+                    )
+                })
+                .collect(),
+        })
+    }
+
+    async fn create_tables(pg_pool: &Pool<Postgres>) -> Result<(), PortfolioError> {
         // Initialize PostgreSQL table
         sqlx::query(
             r#"
@@ -42,28 +214,301 @@ impl Database {
             )
             "#,
         )
-        .execute(&pg_pool)
+        .execute(pg_pool)
         .await
         .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
 
-        Ok(Database {
-            pg_pool,
-            redis_client,
+        // Fee column added after the initial schema
+        sqlx::query(
+            r#"
+            ALTER TABLE trades ADD COLUMN IF NOT EXISTS fee DOUBLE PRECISION NOT NULL DEFAULT 0
+            "#,
+        )
+        .execute(pg_pool)
+        .await
+        .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+
+        // Trigger tag column, likewise added later; older rows keep an empty reason
+        sqlx::query(
+            r#"
+            ALTER TABLE trades ADD COLUMN IF NOT EXISTS reason VARCHAR NOT NULL DEFAULT ''
+            "#,
+        )
+        .execute(pg_pool)
+        .await
+        .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+
+        // Trades moved out by `prune --archive-trades`; they keep their ids and still count for
+        // the cost basis
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS trades_archive (
+                id INTEGER PRIMARY KEY,
+                symbol VARCHAR NOT NULL,
+                quantity DOUBLE PRECISION NOT NULL,
+                price DOUBLE PRECISION NOT NULL,
+                action VARCHAR NOT NULL,
+                timestamp TIMESTAMP WITH TIME ZONE NOT NULL,
+                fee DOUBLE PRECISION NOT NULL DEFAULT 0,
+                reason VARCHAR NOT NULL DEFAULT ''
+            )
+            "#,
+        )
+        .execute(pg_pool)
+        .await
+        .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS price_history (
+                id SERIAL PRIMARY KEY,
+                symbol VARCHAR NOT NULL,
+                price DOUBLE PRECISION NOT NULL,
+                timestamp TIMESTAMP WITH TIME ZONE NOT NULL
+            )
+            "#,
+        )
+        .execute(pg_pool)
+        .await
+        .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS holdings (
+                symbol VARCHAR PRIMARY KEY,
+                quantity DOUBLE PRECISION NOT NULL,
+                purchase_price DOUBLE PRECISION NOT NULL,
+                stop_loss DOUBLE PRECISION NOT NULL
+            )
+            "#,
+        )
+        .execute(pg_pool)
+        .await
+        .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+
+        // HODL flag added later; existing holdings keep their stop-loss
+        sqlx::query(
+            r#"
+            ALTER TABLE holdings ADD COLUMN IF NOT EXISTS stop_loss_enabled BOOLEAN NOT NULL DEFAULT TRUE
+            "#,
+        )
+        .execute(pg_pool)
+        .await
+        .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+
+        // Single row; its presence marks the portfolio as seeded
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS portfolio_state (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                cash DOUBLE PRECISION NOT NULL,
+                updated_at TIMESTAMP WITH TIME ZONE NOT NULL
+            )
+            "#,
+        )
+        .execute(pg_pool)
+        .await
+        .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+
+    // Runs `connect` until it succeeds or database.connect_retries retries have failed, doubling
+    // the delay after each; the last error is returned
+    async fn with_retries<T, F, Fut>(
+        what: &str,
+        database_config: &DatabaseConfig,
+        mut connect: F,
+    ) -> Result<T, PortfolioError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, PortfolioError>>,
+    {
+        let mut attempt = 0;
+        let mut delay = Duration::from_secs(database_config.connect_retry_delay_secs);
+        loop {
+            match connect().await {
+                Ok(connected) => return Ok(connected),
+                Err(e) if attempt < database_config.connect_retries => {
+                    attempt += 1;
+                    let _ = log_action(
+                        &format!(
+                            "{} connection failed (attempt {}/{}): {}. Retrying in {}s",
+                            what,
+                            attempt,
+                            database_config.connect_retries + 1,
+                            e,
+                            delay.as_secs()
+                        ),
+                        None,
+                    );
+                    sleep(delay).await;
+                    delay *= 2; // Exponential backoff
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    // One connection checks that Postgres is up: Pool::connect would keep retrying a refused
+    // connection for its whole acquire timeout, hiding a down Postgres behind a 30s stall
+    async fn connect_postgres(postgres_url: &str) -> Result<Pool<Postgres>, PortfolioError> {
+        let options: PgConnectOptions = postgres_url
+            .parse()
+            .map_err(|e: sqlx::Error| PortfolioError::DatabaseError(e.to_string()))?;
+        let probe = PgConnection::connect_with(&options)
+            .await
+            .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+        let _ = probe.close().await;
+        Ok(PgPoolOptions::new()
+            .max_connections(5)
+            .connect_lazy_with(options))
+    }
+
+    async fn connect_redis(redis_config: &RedisConfig) -> Result<redis::Client, PortfolioError> {
+        let redis_client = redis::Client::open(redis_config.url.as_str())
+            .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+
+        // Client::open doesn't connect, so PING to verify Redis is actually reachable
+        let mut conn = redis_client
+            .get_async_connection()
+            .await
+            .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+        redis::cmd("PING")
+            .query_async::<_, String>(&mut conn)
+            .await
+            .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+        Ok(redis_client)
+    }
+
+    // The pool, or None while Postgres is down; retried at most once per RECONNECT_INTERVAL
+    async fn pool(&self) -> Option<&Pool<Postgres>> {
+        if let Some(pg_pool) = self.pg_pool.get() {
+            return Some(pg_pool);
+        }
+        {
+            let mut last_attempt = self.last_connect_attempt.lock().unwrap();
+            if last_attempt.is_some_and(|last| last.elapsed() < RECONNECT_INTERVAL) {
+                return None;
+            }
+            *last_attempt = Some(Instant::now());
+        }
+        match self.reconnect().await {
+            Ok(pg_pool) => {
+                let _ = log_action("Reconnected to Postgres", None);
+                let _ = self.pg_pool.set(pg_pool);
+                self.pg_pool.get()
+            }
+            Err(e) => {
+                let _ = log_action(&format!("Postgres still unavailable: {}", e), None);
+                None
+            }
+        }
+    }
+
+    async fn reconnect(&self) -> Result<Pool<Postgres>, PortfolioError> {
+        let pg_pool = Self::connect_postgres(&self.postgres_url).await?;
+        Self::create_tables(&pg_pool).await?;
+        Self::replay_trade_log(&pg_pool, &self.trade_log).await?;
+        Ok(pg_pool)
+    }
+
+    // Moves trades logged to the file while Postgres was down into the trades table, in one
+    // transaction, then empties the file. Until that succeeds fetch_trades reads both
+    async fn replay_trade_log(
+        pg_pool: &Pool<Postgres>,
+        trade_log: &FileTradeLog,
+    ) -> Result<(), PortfolioError> {
+        let trades = trade_log.read()?;
+        if trades.is_empty() {
+            return Ok(());
+        }
+        let mut tx = pg_pool
+            .begin()
+            .await
+            .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+        for trade in &trades {
+            sqlx::query(
+                r#"
+                INSERT INTO trades (symbol, quantity, price, action, timestamp, fee, reason)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                "#,
+            )
+            .bind(&trade.symbol)
+            .bind(trade.quantity)
+            .bind(trade.price)
+            .bind(&trade.action)
+            .bind(trade.timestamp)
+            .bind(trade.fee)
+            .bind(&trade.reason)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+        }
+        tx.commit()
+            .await
+            .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+        trade_log.truncate()?;
+        let _ = log_action(
+            &format!("Replayed {} file-logged trades into Postgres", trades.len()),
+            None,
+        );
+        Ok(())
+    }
+
+    // Holdings state and price history have no file fallback
+    async fn postgres(&self) -> Result<&Pool<Postgres>, PortfolioError> {
+        self.pool().await.ok_or_else(|| {
+            PortfolioError::DatabaseError(
+                "Postgres is unavailable (database.required = false)".to_string(),
+            )
         })
     }
 
+    // Namespaces cache keys so several instances can share one Redis
+    fn cache_key(&self, kind: &str, symbol: &str) -> String {
+        if self.key_prefix.is_empty() {
+            format!("{}:{}", kind, symbol)
+        } else {
+            format!("{}:{}:{}", self.key_prefix, kind, symbol)
+        }
+    }
+
+    // e.g., "price:binance:USD:SUI", so sources and quote currencies never share an entry
+    fn price_key(&self, provider: &str, quote: &str, symbol: &str) -> String {
+        self.cache_key("price", &format!("{}:{}:{}", provider, quote, symbol))
+    }
+
+    // e.g., "sentiment:lunarcrush:SUI"; `kind` also covers the sentiment_last/_prev history
+    fn sentiment_key(&self, kind: &str, provider: &str, symbol: &str) -> String {
+        self.cache_key(kind, &format!("{}:{}", provider, symbol))
+    }
+
     pub async fn log_trade(
         &self,
         symbol: &str,
         quantity: f64,
         price: f64,
         action: &str,
+        fee: f64,
+        reason: &str,
     ) -> Result<(), PortfolioError> {
         let timestamp = Utc::now();
+        let Some(pg_pool) = self.pool().await else {
+            return self.trade_log.append(&Trade {
+                id: 0, // Assigned by line order when read back
+                symbol: symbol.to_string(),
+                quantity,
+                price,
+                action: action.to_string(),
+                timestamp,
+                fee,
+                reason: reason.to_string(),
+            });
+        };
         sqlx::query(
             r#"
-            INSERT INTO trades (symbol, quantity, price, action, timestamp)
-            VALUES ($1, $2, $3, $4, $5)
+            INSERT INTO trades (symbol, quantity, price, action, timestamp, fee, reason)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
             "#,
         )
         .bind(symbol)
@@ -71,86 +516,1218 @@ impl Database {
         .bind(price)
         .bind(action)
         .bind(timestamp)
-        .execute(&self.pg_pool)
+        .bind(fee)
+        .bind(reason)
+        .execute(pg_pool)
         .await
         .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
         Ok(())
     }
 
-    pub async fn get_cached_price(&self, symbol: &str) -> Result<Option<f64>, PortfolioError> {
-        let mut conn = self
-            .redis_client
-            .get_async_connection()
+    // Archived trades included, since every caller builds a cost basis from them
+    pub async fn fetch_trades(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Vec<Trade>, PortfolioError> {
+        let mut trades = match self.pool().await {
+            Some(pg_pool) => sqlx::query_as::<_, Trade>(
+                r#"
+                SELECT id, symbol, quantity, price, action, timestamp, fee, reason
+                FROM (
+                    SELECT id, symbol, quantity, price, action, timestamp, fee, reason
+                    FROM trades
+                    UNION ALL
+                    SELECT id, symbol, quantity, price, action, timestamp, fee, reason
+                    FROM trades_archive
+                ) AS all_trades
+                WHERE ($1::timestamptz IS NULL OR timestamp >= $1)
+                  AND ($2::timestamptz IS NULL OR timestamp < $2)
+                "#,
+            )
+            .bind(from)
+            .bind(to)
+            .fetch_all(pg_pool)
             .await
-            .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
-        let price: Option<f64> = conn
-            .get(format!("price:{}", symbol))
+            .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?,
+            None => Vec::new(),
+        };
+        // Trades still in the file: all of them while Postgres is down, or any a failed replay
+        // left behind. They are numbered on from the Postgres ids by line order
+        let first_id = trades.iter().map(|trade| trade.id).max().unwrap_or(0) + 1;
+        let mut logged = self.trade_log.read()?;
+        for (index, trade) in logged.iter_mut().enumerate() {
+            trade.id = first_id + index as i32;
+        }
+        logged.retain(|trade| {
+            from.is_none_or(|from| trade.timestamp >= from)
+                && to.is_none_or(|to| trade.timestamp < to)
+        });
+        trades.extend(logged);
+        trades.sort_by_key(|trade| (trade.timestamp, trade.id));
+        Ok(trades)
+    }
+
+    // None until save_portfolio has run once
+    pub async fn load_portfolio(&self) -> Result<Option<(Vec<Holding>, f64)>, PortfolioError> {
+        let cash: Option<(f64,)> = sqlx::query_as("SELECT cash FROM portfolio_state WHERE id = 1")
+            .fetch_optional(self.postgres().await?)
             .await
             .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
-        Ok(price)
+        let Some((cash,)) = cash else {
+            return Ok(None);
+        };
+        let holdings = sqlx::query_as::<_, Holding>(
+            r#"
+            SELECT symbol, quantity, purchase_price, stop_loss, stop_loss_enabled
+            FROM holdings
+            ORDER BY symbol
+            "#,
+        )
+        .fetch_all(self.postgres().await?)
+        .await
+        .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+        Ok(Some((holdings, cash)))
     }
 
-    pub async fn cache_price(&self, symbol: &str, price: f64) -> Result<(), PortfolioError> {
-        let mut conn = self
-            .redis_client
-            .get_async_connection()
+    // Replaces the stored holdings and cash in one transaction
+    pub async fn save_portfolio(
+        &self,
+        holdings: &[Holding],
+        cash: f64,
+    ) -> Result<(), PortfolioError> {
+        let mut tx = self
+            .postgres()
+            .await?
+            .begin()
             .await
             .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
-        conn.set_ex::<_, _, ()>(&format!("price:{}", symbol), price, 300) // Cache for 5 minutes
+        sqlx::query("DELETE FROM holdings")
+            .execute(&mut *tx)
             .await
             .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+        for holding in holdings {
+            sqlx::query(
+                r#"
+                INSERT INTO holdings (symbol, quantity, purchase_price, stop_loss, stop_loss_enabled)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+            )
+            .bind(&holding.symbol)
+            .bind(holding.quantity)
+            .bind(holding.purchase_price)
+            .bind(holding.stop_loss)
+            .bind(holding.stop_loss_enabled)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+        }
+        sqlx::query(
+            r#"
+            INSERT INTO portfolio_state (id, cash, updated_at)
+            VALUES (1, $1, $2)
+            ON CONFLICT (id) DO UPDATE SET cash = EXCLUDED.cash, updated_at = EXCLUDED.updated_at
+            "#,
+        )
+        .bind(cash)
+        .bind(Utc::now())
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+        tx.commit()
+            .await
+            .map_err(|e| PortfolioError::DatabaseError(e.to_string()))
+    }
+
+    // Price history is skipped without Postgres
+    pub async fn record_price(&self, symbol: &str, price: f64) -> Result<(), PortfolioError> {
+        let Some(pg_pool) = self.pool().await else {
+            return Ok(());
+        };
+        sqlx::query(
+            r#"
+            INSERT INTO price_history (symbol, price, timestamp)
+            VALUES ($1, $2, $3)
+            "#,
+        )
+        .bind(symbol)
+        .bind(price)
+        .bind(Utc::now())
+        .execute(pg_pool)
+        .await
+        .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
         Ok(())
     }
 
-    pub async fn get_cached_sentiment(&self, symbol: &str) -> Result<Option<f64>, PortfolioError> {
-        let mut conn = self
-            .redis_client
-            .get_async_connection()
+    pub async fn fetch_latest_prices(&self) -> Result<HashMap<String, f64>, PortfolioError> {
+        let rows: Vec<(String, f64)> = sqlx::query_as(
+            r#"
+            SELECT DISTINCT ON (symbol) symbol, price
+            FROM price_history
+            ORDER BY symbol, timestamp DESC
+            "#,
+        )
+        .fetch_all(self.postgres().await?)
+        .await
+        .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+        Ok(rows.into_iter().collect())
+    }
+
+    // Deletes price_history rows recorded before `cutoff`; returns how many were removed
+    pub async fn prune_price_history(&self, cutoff: DateTime<Utc>) -> Result<u64, PortfolioError> {
+        let result = sqlx::query("DELETE FROM price_history WHERE timestamp < $1")
+            .bind(cutoff)
+            .execute(self.postgres().await?)
             .await
             .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
-        let sentiment: Option<f64> = conn
-            .get(format!("sentiment:{}", symbol))
+        Ok(result.rows_affected())
+    }
+
+    // Moves trades before `cutoff` into trades_archive in one statement; returns how many moved.
+    // fetch_trades reads both tables, so archived trades still count for the cost basis
+    pub async fn archive_trades(&self, cutoff: DateTime<Utc>) -> Result<u64, PortfolioError> {
+        let result = sqlx::query(
+            r#"
+            WITH moved AS (
+                DELETE FROM trades WHERE timestamp < $1
+                RETURNING id, symbol, quantity, price, action, timestamp, fee, reason
+            )
+            INSERT INTO trades_archive (id, symbol, quantity, price, action, timestamp, fee, reason)
+            SELECT id, symbol, quantity, price, action, timestamp, fee, reason FROM moved
+            "#,
+        )
+        .bind(cutoff)
+        .execute(self.postgres().await?)
+        .await
+        .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+        Ok(result.rows_affected())
+    }
+
+    // Caching is best-effort: each Redis call is retried with a timeout, and a persistent
+    // failure reads as a cache miss so callers fall back to a live fetch
+    async fn best_effort<T, F, Fut>(&self, operation: &str, mut call: F) -> Option<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, PortfolioError>>,
+    {
+        let mut last_error = String::new();
+        for _ in 0..=self.redis_retries {
+            match timeout(self.redis_timeout, call()).await {
+                Ok(Ok(value)) => return Some(value),
+                Ok(Err(e)) => last_error = e.to_string(),
+                Err(_) => last_error = format!("timed out after {:?}", self.redis_timeout),
+            }
+        }
+        let _ = log_action(
+            &format!(
+                "Redis {} failed after {} attempts, skipping cache: {}",
+                operation,
+                self.redis_retries + 1,
+                last_error
+            ),
+            None,
+        );
+        None
+    }
+
+    async fn redis_connection(&self) -> Result<redis::aio::Connection, PortfolioError> {
+        self.redis_client
+            .get_async_connection()
             .await
-            .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
-        Ok(sentiment)
+            .map_err(|e| PortfolioError::DatabaseError(e.to_string()))
+    }
+
+    async fn redis_get(&self, key: &str) -> Result<Option<f64>, PortfolioError> {
+        let mut conn = self.redis_connection().await?;
+        conn.get(key)
+            .await
+            .map_err(|e| PortfolioError::DatabaseError(e.to_string()))
+    }
+
+    async fn redis_set_ex(&self, key: &str, value: f64, ttl: usize) -> Result<(), PortfolioError> {
+        let mut conn = self.redis_connection().await?;
+        conn.set_ex::<_, _, ()>(key, value, ttl)
+            .await
+            .map_err(|e| PortfolioError::DatabaseError(e.to_string()))
+    }
+
+    pub async fn get_cached_price(
+        &self,
+        provider: &str,
+        quote: &str,
+        symbol: &str,
+    ) -> Result<Option<f64>, PortfolioError> {
+        let key = self.price_key(provider, quote, symbol);
+        let cached = self
+            .best_effort("get price", || self.redis_get(&key))
+            .await
+            .flatten();
+        record_cache_lookup(cached.is_some());
+        Ok(cached)
+    }
+
+    pub async fn cache_price(
+        &self,
+        provider: &str,
+        quote: &str,
+        symbol: &str,
+        price: f64,
+    ) -> Result<(), PortfolioError> {
+        let key = self.price_key(provider, quote, symbol);
+        let ttl = self.price_ttl(symbol);
+        self.best_effort("cache price", || self.redis_set_ex(&key, price, ttl))
+            .await;
+        Ok(())
+    }
+
+    // redis.price_ttl_per_symbol for the symbol, else redis.price_ttl_secs
+    fn price_ttl(&self, symbol: &str) -> usize {
+        self.price_ttl_per_symbol
+            .get(&symbol.to_uppercase())
+            .copied()
+            .unwrap_or(self.price_ttl)
+    }
+
+    pub async fn get_cached_sentiment(
+        &self,
+        provider: &str,
+        symbol: &str,
+    ) -> Result<Option<f64>, PortfolioError> {
+        let key = self.sentiment_key("sentiment", provider, symbol);
+        let cached = self
+            .best_effort("get sentiment", || self.redis_get(&key))
+            .await
+            .flatten();
+        record_cache_lookup(cached.is_some());
+        Ok(cached)
     }
 
     pub async fn cache_sentiment(
         &self,
+        provider: &str,
         symbol: &str,
         sentiment: f64,
         ttl: u64,
     ) -> Result<(), PortfolioError> {
-        let mut conn = self
-            .redis_client
-            .get_async_connection()
-            .await
-            .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
         let ttl_usize: usize = ttl.try_into().map_err(|_| {
             PortfolioError::DatabaseError(format!("TTL value {} too large for usize", ttl))
         })?;
-        conn.set_ex::<_, _, ()>(format!("sentiment:{}", symbol), sentiment, ttl_usize)
+        self.best_effort("cache sentiment", || {
+            self.redis_cache_sentiment(provider, symbol, sentiment, ttl_usize)
+        })
+        .await;
+        Ok(())
+    }
+
+    async fn redis_cache_sentiment(
+        &self,
+        provider: &str,
+        symbol: &str,
+        sentiment: f64,
+        ttl: usize,
+    ) -> Result<(), PortfolioError> {
+        let mut conn = self.redis_connection().await?;
+        conn.set_ex::<_, _, ()>(
+            self.sentiment_key("sentiment", provider, symbol),
+            sentiment,
+            ttl,
+        )
+        .await
+        .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+
+        // Keep the last two fetched values without expiry so trends survive cache expiry
+        let last: Option<f64> = conn
+            .get(self.sentiment_key("sentiment_last", provider, symbol))
+            .await
+            .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+        if let Some(last) = last {
+            conn.set::<_, _, ()>(self.sentiment_key("sentiment_prev", provider, symbol), last)
+                .await
+                .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+        }
+        conn.set::<_, _, ()>(
+            self.sentiment_key("sentiment_last", provider, symbol),
+            sentiment,
+        )
+        .await
+        .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+
+    // Marks a symbol as recently sold so sentiment/stop-loss triggers don't resell it right away
+    pub async fn start_sell_cooldown(
+        &self,
+        symbol: &str,
+        cooldown_secs: u64,
+    ) -> Result<(), PortfolioError> {
+        let key = self.cache_key("sell_cooldown", symbol);
+        let ttl = usize::try_from(cooldown_secs).unwrap_or(usize::MAX);
+        self.best_effort("start sell cooldown", || {
+            self.redis_set_ex(&key, Utc::now().timestamp() as f64, ttl)
+        })
+        .await;
+        Ok(())
+    }
+
+    pub async fn in_sell_cooldown(&self, symbol: &str) -> Result<bool, PortfolioError> {
+        let key = self.cache_key("sell_cooldown", symbol);
+        Ok(self
+            .best_effort("check sell cooldown", || self.redis_get(&key))
+            .await
+            .flatten()
+            .is_some())
+    }
+
+    // When the current stop-loss breach was first seen; SET NX keeps the original timestamp across cycles
+    pub async fn stop_loss_breached_since(
+        &self,
+        symbol: &str,
+    ) -> Result<Option<DateTime<Utc>>, PortfolioError> {
+        let key = self.cache_key("stop_loss_breach", symbol);
+        let since = self
+            .best_effort("track stop-loss breach", || async {
+                let mut conn = self.redis_connection().await?;
+                conn.set_nx::<_, _, ()>(&key, Utc::now().timestamp())
+                    .await
+                    .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+                conn.get::<_, i64>(&key)
+                    .await
+                    .map_err(|e| PortfolioError::DatabaseError(e.to_string()))
+            })
+            .await;
+        Ok(since.and_then(|since| DateTime::from_timestamp(since, 0)))
+    }
+
+    pub async fn clear_stop_loss_breach(&self, symbol: &str) -> Result<(), PortfolioError> {
+        let key = self.cache_key("stop_loss_breach", symbol);
+        self.best_effort("clear stop-loss breach", || async {
+            let mut conn = self.redis_connection().await?;
+            conn.del::<_, ()>(&key)
+                .await
+                .map_err(|e| PortfolioError::DatabaseError(e.to_string()))
+        })
+        .await;
+        Ok(())
+    }
+
+    pub async fn get_previous_sentiment(
+        &self,
+        provider: &str,
+        symbol: &str,
+    ) -> Result<Option<f64>, PortfolioError> {
+        let key = self.sentiment_key("sentiment_prev", provider, symbol);
+        Ok(self
+            .best_effort("get previous sentiment", || self.redis_get(&key))
             .await
+            .flatten())
+    }
+
+    // No Redis expiry; the portfolio screen checks saved_at against notification.max_data_age_secs
+    pub async fn save_change_baseline(
+        &self,
+        baseline: &ChangeBaseline,
+    ) -> Result<(), PortfolioError> {
+        let key = self.cache_key("change_baseline", "portfolio");
+        let json = serde_json::to_string(baseline)
             .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+        self.best_effort("save change baseline", || async {
+            let mut conn = self.redis_connection().await?;
+            conn.set::<_, _, ()>(&key, &json)
+                .await
+                .map_err(|e| PortfolioError::DatabaseError(e.to_string()))
+        })
+        .await;
         Ok(())
     }
+
+    // None on first run, or when the stored baseline is unreadable
+    pub async fn load_change_baseline(&self) -> Result<Option<ChangeBaseline>, PortfolioError> {
+        let key = self.cache_key("change_baseline", "portfolio");
+        let json = self
+            .best_effort("load change baseline", || async {
+                let mut conn = self.redis_connection().await?;
+                conn.get::<_, Option<String>>(&key)
+                    .await
+                    .map_err(|e| PortfolioError::DatabaseError(e.to_string()))
+            })
+            .await
+            .flatten();
+        Ok(json.and_then(|json| match serde_json::from_str(&json) {
+            Ok(baseline) => Some(baseline),
+            Err(e) => {
+                let _ = log_action(&format!("Ignoring invalid change baseline: {}", e), None);
+                None
+            }
+        }))
+    }
+}
+
+// Approval queue; unlike the caches these calls aren't best-effort, as a lost entry could skip or repeat a sell
+impl Database {
+    // One pending sell per symbol: pending_sell:<id> holds the JSON, pending_sell_symbol:<symbol> its id
+    pub async fn queue_pending_sell(
+        &self,
+        symbol: &str,
+        quantity: f64,
+        price: f64,
+    ) -> Result<PendingSell, PortfolioError> {
+        let mut conn = self.redis_connection().await?;
+        let id: u64 = conn
+            .incr(self.cache_key("pending_sell_seq", "id"), 1)
+            .await
+            .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+        let pending = PendingSell {
+            id,
+            symbol: symbol.to_string(),
+            quantity,
+            price,
+            approved: false,
+            queued_at: Utc::now(),
+        };
+        self.write_pending_sell(&pending).await?;
+        conn.set::<_, _, ()>(self.cache_key("pending_sell_symbol", symbol), id)
+            .await
+            .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+        Ok(pending)
+    }
+
+    async fn write_pending_sell(&self, pending: &PendingSell) -> Result<(), PortfolioError> {
+        let json = serde_json::to_string(pending)
+            .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+        let mut conn = self.redis_connection().await?;
+        conn.set::<_, _, ()>(
+            self.cache_key("pending_sell", &pending.id.to_string()),
+            json,
+        )
+        .await
+        .map_err(|e| PortfolioError::DatabaseError(e.to_string()))
+    }
+
+    pub async fn pending_sell(&self, id: u64) -> Result<Option<PendingSell>, PortfolioError> {
+        let mut conn = self.redis_connection().await?;
+        let json: Option<String> = conn
+            .get(self.cache_key("pending_sell", &id.to_string()))
+            .await
+            .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+        json.map(|json| {
+            serde_json::from_str(&json).map_err(|e| {
+                PortfolioError::DatabaseError(format!("Invalid pending sell {}: {}", id, e))
+            })
+        })
+        .transpose()
+    }
+
+    pub async fn pending_sell_for(
+        &self,
+        symbol: &str,
+    ) -> Result<Option<PendingSell>, PortfolioError> {
+        let mut conn = self.redis_connection().await?;
+        let id: Option<u64> = conn
+            .get(self.cache_key("pending_sell_symbol", symbol))
+            .await
+            .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+        match id {
+            Some(id) => self.pending_sell(id).await,
+            None => Ok(None),
+        }
+    }
+
+    // Oldest first; the `approve` subcommand lists these when run without an id
+    pub async fn pending_sells(&self) -> Result<Vec<PendingSell>, PortfolioError> {
+        let mut conn = self.redis_connection().await?;
+        // SCAN rather than KEYS, which blocks Redis while it walks the whole keyspace
+        let mut keys: Vec<String> = Vec::new();
+        let mut scan = conn
+            .scan_match(self.cache_key("pending_sell", "*"))
+            .await
+            .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
+        while let Some(key) = scan.next_item().await {
+            keys.push(key);
+        }
+        let mut pending = Vec::new();
+        for key in keys {
+            let Some(id) = key.rsplit(':').next().and_then(|id| id.parse().ok()) else {
+                continue;
+            };
+            if let Some(sell) = self.pending_sell(id).await? {
+                pending.push(sell);
+            }
+        }
+        pending.sort_by_key(|sell| sell.id);
+        Ok(pending)
+    }
+
+    // Marks the sell approved; the portfolio screen executes it on its next check
+    pub async fn approve_pending_sell(
+        &self,
+        id: u64,
+    ) -> Result<Option<PendingSell>, PortfolioError> {
+        let Some(mut pending) = self.pending_sell(id).await? else {
+            return Ok(None);
+        };
+        pending.approved = true;
+        self.write_pending_sell(&pending).await?;
+        Ok(Some(pending))
+    }
+
+    pub async fn clear_pending_sell(&self, pending: &PendingSell) -> Result<(), PortfolioError> {
+        let mut conn = self.redis_connection().await?;
+        conn.del::<_, ()>(vec![
+            self.cache_key("pending_sell", &pending.id.to_string()),
+            self.cache_key("pending_sell_symbol", &pending.symbol),
+        ])
+        .await
+        .map_err(|e| PortfolioError::DatabaseError(e.to_string()))
+    }
 }
 
 // Add method to get TTL from Redis (new)
 impl Database {
     pub async fn get_cached_sentiment_ttl(
         &self,
+        provider: &str,
         symbol: &str,
     ) -> Result<Option<u64>, PortfolioError> {
-        let mut conn = self
-            .redis_client
-            .get_async_connection()
+        let key = self.sentiment_key("sentiment", provider, symbol);
+        let ttl = self
+            .best_effort("get sentiment TTL", || async {
+                let mut conn = self.redis_connection().await?;
+                conn.ttl::<_, Option<i64>>(&key)
+                    .await
+                    .map_err(|e| PortfolioError::DatabaseError(e.to_string()))
+            })
             .await
-            .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
-        let ttl: Option<i64> = conn
-            .ttl(format!("sentiment:{}", symbol))
+            .flatten();
+        // Redis answers -2 for a missing key and -1 for one without an expiry
+        Ok(match ttl {
+            Some(t) if t > 0 => Some(t as u64),
+            _ => None,
+        })
+    }
+}
+
+#[cfg(test)]
+impl Database {
+    // No Postgres, and a Redis that refuses connections, so trades go to the file log and every
+    // best-effort cache call misses straight away
+    pub fn offline(trade_log_path: &str) -> Self {
+        Database {
+            pg_pool: OnceCell::new(),
+            postgres_url: String::new(),
+            last_connect_attempt: Mutex::new(Some(Instant::now())),
+            trade_log: FileTradeLog::new(trade_log_path),
+            redis_client: redis::Client::open("redis://127.0.0.1:1").unwrap(),
+            key_prefix: String::new(),
+            redis_retries: 0,
+            redis_timeout: Duration::from_millis(200),
+            price_ttl: 300,
+            price_ttl_per_symbol: HashMap::new(),
+        }
+    }
+
+    // Points the cache and queues at a test Redis such as testing::mock_redis
+    pub fn with_redis(mut self, url: &str) -> Self {
+        self.redis_client = redis::Client::open(url).unwrap();
+        self.redis_timeout = Duration::from_secs(2);
+        self
+    }
+}
+
+// In-memory Redis for tests, speaking just enough RESP for the commands Database sends
+#[cfg(test)]
+pub mod testing {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::tcp::OwnedReadHalf;
+    use tokio::net::{TcpListener, TcpStream};
+
+    // Value and expiry per key
+    type Store = Arc<Mutex<HashMap<String, (String, Option<Instant>)>>>;
+
+    // Returns the server's redis:// URL; each call starts an empty store
+    pub async fn mock_redis() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("redis://{}", listener.local_addr().unwrap());
+        let store = Store::default();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, store.clone()));
+            }
+        });
+        url
+    }
+
+    async fn serve(stream: TcpStream, store: Store) {
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+        while let Some(args) = read_command(&mut reader).await {
+            let reply = execute(&args, &store);
+            if writer.write_all(reply.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    async fn read_line(reader: &mut BufReader<OwnedReadHalf>) -> Option<String> {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await.ok()? == 0 {
+            return None;
+        }
+        Some(line.trim_end().to_string())
+    }
+
+    // A command arrives as an array of bulk strings: *<count>, then $<length> and the bytes for each
+    async fn read_command(reader: &mut BufReader<OwnedReadHalf>) -> Option<Vec<String>> {
+        let count: usize = read_line(reader).await?.strip_prefix('*')?.parse().ok()?;
+        let mut args = Vec::with_capacity(count);
+        for _ in 0..count {
+            let length: usize = read_line(reader).await?.strip_prefix('$')?.parse().ok()?;
+            let mut arg = vec![0; length + 2];
+            reader.read_exact(&mut arg).await.ok()?;
+            arg.truncate(length);
+            args.push(String::from_utf8(arg).ok()?);
+        }
+        Some(args)
+    }
+
+    fn bulk(value: Option<&str>) -> String {
+        match value {
+            Some(value) => format!("${}\r\n{}\r\n", value.len(), value),
+            None => "$-1\r\n".to_string(),
+        }
+    }
+
+    // Only the trailing `*` glob that cache_key patterns use
+    fn matches(pattern: &str, key: &str) -> bool {
+        match pattern.strip_suffix('*') {
+            Some(prefix) => key.starts_with(prefix),
+            None => pattern == key,
+        }
+    }
+
+    fn execute(args: &[String], store: &Store) -> String {
+        let mut store = store.lock().unwrap();
+        let now = Instant::now();
+        store.retain(|_, (_, expires)| expires.is_none_or(|expires| expires > now));
+        let expiry = |secs: &str| {
+            secs.parse()
+                .ok()
+                .map(|secs| now + Duration::from_secs(secs))
+        };
+        match (args[0].to_uppercase().as_str(), &args[1..]) {
+            ("PING", _) => "+PONG\r\n".to_string(),
+            ("GET", [key]) => bulk(store.get(key).map(|(value, _)| value.as_str())),
+            ("SET", [key, value]) => {
+                store.insert(key.clone(), (value.clone(), None));
+                "+OK\r\n".to_string()
+            }
+            ("SETEX", [key, secs, value]) => {
+                store.insert(key.clone(), (value.clone(), expiry(secs)));
+                "+OK\r\n".to_string()
+            }
+            ("SETNX", [key, value]) => {
+                if store.contains_key(key) {
+                    ":0\r\n".to_string()
+                } else {
+                    store.insert(key.clone(), (value.clone(), None));
+                    ":1\r\n".to_string()
+                }
+            }
+            ("DEL", keys) => {
+                let removed = keys
+                    .iter()
+                    .filter(|key| store.remove(*key).is_some())
+                    .count();
+                format!(":{}\r\n", removed)
+            }
+            ("INCR" | "INCRBY", [key, by @ ..]) => {
+                let by: i64 = by.first().and_then(|by| by.parse().ok()).unwrap_or(1);
+                let entry = store.entry(key.clone()).or_insert(("0".to_string(), None));
+                let value = entry.0.parse::<i64>().unwrap_or(0) + by;
+                entry.0 = value.to_string();
+                format!(":{}\r\n", value)
+            }
+            ("TTL", [key]) => match store.get(key) {
+                None => ":-2\r\n".to_string(),
+                Some((_, None)) => ":-1\r\n".to_string(),
+                Some((_, Some(expires))) => {
+                    format!(":{}\r\n", (*expires - now).as_secs_f64().round())
+                }
+            },
+            // One pass returns every match, with cursor 0 to end the iteration
+            ("SCAN", [_cursor, options @ ..]) => {
+                let pattern = options
+                    .iter()
+                    .position(|option| option.eq_ignore_ascii_case("MATCH"))
+                    .and_then(|index| options.get(index + 1))
+                    .map_or("*", String::as_str);
+                let keys: Vec<&String> = store.keys().filter(|key| matches(pattern, key)).collect();
+                let mut reply = format!("*2\r\n{}*{}\r\n", bulk(Some("0")), keys.len());
+                for key in keys {
+                    reply.push_str(&bulk(Some(key)));
+                }
+                reply
+            }
+            _ => format!("-ERR unsupported command '{}'\r\n", args.join(" ")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade_log_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("crypto-portfolio-{}.jsonl", name));
+        let _ = fs::remove_file(&path);
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn baselines_without_a_timestamp_read_as_the_epoch() {
+        let baseline: ChangeBaseline =
+            serde_json::from_str(r#"{"value":100.0,"prices":{"SUI":2.0},"sentiments":{}}"#)
+                .unwrap();
+        assert_eq!(baseline.saved_at, DateTime::<Utc>::UNIX_EPOCH);
+    }
+
+    #[tokio::test]
+    async fn key_prefixes_keep_instances_apart() {
+        let redis = testing::mock_redis().await;
+        let mut prod = Database::offline(&trade_log_path("prefix-prod")).with_redis(&redis);
+        prod.key_prefix = "prod".to_string();
+        let mut dev = Database::offline(&trade_log_path("prefix-dev")).with_redis(&redis);
+        dev.key_prefix = "dev".to_string();
+        prod.cache_price("binance", "USD", "SUI", 2.0)
             .await
-            .map_err(|e| PortfolioError::DatabaseError(e.to_string()))?;
-        Ok(ttl.map(|t| t as u64))
+            .unwrap();
+        assert_eq!(
+            dev.get_cached_price("binance", "USD", "SUI").await.unwrap(),
+            None
+        );
+        dev.cache_price("binance", "USD", "SUI", 1.5).await.unwrap();
+        assert_eq!(
+            prod.get_cached_price("binance", "USD", "SUI")
+                .await
+                .unwrap(),
+            Some(2.0)
+        );
+        assert_eq!(
+            prod.price_key("binance", "USD", "SUI"),
+            "prod:price:binance:USD:SUI"
+        );
+    }
+
+    #[tokio::test]
+    async fn quote_currencies_are_cached_apart() {
+        let db = Database::offline(&trade_log_path("quote-keys"))
+            .with_redis(&testing::mock_redis().await);
+        assert_ne!(
+            db.price_key("binance", "USD", "SUI"),
+            db.price_key("binance", "EUR", "SUI")
+        );
+        db.cache_price("binance", "USD", "SUI", 2.0).await.unwrap();
+        db.cache_price("binance", "EUR", "SUI", 1.8).await.unwrap();
+        let cached = |quote| db.get_cached_price("binance", quote, "SUI");
+        assert_eq!(cached("USD").await.unwrap(), Some(2.0));
+        assert_eq!(cached("EUR").await.unwrap(), Some(1.8));
+    }
+
+    #[tokio::test]
+    async fn a_redis_outage_reads_as_a_cache_miss() {
+        // Accepts connections but never answers, so every call runs into the timeout
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("redis://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                held.push(socket);
+            }
+        });
+        let mut hanging = Database::offline(&trade_log_path("redis-hang")).with_redis(&url);
+        hanging.redis_timeout = Duration::from_millis(100);
+        hanging.redis_retries = 1;
+        let refused = Database::offline(&trade_log_path("redis-down"));
+        for db in [hanging, refused] {
+            db.cache_price("binance", "USD", "SUI", 2.0).await.unwrap();
+            assert_eq!(
+                db.get_cached_price("binance", "USD", "SUI").await.unwrap(),
+                None
+            );
+            db.cache_sentiment("lunarcrush", "SUI", 0.5, 60)
+                .await
+                .unwrap();
+            assert_eq!(
+                db.get_cached_sentiment("lunarcrush", "SUI").await.unwrap(),
+                None
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn pending_sells_are_listed_oldest_first() {
+        let db = Database::offline(&trade_log_path("pending-scan"))
+            .with_redis(&testing::mock_redis().await);
+        for symbol in ["SUI", "PHA"] {
+            db.queue_pending_sell(symbol, 10.0, 2.0).await.unwrap();
+        }
+        let symbols: Vec<String> = db
+            .pending_sells()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|sell| sell.symbol)
+            .collect();
+        assert_eq!(symbols, ["SUI", "PHA"]);
+    }
+
+    #[tokio::test]
+    async fn per_symbol_price_ttls_override_the_global_one() {
+        let mut db = Database::offline(&trade_log_path("price-ttl"))
+            .with_redis(&testing::mock_redis().await);
+        db.price_ttl_per_symbol.insert("SUI".to_string(), 30);
+        db.cache_price("binance", "USD", "sui", 2.0).await.unwrap();
+        db.cache_price("binance", "USD", "PHA", 0.1).await.unwrap();
+        let mut conn = db.redis_connection().await.unwrap();
+        for (symbol, ttl) in [("sui", 30), ("PHA", 300)] {
+            let key = db.price_key("binance", "USD", symbol);
+            assert_eq!(conn.ttl::<_, i64>(&key).await.unwrap(), ttl);
+        }
+
+        // Neither an expiry-less key nor a missing one reads as a TTL
+        let key = db.sentiment_key("sentiment", "lunarcrush", "SUI");
+        conn.set::<_, _, ()>(&key, 0.5).await.unwrap();
+        assert_eq!(
+            db.get_cached_sentiment_ttl("lunarcrush", "SUI")
+                .await
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            db.get_cached_sentiment_ttl("lunarcrush", "PHA")
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn trades_fall_back_to_the_file_log() {
+        let db = Database::offline(&trade_log_path("file-log"));
+        db.log_trade("SUI", 10.0, 2.0, "sell", 0.02, "stop-loss")
+            .await
+            .unwrap();
+        db.log_trade("PHA", 50.0, 0.1, "buy", 0.0, "manual")
+            .await
+            .unwrap();
+        let trades = db.fetch_trades(None, None).await.unwrap();
+        let logged: Vec<(i32, &str)> = trades
+            .iter()
+            .map(|trade| (trade.id, trade.symbol.as_str()))
+            .collect();
+        assert_eq!(logged, vec![(1, "SUI"), (2, "PHA")]);
+
+        let later = Utc::now() + chrono::Duration::minutes(1);
+        assert!(db.fetch_trades(Some(later), None).await.unwrap().is_empty());
+        assert_eq!(db.fetch_trades(None, Some(later)).await.unwrap().len(), 2);
+    }
+
+    // Bound and dropped straight away, so connecting to it is refused
+    fn closed_port_url(scheme: &str) -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("{}://{}", scheme, listener.local_addr().unwrap())
+    }
+
+    fn connect_config(postgres_url: &str, required: bool, trade_log_path: &str) -> DatabaseConfig {
+        toml::from_str(&format!(
+            r#"
+            postgres_url = "{}"
+            required = {}
+            connect_retries = 2
+            connect_retry_delay_secs = 1
+            trade_log_path = "{}"
+            "#,
+            postgres_url, required, trade_log_path
+        ))
+        .unwrap()
+    }
+
+    fn redis_config(url: &str) -> RedisConfig {
+        toml::from_str(&format!("url = \"{}\"", url)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn optional_postgres_falls_back_without_retrying() {
+        let path = trade_log_path("optional-postgres");
+        let config = connect_config(&closed_port_url("postgresql"), false, &path);
+        let redis = redis_config(&testing::mock_redis().await);
+        let started = Instant::now();
+        let db = Database::new(&config, &redis, true).await.unwrap();
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(db.pg_pool.get().is_none());
+        db.log_trade("SUI", 10.0, 2.0, "sell", 0.02, "stop-loss")
+            .await
+            .unwrap();
+        assert_eq!(db.trade_log.read().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn connects_once_an_attempt_succeeds() {
+        let mut config = connect_config("", true, &trade_log_path("retries"));
+        config.connect_retry_delay_secs = 0;
+        let attempts = std::cell::Cell::new(0);
+        let connect = |succeed_on| {
+            attempts.set(0);
+            let attempts = &attempts;
+            move || {
+                attempts.set(attempts.get() + 1);
+                let attempt = attempts.get();
+                async move {
+                    if attempt == succeed_on {
+                        Ok(attempt)
+                    } else {
+                        Err(PortfolioError::DatabaseError(format!(
+                            "refused #{}",
+                            attempt
+                        )))
+                    }
+                }
+            }
+        };
+        let connected = Database::with_retries("Postgres", &config, connect(3)).await;
+        assert_eq!((connected.unwrap(), attempts.get()), (3, 3));
+
+        // connect_retries = 2 gives up after the third attempt, with its error
+        let error = Database::with_retries("Postgres", &config, connect(4))
+            .await
+            .unwrap_err();
+        assert_eq!(attempts.get(), 3);
+        assert!(error.to_string().contains("refused #3"), "{}", error);
+
+        // A required Postgres that never comes up fails startup
+        let config = DatabaseConfig {
+            postgres_url: closed_port_url("postgresql"),
+            ..config
+        };
+        let redis = redis_config(&testing::mock_redis().await);
+        assert!(Database::new(&config, &redis, true).await.is_err());
+    }
+
+    // Needs a scratch database, e.g. TEST_POSTGRES_URL=postgresql://postgres@localhost/postgres;
+    // run with `cargo test -- --ignored`
+    fn test_postgres_url() -> String {
+        std::env::var("TEST_POSTGRES_URL").expect("TEST_POSTGRES_URL is not set")
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn reconnecting_replays_the_file_log() {
+        let path = trade_log_path("replay");
+        let mut db = Database::offline(&path);
+        db.log_trade("REPLAYTEST", 10.0, 2.0, "sell", 0.02, "stop-loss")
+            .await
+            .unwrap();
+        assert_eq!(db.trade_log.read().unwrap().len(), 1);
+
+        // Postgres comes back, and the next call that needs it reconnects
+        db.postgres_url = test_postgres_url();
+        db.last_connect_attempt = Mutex::new(None);
+        let trades = db.fetch_trades(None, None).await.unwrap();
+        let replayed: Vec<&Trade> = trades
+            .iter()
+            .filter(|trade| trade.symbol == "REPLAYTEST")
+            .collect();
+        assert_eq!(replayed.len(), 1);
+        assert_eq!(replayed[0].reason, "stop-loss");
+        assert!(db.trade_log.read().unwrap().is_empty());
+
+        sqlx::query("DELETE FROM trades WHERE symbol = 'REPLAYTEST'")
+            .execute(db.pool().await.unwrap())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn pruning_removes_only_old_prices() {
+        let mut db = Database::offline(&trade_log_path("prune"));
+        db.postgres_url = test_postgres_url();
+        db.last_connect_attempt = Mutex::new(None);
+        let pg_pool = db.postgres().await.unwrap();
+        let now = Utc::now();
+        for days in [100, 10] {
+            sqlx::query(
+                "INSERT INTO price_history (symbol, price, timestamp) VALUES ('PRUNETEST', 2, $1)",
+            )
+            .bind(now - chrono::Duration::days(days))
+            .execute(pg_pool)
+            .await
+            .unwrap();
+        }
+
+        let cutoff = now - chrono::Duration::days(90);
+        assert!(db.prune_price_history(cutoff).await.unwrap() >= 1);
+        let kept: Vec<(DateTime<Utc>,)> =
+            sqlx::query_as("SELECT timestamp FROM price_history WHERE symbol = 'PRUNETEST'")
+                .fetch_all(pg_pool)
+                .await
+                .unwrap();
+        assert_eq!(kept.len(), 1);
+        assert!(kept[0].0 >= cutoff);
+
+        sqlx::query("DELETE FROM price_history WHERE symbol = 'PRUNETEST'")
+            .execute(pg_pool)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn archiving_moves_only_old_trades() {
+        let mut db = Database::offline(&trade_log_path("archive"));
+        db.postgres_url = test_postgres_url();
+        db.last_connect_attempt = Mutex::new(None);
+        let pg_pool = db.postgres().await.unwrap();
+        let now = Utc::now();
+        for days in [100, 10] {
+            sqlx::query(
+                r#"
+                INSERT INTO trades (symbol, quantity, price, action, timestamp, fee, reason)
+                VALUES ('ARCHIVETEST', 1, 2, 'buy', $1, 0.01, 'manual')
+                "#,
+            )
+            .bind(now - chrono::Duration::days(days))
+            .execute(pg_pool)
+            .await
+            .unwrap();
+        }
+
+        let cutoff = now - chrono::Duration::days(90);
+        assert!(db.archive_trades(cutoff).await.unwrap() >= 1);
+        let kept: Vec<(DateTime<Utc>,)> =
+            sqlx::query_as("SELECT timestamp FROM trades WHERE symbol = 'ARCHIVETEST'")
+                .fetch_all(pg_pool)
+                .await
+                .unwrap();
+        assert_eq!(kept.len(), 1);
+        assert!(kept[0].0 >= cutoff);
+        let archived: Vec<(DateTime<Utc>, f64, String)> = sqlx::query_as(
+            "SELECT timestamp, fee, reason FROM trades_archive WHERE symbol = 'ARCHIVETEST'",
+        )
+        .fetch_all(pg_pool)
+        .await
+        .unwrap();
+        assert_eq!(archived.len(), 1);
+        assert!(archived[0].0 < cutoff);
+        assert_eq!((archived[0].1, archived[0].2.as_str()), (0.01, "manual"));
+
+        // Both still feed the cost basis
+        let trades = db.fetch_trades(None, None).await.unwrap();
+        assert_eq!(
+            trades
+                .iter()
+                .filter(|trade| trade.symbol == "ARCHIVETEST")
+                .count(),
+            2
+        );
+
+        for table in ["trades", "trades_archive"] {
+            sqlx::query(&format!(
+                "DELETE FROM {} WHERE symbol = 'ARCHIVETEST'",
+                table
+            ))
+            .execute(pg_pool)
+            .await
+            .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn saved_portfolio_survives_a_restart() {
+        let holding = |symbol: &str, quantity: f64, stop_loss_enabled: bool| Holding {
+            symbol: symbol.to_string(),
+            quantity,
+            purchase_price: 1.5,
+            stop_loss: 1.2,
+            stop_loss_enabled,
+        };
+        let config = connect_config(&test_postgres_url(), true, &trade_log_path("state"));
+        let redis = redis_config(&testing::mock_redis().await);
+        let db = Database::new(&config, &redis, true).await.unwrap();
+        db.save_portfolio(
+            &[holding("SUI", 10.0, true), holding("PHA", 250.0, false)],
+            42.5,
+        )
+        .await
+        .unwrap();
+        drop(db);
+
+        // A fresh connection, as after a restart, reads back what was saved
+        let db = Database::new(&config, &redis, true).await.unwrap();
+        let (holdings, cash) = db.load_portfolio().await.unwrap().unwrap();
+        assert_eq!(cash, 42.5);
+        let loaded: Vec<(&str, f64, bool)> = holdings
+            .iter()
+            .map(|h| (h.symbol.as_str(), h.quantity, h.stop_loss_enabled))
+            .collect();
+        assert_eq!(loaded, vec![("PHA", 250.0, false), ("SUI", 10.0, true)]);
+    }
+
+    #[tokio::test]
+    async fn sell_reasons_are_kept_in_the_file_log() {
+        let path = trade_log_path("reasons");
+        let db = Database::offline(&path);
+        db.log_trade("SUI", 10.0, 2.0, "sell", 0.02, "stop-loss $1.20")
+            .await
+            .unwrap();
+        db.log_trade("PHA", 5.0, 0.1, "sell", 0.0, "manual approval")
+            .await
+            .unwrap();
+
+        let trades = Database::offline(&path)
+            .fetch_trades(None, None)
+            .await
+            .unwrap();
+        let reasons: Vec<(&str, &str)> = trades
+            .iter()
+            .map(|trade| (trade.symbol.as_str(), trade.reason.as_str()))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![("SUI", "stop-loss $1.20"), ("PHA", "manual approval")]
+        );
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn sell_reasons_are_stored_in_postgres() {
+        let config = connect_config(&test_postgres_url(), true, &trade_log_path("pg-reasons"));
+        let redis = redis_config(&testing::mock_redis().await);
+        let db = Database::new(&config, &redis, true).await.unwrap();
+        db.log_trade(
+            "REASONTEST",
+            10.0,
+            2.0,
+            "sell",
+            0.02,
+            "sentiment 0.21 below 0.30",
+        )
+        .await
+        .unwrap();
+        let pg_pool = db.pool().await.unwrap();
+        let reasons: Vec<(String,)> =
+            sqlx::query_as("SELECT reason FROM trades WHERE symbol = 'REASONTEST'")
+                .fetch_all(pg_pool)
+                .await
+                .unwrap();
+        assert_eq!(reasons, vec![("sentiment 0.21 below 0.30".to_string(),)]);
+
+        sqlx::query("DELETE FROM trades WHERE symbol = 'REASONTEST'")
+            .execute(pg_pool)
+            .await
+            .unwrap();
     }
 }
@@ -1,16 +1,304 @@
-use crate::database::Database;
+use crate::config::{DisplayConfig, SentimentBands, SentimentConfig};
+use crate::database::{Database, PendingSell};
 use crate::errors::PortfolioError;
-use crate::exchange::{DetailedSentiment, SentimentProvider};
-use crate::portfolio::Portfolio;
-use comfy_table::{Cell, Color, Table};
-use std::collections::HashMap;
+use crate::exchange::{
+    DetailedSentiment, NetworkEngagement, SentimentDate, SentimentProvider, Theme as SentimentTheme,
+};
+use crate::logger::{display_now, to_display_zone, SCREEN};
+use crate::portfolio::{format_sentiment, rounding_strategy, Discrepancy, Holding, Portfolio};
+use crate::stats::{ProviderStats, Stats};
+use chrono::NaiveDate;
+use comfy_table::presets::{ASCII_FULL, ASCII_MARKDOWN, NOTHING, UTF8_FULL};
+use comfy_table::{Attribute, Cell, Color, ContentArrangement, Table};
+use futures::future::join_all;
+use indicatif::{ProgressBar, ProgressStyle};
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+use std::io::IsTerminal;
+use std::time::Duration;
+use tokio::sync::Semaphore;
 
-pub fn display_portfolio(
+// Maps display.table_style onto a comfy_table preset; unknown styles keep the ASCII default
+pub fn styled_table(style: &str) -> Table {
+    let preset = match style {
+        "utf8_full" => UTF8_FULL,
+        "markdown" => ASCII_MARKDOWN,
+        "borderless" => NOTHING,
+        _ => ASCII_FULL,
+    };
+    let mut table = Table::new();
+    table.load_preset(preset);
+    table
+}
+
+// Tables wrap to the terminal width, or to display.table_width when configured
+pub fn new_table(display: &DisplayConfig) -> Table {
+    let mut table = styled_table(&display.table_style);
+    table.set_content_arrangement(ContentArrangement::Dynamic);
+    if let Some(width) = display.table_width {
+        table.set_width(width);
+    }
+    table
+}
+
+// Spinner for a cycle's fetch phase; hidden when piped or when screens share one terminal
+pub fn fetch_progress(total: usize, display: &DisplayConfig) -> ProgressBar {
+    if !shows_progress(display, std::io::stdout().is_terminal()) {
+        return ProgressBar::hidden();
+    }
+    let progress = ProgressBar::new(total as u64);
+    progress.set_style(
+        ProgressStyle::with_template("{spinner} Fetching {pos}/{len} symbols...")
+            .unwrap_or_else(|_| ProgressStyle::default_spinner()),
+    );
+    progress.enable_steady_tick(Duration::from_millis(100));
+    progress
+}
+
+fn shows_progress(display: &DisplayConfig, interactive: bool) -> bool {
+    let shared_terminal = SCREEN.try_with(|_| ()).is_ok();
+    display.show_progress && interactive && !shared_terminal
+}
+
+// Configured places, or adaptive so sub-dollar and sub-cent tokens stay readable
+pub fn price_decimals(price: f64, display: &DisplayConfig) -> usize {
+    display.price_decimals.unwrap_or({
+        let magnitude = price.abs();
+        if magnitude >= 1.0 || magnitude == 0.0 {
+            2
+        } else if magnitude >= 0.01 {
+            4
+        } else {
+            6
+        }
+    })
+}
+
+pub fn format_price(price: f64, display: &DisplayConfig) -> String {
+    format_money(price, price_decimals(price, display), display)
+}
+
+pub fn format_value(value: f64, display: &DisplayConfig) -> String {
+    format_money(value, display.value_decimals, display)
+}
+
+// Rounded with portfolio.rounding_mode rather than the formatter's own rounding
+fn format_money(amount: f64, decimals: usize, display: &DisplayConfig) -> String {
+    match Decimal::from_f64(amount) {
+        Some(amount) => format!(
+            "${:.*}",
+            decimals,
+            amount
+                .round_dp_with_strategy(decimals as u32, rounding_strategy(&display.rounding_mode))
+        ),
+        None => format!("${:.*}", decimals, amount),
+    }
+}
+
+// Percentages everywhere read "+3.25%", "-3.25%" or "0.00%" (no sign once rounded to zero)
+pub fn format_percent(value: f64, decimals: usize) -> String {
+    let rounded = format!("{:.*}", decimals, value.abs());
+    if rounded.chars().all(|c| c == '0' || c == '.') {
+        format!("{}%", rounded)
+    } else {
+        format!("{:+.*}%", decimals, value)
+    }
+}
+
+// What a colored value means; the Theme decides how each tone looks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tone {
+    Positive,
+    MildPositive,
+    Neutral,
+    Caution,
+    Negative,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    pub positive: Option<Color>,
+    pub mild_positive: Option<Color>,
+    pub neutral: Option<Color>,
+    pub caution: Option<Color>,
+    pub negative: Option<Color>,
+}
+
+impl Theme {
+    // display.color_theme, or "mono" when use_colors is off; unknown names keep "classic"
+    pub fn from_config(display: &DisplayConfig) -> Self {
+        let name = if display.use_colors {
+            display.color_theme.as_str()
+        } else {
+            "mono"
+        };
+        match name {
+            "mono" => Theme {
+                positive: None,
+                mild_positive: None,
+                neutral: None,
+                caution: None,
+                negative: None,
+            },
+            // Okabe-Ito blue/orange, distinguishable with red-green color blindness
+            "colorblind" => Theme {
+                positive: Some(Color::Rgb {
+                    r: 0,
+                    g: 114,
+                    b: 178,
+                }),
+                mild_positive: Some(Color::Rgb {
+                    r: 86,
+                    g: 180,
+                    b: 233,
+                }),
+                neutral: None,
+                caution: Some(Color::Rgb {
+                    r: 240,
+                    g: 228,
+                    b: 66,
+                }),
+                negative: Some(Color::Rgb {
+                    r: 230,
+                    g: 159,
+                    b: 0,
+                }),
+            },
+            _ => Theme {
+                positive: Some(Color::Green),
+                mild_positive: Some(Color::DarkGreen),
+                neutral: None,
+                caution: Some(Color::DarkYellow),
+                negative: Some(Color::Red),
+            },
+        }
+    }
+
+    pub fn color(&self, tone: Tone) -> Option<Color> {
+        match tone {
+            Tone::Positive => self.positive,
+            Tone::MildPositive => self.mild_positive,
+            Tone::Neutral => self.neutral,
+            Tone::Caution => self.caution,
+            Tone::Negative => self.negative,
+        }
+    }
+
+    pub fn cell(&self, text: impl ToString, tone: Tone) -> Cell {
+        let cell = Cell::new(text.to_string());
+        match self.color(tone) {
+            Some(color) => cell.fg(color),
+            None => cell,
+        }
+    }
+}
+
+// Gains positive, losses negative, flat moves neutral
+pub fn change_cell(text: String, change: f64, theme: &Theme) -> Cell {
+    let tone = if change > 0.0 {
+        Tone::Positive
+    } else if change < 0.0 {
+        Tone::Negative
+    } else {
+        Tone::Neutral
+    };
+    theme.cell(text, tone)
+}
+
+pub fn percent_cell(value: f64, decimals: usize, theme: &Theme) -> Cell {
+    change_cell(format_percent(value, decimals), value, theme)
+}
+
+// Relative for prices, absolute below 1.0 (sentiment scores), so rounding noise isn't a change
+const DIFF_EPSILON: f64 = 1e-6;
+
+pub fn moved(previous: Option<f64>, current: Option<f64>) -> bool {
+    match (previous, current) {
+        (Some(previous), Some(current)) => {
+            (current - previous).abs() > DIFF_EPSILON * previous.abs().max(current.abs()).max(1.0)
+        }
+        (previous, current) => previous.is_some() != current.is_some(),
+    }
+}
+
+// Symbols whose price or sentiment moved since the previous cycle; new symbols count as changed
+pub fn changed_symbols(
+    previous_prices: &HashMap<String, f64>,
+    prices: &HashMap<String, f64>,
+    previous_sentiments: &HashMap<String, f64>,
+    sentiments: &HashMap<String, f64>,
+) -> HashSet<String> {
+    prices
+        .keys()
+        .chain(sentiments.keys())
+        .filter(|symbol| {
+            moved(
+                previous_prices.get(*symbol).copied(),
+                prices.get(*symbol).copied(),
+            ) || moved(
+                previous_sentiments.get(*symbol).copied(),
+                sentiments.get(*symbol).copied(),
+            )
+        })
+        .cloned()
+        .collect()
+}
+
+// --diff mode: changed rows in bold, unchanged rows dimmed (needs display.use_colors)
+pub fn diff_row(row: Vec<Cell>, changed: bool, use_colors: bool) -> Vec<Cell> {
+    if !use_colors {
+        return row;
+    }
+    let attribute = if changed {
+        Attribute::Bold
+    } else {
+        Attribute::Dim
+    };
+    row.into_iter()
+        .map(|cell| cell.add_attribute(attribute))
+        .collect()
+}
+
+// display.holdings_order; ties (and unpriced holdings when ordering by value) fall back to the symbol
+fn ordered_holdings<'a>(
+    holdings: &'a [Holding],
+    prices: &HashMap<String, f64>,
+    order: &str,
+) -> Vec<&'a Holding> {
+    let mut ordered: Vec<&Holding> = holdings.iter().collect();
+    match order {
+        "symbol" => ordered.sort_by(|a, b| a.symbol.cmp(&b.symbol)),
+        "value" => {
+            let value = |holding: &Holding| {
+                prices
+                    .get(&holding.symbol)
+                    .map(|price| holding.quantity * price)
+                    .filter(|value| !value.is_nan())
+                    .unwrap_or(0.0)
+            };
+            ordered.sort_by(|a, b| {
+                value(b)
+                    .total_cmp(&value(a))
+                    .then_with(|| a.symbol.cmp(&b.symbol))
+            });
+        }
+        _ => {}
+    }
+    ordered
+}
+
+// The portfolio table with its heading, and the paused banner while trading is off
+pub fn render_portfolio(
     portfolio: &Portfolio,
     total_value: f64,
+    prices: &HashMap<String, f64>,
     sentiments: &HashMap<String, f64>,
-) {
-    let mut table = Table::new();
+    changed: Option<&HashSet<String>>,
+    display: &DisplayConfig,
+) -> String {
+    let mut table = new_table(display);
     table.set_header(vec![
         "Symbol",
         "Quantity",
@@ -19,163 +307,503 @@ pub fn display_portfolio(
         "Current Value",
         "Sentiment",
     ]);
-    for holding in &portfolio.holdings {
-        let current_value = holding.quantity * sentiments.get(&holding.symbol).unwrap_or(&0.0);
-        table.add_row(vec![
-            holding.symbol.clone(),
-            format!("{:.2}", holding.quantity),
-            format!("${:.2}", holding.purchase_price),
-            format!("${:.2}", holding.stop_loss),
-            format!("${:.2}", current_value),
-            format!("{:.2}", sentiments.get(&holding.symbol).unwrap_or(&0.5)),
-        ]);
+    for holding in ordered_holdings(&portfolio.holdings, prices, &display.holdings_order) {
+        let current_value = prices
+            .get(&holding.symbol)
+            .map(|price| format_value(holding.quantity * price, display))
+            .unwrap_or_else(|| "N/A".to_string());
+        let row = vec![
+            Cell::new(&holding.symbol),
+            Cell::new(format!("{:.2}", holding.quantity)),
+            Cell::new(format_price(holding.purchase_price, display)),
+            Cell::new(if holding.stop_loss_enabled {
+                format_price(holding.stop_loss, display)
+            } else {
+                "HODL".to_string()
+            }),
+            Cell::new(current_value),
+            Cell::new(format_sentiment(sentiments.get(&holding.symbol).copied())),
+        ];
+        match changed {
+            Some(changed) => table.add_row(diff_row(
+                row,
+                changed.contains(&holding.symbol),
+                display.use_colors,
+            )),
+            None => table.add_row(row),
+        };
     }
     table.add_row(vec![
         "Cash".to_string(),
-        format!("${:.2}", portfolio.cash),
+        format_value(portfolio.cash, display),
         "".to_string(),
         "".to_string(),
         "".to_string(),
         "".to_string(),
     ]);
+    if portfolio.config.max_holdings > 0 {
+        table.add_row(vec![
+            "Positions".to_string(),
+            format!(
+                "{}/{}",
+                portfolio.holdings.len(),
+                portfolio.config.max_holdings
+            ),
+            "".to_string(),
+            "".to_string(),
+            "".to_string(),
+            "".to_string(),
+        ]);
+    }
+    if portfolio.config.min_cash_reserve_usd > 0.0 {
+        table.add_row(vec![
+            "Investable".to_string(),
+            format_value(portfolio.investable_cash(), display),
+            "".to_string(),
+            "".to_string(),
+            "".to_string(),
+            "".to_string(),
+        ]);
+    }
     table.add_row(vec![
         "Total".to_string(),
         "".to_string(),
         "".to_string(),
         "".to_string(),
-        format!("${:.2}", total_value),
+        format_value(total_value, display),
         "".to_string(),
     ]);
 
-    println!("=== Portfolio Status ===\n{}", table);
+    let mut screen = format!(
+        "=== Portfolio Status ===\nTimestamp: {}\n{}",
+        display_now(),
+        table
+    );
+    if !portfolio.config.trading_enabled {
+        screen.push_str("\n*** TRADING PAUSED: stop-loss and sentiment sells are disabled ***");
+    }
+    screen
+}
+
+pub fn display_prices(prices: &[(String, Option<f64>)], display: &DisplayConfig) {
+    let mut table = new_table(display);
+    table.set_header(vec!["Symbol", "Price (USD)"]);
+    for (symbol, price) in prices {
+        table.add_row(vec![
+            symbol.clone(),
+            price
+                .map(|price| format_price(price, display))
+                .unwrap_or_else(|| "N/A".to_string()),
+        ]);
+    }
+    println!("=== Prices ===\nTimestamp: {}\n{}", display_now(), table);
+}
+
+pub fn display_stats(all: &[Stats], display: &DisplayConfig) {
+    let mut table = new_table(display);
+    table.set_header(vec![
+        "Process",
+        "Since",
+        "Updated",
+        "API Calls",
+        "Cache Hit Rate",
+        "Sells",
+    ]);
+    let mut providers: BTreeMap<&str, ProviderStats> = BTreeMap::new();
+    for stats in all {
+        table.add_row(vec![
+            stats.process.clone(),
+            to_display_zone(stats.started_at)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string(),
+            to_display_zone(stats.updated_at)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string(),
+            stats.api_calls().to_string(),
+            stats
+                .cache_hit_ratio()
+                .map(|ratio| format!("{:.1}%", ratio * 100.0))
+                .unwrap_or_else(|| "N/A".to_string()),
+            stats.sells.to_string(),
+        ]);
+        for (name, provider) in &stats.providers {
+            let total = providers.entry(name).or_default();
+            total.calls += provider.calls;
+            total.total_latency_ms += provider.total_latency_ms;
+        }
+    }
+
+    let mut provider_table = new_table(display);
+    provider_table.set_header(vec!["Provider", "API Calls", "Avg Latency (ms)"]);
+    for (name, provider) in &providers {
+        provider_table.add_row(vec![
+            name.to_string(),
+            provider.calls.to_string(),
+            format!("{:.0}", provider.average_latency_ms()),
+        ]);
+    }
+
+    println!(
+        "=== Runtime Stats ===\nTimestamp: {}\n{}\n{}",
+        display_now(),
+        table,
+        provider_table
+    );
+}
+
+pub fn display_reconciliation(discrepancies: &[Discrepancy], display: &DisplayConfig) {
+    if discrepancies.is_empty() {
+        println!("=== Reconciliation ===\nAll holdings match the exchange balances.");
+        return;
+    }
+    let theme = Theme::from_config(display);
+    let mut table = new_table(display);
+    table.set_header(vec!["Symbol", "Configured", "Exchange", "Drift"]);
+    for discrepancy in discrepancies {
+        let drift = format!("{:+.8}", discrepancy.drift());
+        let drift_cell = theme.cell(drift, Tone::Caution);
+        table.add_row(vec![
+            Cell::new(&discrepancy.symbol),
+            Cell::new(format!("{:.8}", discrepancy.configured)),
+            Cell::new(format!("{:.8}", discrepancy.actual)),
+            drift_cell,
+        ]);
+    }
+    println!(
+        "=== Reconciliation ===\nTimestamp: {}\n{}",
+        display_now(),
+        table
+    );
+}
+
+pub fn display_pending_sells(pending: &[PendingSell], display: &DisplayConfig) {
+    if pending.is_empty() {
+        println!("=== Pending Sells ===\nNo sells are waiting for approval.");
+        return;
+    }
+    let theme = Theme::from_config(display);
+    let mut table = new_table(display);
+    table.set_header(vec![
+        "ID", "Symbol", "Quantity", "Price", "Value", "Queued", "Status",
+    ]);
+    for sell in pending {
+        let status = if sell.approved {
+            theme.cell("Approved", Tone::Positive)
+        } else {
+            theme.cell("Awaiting approval", Tone::Caution)
+        };
+        table.add_row(vec![
+            Cell::new(sell.id),
+            Cell::new(&sell.symbol),
+            Cell::new(format!("{:.8}", sell.quantity)),
+            Cell::new(format!(
+                "${:.*}",
+                price_decimals(sell.price, display),
+                sell.price
+            )),
+            Cell::new(format!("${:.*}", display.value_decimals, sell.value())),
+            Cell::new(
+                to_display_zone(sell.queued_at)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string(),
+            ),
+            status,
+        ]);
+    }
+    println!(
+        "=== Pending Sells ===\nTimestamp: {}\n{}\nRun `approve <id>` to execute a sell on the next portfolio check.",
+        display_now(),
+        table
+    );
+}
+
+// Below this the move is shown as flat, since scores are displayed to two decimals
+const SENTIMENT_TREND_EPSILON: f64 = 0.005;
+
+fn sentiment_trend(current: f64, previous: Option<f64>) -> String {
+    match previous {
+        Some(previous) => {
+            let delta = current - previous;
+            if delta > SENTIMENT_TREND_EPSILON {
+                format!("▲ {:+.2}", delta)
+            } else if delta < -SENTIMENT_TREND_EPSILON {
+                format!("▼ {:+.2}", delta)
+            } else {
+                "→ 0.00".to_string()
+            }
+        }
+        None => "N/A".to_string(),
+    }
+}
+
+fn sentiment_trend_cell(current: f64, previous: Option<f64>, theme: &Theme) -> Cell {
+    let trend = sentiment_trend(current, previous);
+    let tone = match previous {
+        Some(previous) if current - previous > SENTIMENT_TREND_EPSILON => Tone::Positive,
+        Some(previous) if current - previous < -SENTIMENT_TREND_EPSILON => Tone::Negative,
+        _ => Tone::Neutral,
+    };
+    theme.cell(trend, tone)
+}
+
+// Hold/Buy at or above the positive threshold, Sell at or below the negative one, Monitor between;
+// setting buy_threshold or watch_threshold splits that into Strong Buy/Accumulate/Hold/Reduce/Sell
+pub fn recommendation(sentiment: f64, bands: &SentimentBands) -> (&'static str, Tone) {
+    if bands.buy.is_none() && bands.watch.is_none() {
+        return if sentiment >= bands.positive {
+            ("Hold/Buy", Tone::Positive)
+        } else if sentiment <= bands.negative {
+            ("Sell", Tone::Negative)
+        } else {
+            ("Monitor", Tone::Neutral)
+        };
+    }
+    // An unset cut-off collapses its band into the neighbouring Hold band
+    let buy = bands.buy.unwrap_or(bands.positive);
+    let watch = bands.watch.unwrap_or(bands.negative);
+    if sentiment >= bands.positive {
+        ("Strong Buy", Tone::Positive)
+    } else if sentiment >= buy {
+        ("Accumulate", Tone::MildPositive)
+    } else if sentiment <= bands.negative {
+        ("Sell", Tone::Negative)
+    } else if sentiment < watch {
+        ("Reduce", Tone::Caution)
+    } else {
+        ("Hold", Tone::Neutral)
+    }
+}
+
+pub fn recommendation_cell(sentiment: f64, bands: &SentimentBands, theme: &Theme) -> Cell {
+    let (recommendation, tone) = recommendation(sentiment, bands);
+    theme.cell(recommendation, tone)
+}
+
+// Percentage of the summary populated; below sentiment.min_data_quality it is marked low
+fn data_quality_cell(quality: f64, min_quality: f64, theme: &Theme) -> Cell {
+    if quality < min_quality {
+        theme.cell(format!("{:.0}% (low)", quality * 100.0), Tone::Caution)
+    } else {
+        Cell::new(format!("{:.0}%", quality * 100.0))
+    }
+}
+
+// Heaviest theme first
+fn by_weight(mut themes: Vec<SentimentTheme>) -> Vec<SentimentTheme> {
+    themes.sort_by(|a, b| b.weight.total_cmp(&a.weight));
+    themes
+}
+
+// "2024-03-11 (217d ago)"; unparsed dates are shown as LunarCrush wrote them
+fn format_sentiment_date(date: &SentimentDate, today: NaiveDate) -> String {
+    match date.days_before(today) {
+        Some(days) => format!("{} ({}d ago)", date, days),
+        None => date.to_string(),
+    }
+}
+
+// Busiest networks first (by total mentions), ties broken by name so refreshes render stably
+fn sorted_networks(
+    network_engagement: &HashMap<String, NetworkEngagement>,
+) -> Vec<(&String, &NetworkEngagement)> {
+    let mut networks: Vec<_> = network_engagement.iter().collect();
+    networks.sort_by(|(a_name, a), (b_name, b)| {
+        b.total_engagement()
+            .total_cmp(&a.total_engagement())
+            .then_with(|| a_name.cmp(b_name))
+    });
+    networks
+}
+
+// Every symbol's summary, fetched once for both the dashboard and the detail tables with at most
+// sentiment.max_concurrency requests in flight; any failure fails the whole refresh
+async fn fetch_detailed_sentiments(
+    symbols: &[String],
+    sentiment_provider: &dyn SentimentProvider,
+    max_concurrency: usize,
+) -> Result<HashMap<String, DetailedSentiment>, PortfolioError> {
+    let limiter = Semaphore::new(max_concurrency.max(1));
+    let results = join_all(symbols.iter().map(|symbol| {
+        let limiter = &limiter;
+        async move {
+            let _permit = limiter.acquire().await.expect("limiter is never closed");
+            (
+                symbol.clone(),
+                sentiment_provider.fetch_detailed_sentiment(symbol).await,
+            )
+        }
+    }))
+    .await;
+    results
+        .into_iter()
+        .map(|(symbol, detailed)| detailed.map(|detailed| (symbol, detailed)))
+        .collect()
 }
 
 pub async fn display_sentiment_screen(
-    portfolio: &Portfolio,
+    symbols: &[String],
     sentiments: &HashMap<String, f64>,
     db: &Database,
-    sentiment_provider: &impl SentimentProvider,
-    positive_threshold: f64,
-    negative_threshold: f64,
-    use_colors: bool,
+    sentiment_provider: &dyn SentimentProvider,
+    sentiment_config: &SentimentConfig,
+    display: &DisplayConfig,
 ) -> Result<(), PortfolioError> {
-    let mut table = Table::new();
+    let theme = Theme::from_config(display);
+    let mut table = new_table(display);
     table.set_header(vec![
         "Symbol",
         "Sentiment Score",
+        "Δ Since Last",
         "Data Source",
         "Cache TTL",
         "Recommendation",
         "Daily Avg",
         "1-Week",
         "1-Month",
+        "Data Quality",
     ]);
-    for holding in &portfolio.holdings {
-        let sentiment = *sentiments.get(&holding.symbol).unwrap_or(&0.5);
-        let detailed = sentiment_provider
-            .fetch_detailed_sentiment(&holding.symbol)
-            .await?;
-        let (source, ttl) =
-            if let Some(cached_sentiment) = db.get_cached_sentiment(&holding.symbol).await? {
-                (
-                    "Redis Cache".to_string(),
-                    db.get_cached_sentiment_ttl(&holding.symbol)
-                        .await?
-                        .unwrap_or(0),
-                )
-            } else {
-                ("API Fetch".to_string(), 0)
-            };
-        let recommendation = if sentiment >= positive_threshold {
-            "Hold/Buy".to_string()
-        } else if sentiment <= negative_threshold {
-            "Sell".to_string()
-        } else {
-            "Monitor".to_string()
+    let details = fetch_detailed_sentiments(
+        symbols,
+        sentiment_provider,
+        sentiment_config.max_concurrency,
+    )
+    .await?;
+    for symbol in symbols {
+        let sentiment = sentiments.get(symbol).copied();
+        let Some(detailed) = details.get(symbol) else {
+            continue;
         };
-        let recommendation_cell = if use_colors {
-            if sentiment >= positive_threshold {
-                Cell::new(&recommendation).fg(Color::Green)
-            } else if sentiment <= negative_threshold {
-                Cell::new(&recommendation).fg(Color::Red)
-            } else {
-                Cell::new(&recommendation)
-            }
+        let (source, ttl) = if db
+            .get_cached_sentiment(sentiment_provider.name(), symbol)
+            .await?
+            .is_some()
+        {
+            (
+                "Redis Cache".to_string(),
+                db.get_cached_sentiment_ttl(sentiment_provider.name(), symbol)
+                    .await?
+                    .unwrap_or(0),
+            )
         } else {
-            Cell::new(&recommendation)
+            ("API Fetch".to_string(), 0)
+        };
+        let previous = db
+            .get_previous_sentiment(sentiment_provider.name(), symbol)
+            .await?;
+        let bands = sentiment_config.bands_for(symbol);
+        let (trend_cell, recommendation_cell) = match sentiment {
+            Some(sentiment) => (
+                sentiment_trend_cell(sentiment, previous, &theme),
+                recommendation_cell(sentiment, &bands, &theme),
+            ),
+            None => (Cell::new("N/A"), Cell::new("N/A")),
         };
         table.add_row(vec![
-            Cell::new(holding.symbol.clone()),
-            Cell::new(format!("{:.2}", sentiment)),
+            Cell::new(symbol.clone()),
+            Cell::new(format_sentiment(sentiment)),
+            trend_cell,
             Cell::new(source),
             Cell::new(format!("{}s", ttl)),
             recommendation_cell,
             Cell::new(format!("{:.2}", detailed.daily_average)),
-            Cell::new(format!(
-                "{:.2} ({:.0}%)",
-                detailed.one_week_value,
-                detailed.one_week_change * 100.0
-            )),
-            Cell::new(format!(
-                "{:.2} ({:.0}%)",
-                detailed.one_month_value,
-                detailed.one_month_change * 100.0
-            )),
+            change_cell(
+                format!(
+                    "{:.2} ({})",
+                    detailed.one_week_value,
+                    format_percent(detailed.one_week_change * 100.0, 2)
+                ),
+                detailed.one_week_change,
+                &theme,
+            ),
+            change_cell(
+                format!(
+                    "{:.2} ({})",
+                    detailed.one_month_value,
+                    format_percent(detailed.one_month_change * 100.0, 2)
+                ),
+                detailed.one_month_change,
+                &theme,
+            ),
+            data_quality_cell(
+                detailed.data_quality(),
+                sentiment_config.min_data_quality,
+                &theme,
+            ),
         ]);
     }
 
     println!(
         "=== Sentiment Analysis Dashboard ===\nTimestamp: {}\n{}",
-        chrono::Utc::now(),
+        display_now(),
         table
     );
 
-    // Detailed sentiment for each holding
-    for holding in &portfolio.holdings {
-        let detailed = sentiment_provider
-            .fetch_detailed_sentiment(&holding.symbol)
-            .await?;
+    if let Some(details) = sentiment_details(symbols, details, sentiment_config, display) {
+        print!("{}", details);
+    }
+    Ok(())
+}
+
+// Per-symbol detail tables below the dashboard; None in summary mode, which stops at the
+// dashboard table for portfolios too large to page through
+fn sentiment_details(
+    symbols: &[String],
+    mut details: HashMap<String, DetailedSentiment>,
+    sentiment_config: &SentimentConfig,
+    display: &DisplayConfig,
+) -> Option<String> {
+    if sentiment_config.detail_level == "summary" {
+        return None;
+    }
+
+    let mut output = String::new();
+    for symbol in symbols {
+        let Some(detailed) = details.remove(symbol) else {
+            continue;
+        };
 
         // High/Low table
-        let mut high_low_table = Table::new();
+        let mut high_low_table = new_table(display);
         high_low_table.set_header(vec!["1-Year High", "Date", "1-Year Low", "Date"]);
+        let today = display_now().date_naive();
         high_low_table.add_row(vec![
             format!("{:.2}", detailed.one_year_high),
-            detailed.one_year_high_date,
+            format_sentiment_date(&detailed.one_year_high_date, today),
             format!("{:.2}", detailed.one_year_low),
-            detailed.one_year_low_date,
+            format_sentiment_date(&detailed.one_year_low_date, today),
         ]);
-        println!("\n{} High/Low:", holding.symbol);
-        println!("{}", high_low_table);
+        let _ = writeln!(output, "\n{} High/Low:\n{}", symbol, high_low_table);
 
         // Supportive Themes table
-        let mut supportive_table = Table::new();
+        let mut supportive_table = new_table(display);
         supportive_table.set_header(vec!["Supportive Theme", "Weight", "Description"]);
-        for theme in detailed.supportive_themes {
+        for theme in by_weight(detailed.supportive_themes) {
             supportive_table.add_row(vec![
                 theme.name,
                 format!("{:.0}%", theme.weight * 100.0),
                 theme.description,
             ]);
         }
-        println!("\n{} Supportive Themes:", holding.symbol);
-        println!("{}", supportive_table);
+        let _ = writeln!(
+            output,
+            "\n{} Supportive Themes:\n{}",
+            symbol, supportive_table
+        );
 
         // Critical Themes table
-        let mut critical_table = Table::new();
+        let mut critical_table = new_table(display);
         critical_table.set_header(vec!["Critical Theme", "Weight", "Description"]);
-        for theme in detailed.critical_themes {
+        for theme in by_weight(detailed.critical_themes) {
             critical_table.add_row(vec![
                 theme.name,
                 format!("{:.0}%", theme.weight * 100.0),
                 theme.description,
             ]);
         }
-        println!("\n{} Critical Themes:", holding.symbol);
-        println!("{}", critical_table);
+        let _ = writeln!(output, "\n{} Critical Themes:\n{}", symbol, critical_table);
 
         // Network Engagement table
-        let mut engagement_table = Table::new();
+        let mut engagement_table = new_table(display);
         engagement_table.set_header(vec![
             "Network",
             "Positive",
@@ -185,9 +813,11 @@ pub async fn display_sentiment_screen(
             "Negative",
             "Negative %",
         ]);
-        for (network, engagement) in detailed.network_engagement {
+        let networks = sorted_networks(&detailed.network_engagement);
+        let shown = display.max_network_rows.unwrap_or(networks.len());
+        for (network, engagement) in networks.iter().take(shown) {
             engagement_table.add_row(vec![
-                network,
+                network.to_string(),
                 engagement.positive.to_string(),
                 format!("{:.0}%", engagement.positive_percentage * 100.0),
                 engagement.neutral.to_string(),
@@ -196,9 +826,342 @@ pub async fn display_sentiment_screen(
                 format!("{:.0}%", engagement.negative_percentage * 100.0),
             ]);
         }
-        println!("\n{} Network Engagement:", holding.symbol);
-        println!("{}", engagement_table);
+        let _ = writeln!(
+            output,
+            "\n{} Network Engagement:\n{}",
+            symbol, engagement_table
+        );
+        if networks.len() > shown {
+            let _ = writeln!(output, "... and {} more", networks.len() - shown);
+        }
     }
+    Some(output)
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Records the most detailed fetches it ever had in flight at once
+    #[derive(Default)]
+    struct CountingProvider {
+        in_flight: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl SentimentProvider for CountingProvider {
+        async fn fetch_sentiment(&self, _symbol: &str) -> Result<f64, PortfolioError> {
+            Ok(0.5)
+        }
+
+        async fn fetch_detailed_sentiment(
+            &self,
+            _symbol: &str,
+        ) -> Result<DetailedSentiment, PortfolioError> {
+            let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            Ok(DetailedSentiment::default())
+        }
+
+        fn name(&self) -> &str {
+            "counting"
+        }
+    }
+
+    fn display_config(extra: &str) -> DisplayConfig {
+        toml::from_str(&format!(
+            "sentiment_refresh_secs = 60\nuse_colors = true\n{}",
+            extra
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn configured_width_constrains_tables() {
+        let mut table = new_table(&display_config("table_width = 40"));
+        table.set_header(vec!["Symbol", "Description"]);
+        table.add_row(vec![
+            "SUI",
+            "A long description that would never fit on one forty column line",
+        ]);
+        let rendered = table.to_string();
+        assert!(rendered.lines().count() > 5, "{}", rendered);
+        assert!(
+            rendered.lines().all(|line| line.chars().count() <= 40),
+            "{}",
+            rendered
+        );
+    }
+
+    #[test]
+    fn sentiment_trends_point_the_way_scores_moved() {
+        assert_eq!(sentiment_trend(0.62, Some(0.5)), "▲ +0.12");
+        assert_eq!(sentiment_trend(0.38, Some(0.5)), "▼ -0.12");
+        assert_eq!(sentiment_trend(0.502, Some(0.5)), "→ 0.00");
+        assert_eq!(sentiment_trend(0.5, None), "N/A");
+    }
+
+    #[test]
+    fn configured_decimals_are_honored() {
+        let adaptive = display_config("");
+        assert_eq!(format_price(1234.5678, &adaptive), "$1234.57");
+        assert_eq!(format_price(0.123456, &adaptive), "$0.1235");
+        assert_eq!(format_price(0.00123456, &adaptive), "$0.001235");
+        assert_eq!(format_value(1234.5678, &adaptive), "$1234.57");
+
+        let fixed = display_config("price_decimals = 3\nvalue_decimals = 0");
+        assert_eq!(format_price(0.00123456, &fixed), "$0.001");
+        assert_eq!(format_price(1234.5678, &fixed), "$1234.568");
+        assert_eq!(format_value(1234.5678, &fixed), "$1235");
+    }
+
+    #[test]
+    fn networks_sort_by_engagement_then_name() {
+        let engagement = |positive: &str| NetworkEngagement {
+            positive: positive.to_string(),
+            positive_percentage: 1.0,
+            neutral: "0".to_string(),
+            neutral_percentage: 0.0,
+            negative: "0".to_string(),
+            negative_percentage: 0.0,
+        };
+        // Each map gets its own hash seed, so iteration order varies; the sorted order never does
+        for _ in 0..5 {
+            let networks = HashMap::from([
+                ("Reddit".to_string(), engagement("50")),
+                ("YouTube".to_string(), engagement("900")),
+                ("X".to_string(), engagement("900")),
+                ("TikTok".to_string(), engagement("n/a")),
+                ("News".to_string(), engagement("50")),
+            ]);
+            let names: Vec<&str> = sorted_networks(&networks)
+                .into_iter()
+                .map(|(name, _)| name.as_str())
+                .collect();
+            assert_eq!(names, ["X", "YouTube", "News", "Reddit", "TikTok"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn detailed_fetches_stay_under_max_concurrency() {
+        let symbols: Vec<String> = (0..8).map(|i| format!("SYM{}", i)).collect();
+        let provider = CountingProvider::default();
+        let detailed = fetch_detailed_sentiments(&symbols, &provider, 3)
+            .await
+            .unwrap();
+        assert_eq!(detailed.len(), 8);
+        assert_eq!(provider.peak.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn markdown_style_draws_pipe_delimited_rows() {
+        let mut table = new_table(&display_config("table_style = \"markdown\""));
+        table.set_header(vec!["Symbol", "Price"]);
+        table.add_row(vec!["SUI", "1.25"]);
+        let rendered = table.to_string();
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines.len(), 3, "{}", rendered);
+        assert!(
+            lines
+                .iter()
+                .all(|line| line.starts_with('|') && line.ends_with('|')),
+            "{}",
+            rendered
+        );
+        assert!(lines[2].contains("| SUI") && lines[2].contains("| 1.25"));
+    }
+
+    #[test]
+    fn percentages_are_signed_except_zero() {
+        assert_eq!(format_percent(3.456, 2), "+3.46%");
+        assert_eq!(format_percent(-3.456, 2), "-3.46%");
+        assert_eq!(format_percent(0.0, 2), "0.00%");
+        // Values that round to zero drop their sign too, rather than showing "-0.00%"
+        assert_eq!(format_percent(-0.001, 2), "0.00%");
+        assert_eq!(format_percent(12.5, 0), "+12%");
+    }
+
+    #[tokio::test]
+    async fn progress_is_hidden_unless_interactive() {
+        let display = display_config("");
+        assert!(display.show_progress);
+        assert!(shows_progress(&display, true));
+        assert!(!shows_progress(&display, false));
+        assert!(!shows_progress(
+            &display_config("show_progress = false"),
+            true
+        ));
+        // Screens sharing one terminal in single-process mode never draw it
+        SCREEN
+            .scope("market", async {
+                assert!(!shows_progress(&display, true));
+                assert!(fetch_progress(3, &display).is_hidden());
+            })
+            .await;
+    }
+
+    #[test]
+    fn recommendations_switch_at_each_boundary() {
+        let label = |sentiment: f64, bands: &SentimentBands| recommendation(sentiment, bands).0;
+        let bands = SentimentBands {
+            positive: 0.7,
+            buy: Some(0.6),
+            watch: Some(0.4),
+            negative: 0.3,
+        };
+        let cases = [
+            (0.7, "Strong Buy"),
+            (0.69, "Accumulate"),
+            (0.6, "Accumulate"),
+            (0.59, "Hold"),
+            (0.4, "Hold"),
+            (0.39, "Reduce"),
+            (0.31, "Reduce"),
+            (0.3, "Sell"),
+        ];
+        for (sentiment, expected) in cases {
+            assert_eq!(label(sentiment, &bands), expected, "{}", sentiment);
+        }
+
+        // Without buy/watch cut-offs only the three original bands remain
+        let bands = SentimentBands {
+            buy: None,
+            watch: None,
+            ..bands
+        };
+        let cases = [
+            (0.7, "Hold/Buy"),
+            (0.69, "Monitor"),
+            (0.31, "Monitor"),
+            (0.3, "Sell"),
+        ];
+        for (sentiment, expected) in cases {
+            assert_eq!(label(sentiment, &bands), expected, "{}", sentiment);
+        }
+    }
+
+    #[test]
+    fn changed_rows_are_bold_and_unchanged_ones_dimmed() {
+        let render = |changed: bool, use_colors: bool| {
+            let mut table = Table::new();
+            table.enforce_styling();
+            table.add_row(diff_row(vec![Cell::new("SUI")], changed, use_colors));
+            table.to_string()
+        };
+        let (bold, dim) = ("\u{1b}[1m", "\u{1b}[2m");
+        assert!(render(true, true).contains(bold));
+        assert!(!render(true, true).contains(dim));
+        assert!(render(false, true).contains(dim));
+        assert!(!render(false, true).contains(bold));
+        // Without colors the row is left as it was
+        assert!(!render(true, false).contains('\u{1b}'));
+    }
+
+    #[test]
+    fn colorblind_theme_avoids_red_and_green() {
+        let tones = [
+            Tone::Positive,
+            Tone::MildPositive,
+            Tone::Neutral,
+            Tone::Caution,
+            Tone::Negative,
+        ];
+        let red_or_green = |color: Color| {
+            matches!(
+                color,
+                Color::Red | Color::DarkRed | Color::Green | Color::DarkGreen
+            )
+        };
+        let colorblind = Theme::from_config(&display_config("color_theme = \"colorblind\""));
+        for tone in tones {
+            assert!(
+                !colorblind.color(tone).is_some_and(red_or_green),
+                "{:?}",
+                tone
+            );
+        }
+        // Gains and losses still look different
+        assert_ne!(colorblind.positive, colorblind.negative);
+        let classic = Theme::from_config(&display_config(""));
+        assert_eq!(classic.color(Tone::Negative), Some(Color::Red));
+    }
+
+    #[test]
+    fn holdings_order_is_stable_across_runs() {
+        let portfolio = Portfolio::from_csv(
+            toml::from_str(
+                "check_interval_secs = 60\nmax_allocation = 0.6\nstop_loss_percentage = 0.2",
+            )
+            .unwrap(),
+            "SUI,10,1.5,1.2\nDUSK,50,0.2,0.1\nPHA,100,0.1,0.05\nBTC,1,40000,30000\n",
+        )
+        .unwrap();
+        let order = |order: &str| -> Vec<String> {
+            // A fresh map each run, so its iteration order differs from run to run
+            let prices: HashMap<String, f64> = [("SUI", 2.0), ("DUSK", 0.4), ("PHA", 0.2)]
+                .into_iter()
+                .map(|(symbol, price)| (symbol.to_string(), price))
+                .collect();
+            ordered_holdings(&portfolio.holdings, &prices, order)
+                .iter()
+                .map(|holding| holding.symbol.clone())
+                .collect()
+        };
+        for _ in 0..5 {
+            assert_eq!(order("config"), ["SUI", "DUSK", "PHA", "BTC"]);
+            assert_eq!(order("symbol"), ["BTC", "DUSK", "PHA", "SUI"]);
+            // DUSK, PHA and SUI all tie at $20 and fall back to the symbol; unpriced BTC sorts last
+            assert_eq!(order("value"), ["DUSK", "PHA", "SUI", "BTC"]);
+        }
+    }
+
+    #[test]
+    fn themes_are_listed_heaviest_first() {
+        let theme = |name: &str, weight: f64| SentimentTheme {
+            name: name.to_string(),
+            weight,
+            description: String::new(),
+        };
+        let themes = by_weight(vec![
+            theme("Fees", 0.1),
+            theme("Adoption", 0.45),
+            theme("Staking", 0.3),
+        ]);
+        let names: Vec<&str> = themes.iter().map(|theme| theme.name.as_str()).collect();
+        assert_eq!(names, ["Adoption", "Staking", "Fees"]);
+    }
+
+    #[test]
+    fn summary_mode_omits_the_detail_tables() {
+        let symbols = vec!["SUI".to_string()];
+        let details = || HashMap::from([("SUI".to_string(), DetailedSentiment::default())]);
+        let config = |detail_level: &str| -> SentimentConfig {
+            toml::from_str(&format!(
+                "api_key = \"\"\ncache_ttl_secs = 60\npositive_threshold = 0.7\n\
+                 negative_threshold = 0.3\ndetail_level = \"{}\"",
+                detail_level
+            ))
+            .unwrap()
+        };
+        let display = display_config("");
+
+        let full = sentiment_details(&symbols, details(), &config("full"), &display).unwrap();
+        for section in [
+            "High/Low",
+            "Supportive Themes",
+            "Critical Themes",
+            "Network Engagement",
+        ] {
+            assert!(full.contains(&format!("SUI {}:", section)), "{}", full);
+        }
+        assert_eq!(
+            sentiment_details(&symbols, details(), &config("summary"), &display),
+            None
+        );
+    }
 }
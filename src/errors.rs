@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+// Every variant names a kind of error, matched on throughout the crate
+#[allow(clippy::enum_variant_names)]
 #[derive(Error, Debug)]
 pub enum PortfolioError {
     #[error("Configuration error: {0}")]
@@ -14,4 +16,6 @@ pub enum PortfolioError {
     NotificationError(String),
     #[error("ApiE error: {0}")]
     ApiError(String),
+    #[error("Unknown symbol: {0}")]
+    UnknownSymbolError(String), // The source answered and doesn't list the symbol, unlike a failed request
 }
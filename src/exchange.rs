@@ -1,16 +1,118 @@
-use crate::config::ExchangeConfig;
+use crate::config::{Config, ExchangeConfig, SentimentConfig};
 use crate::errors::PortfolioError;
-use crate::logger::log_action;
+use crate::http::{read_text, request_permit};
+use crate::logger::{log_action, log_elapsed};
+use crate::stats::record_api_call;
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use hmac::{Hmac, Mac};
+use log::debug;
+use reqwest::header::USER_AGENT;
 use reqwest::Client;
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::Decimal;
 use scraper::{Html, Selector};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Instant;
+use tokio::sync::OnceCell;
 
-pub trait Exchange {
+#[async_trait]
+pub trait Exchange: Send + Sync {
     async fn fetch_price(&self, symbol: &str) -> Result<f64, PortfolioError>;
+
+    // Price for stop-loss and proceeds math; sources that only quote floats convert theirs
+    async fn fetch_price_exact(&self, symbol: &str) -> Result<Decimal, PortfolioError> {
+        to_decimal(self.fetch_price(symbol).await?)
+    }
+
+    // Rolling 24h stats for market rows CoinGecko can't supply; None when the source has none
+    async fn fetch_24h_stats(&self, _symbol: &str) -> Result<Option<Ticker24h>, PortfolioError> {
+        Ok(None)
+    }
+
+    // Backend identifier for log lines and price cache keys, e.g., "binance"
+    fn name(&self) -> &str;
+
+    // Backend whose price for `symbol` was fetched last; only a chain answers from several
+    fn answered_by(&self, _symbol: &str) -> String {
+        self.name().to_string()
+    }
+
+    // Percentage charged on each market order, e.g., 0.1 for 0.1%
+    fn taker_fee_pct(&self) -> f64 {
+        0.0
+    }
+}
+
+// Places real orders; only built when portfolio.live_trading is set, otherwise trades are simulated
+#[async_trait]
+pub trait OrderExecutor: Send + Sync {
+    async fn place_market_sell(
+        &self,
+        symbol: &str,
+        quantity: f64,
+    ) -> Result<OrderFill, PortfolioError>;
+    async fn place_market_buy(
+        &self,
+        symbol: &str,
+        quantity: f64,
+    ) -> Result<OrderFill, PortfolioError>;
+
+    // Checked once at startup so a mistyped holding fails fast instead of at the first order
+    async fn validate_symbols(&self, _symbols: &[String]) -> Result<(), PortfolioError> {
+        Ok(())
+    }
+}
+
+// Every exchange price is quoted (or converted) to US dollars; cached prices are keyed by it
+pub const QUOTE_CURRENCY: &str = "USD";
+
+// Quantities, fills and config values enter decimal math through here; NaN and infinity have no decimal form
+pub fn to_decimal(value: f64) -> Result<Decimal, PortfolioError> {
+    Decimal::from_f64(value).ok_or_else(|| {
+        PortfolioError::ExchangeError(format!("{} has no exact decimal value", value))
+    })
+}
+
+// Back to f64 for display, caching and valuation
+pub fn to_f64(value: Decimal) -> f64 {
+    value.to_f64().unwrap_or(0.0)
+}
+
+// USD-quoted 24h ticker; volume is in the quote asset so it compares with CoinGecko's total_volume
+#[derive(Debug, Clone)]
+pub struct Ticker24h {
+    pub price_change: f64,
+    pub price_change_percent: f64,
+    pub high: f64,
+    pub low: f64,
+    pub quote_volume: f64,
 }
 
 #[derive(Debug, Clone)]
+pub struct OrderFill {
+    pub order_id: i64,
+    pub executed_quantity: f64,
+    pub quote_quantity: f64, // USDT spent or received
+}
+
+impl OrderFill {
+    pub fn average_price(&self) -> f64 {
+        if self.executed_quantity > 0.0 {
+            self.quote_quantity / self.executed_quantity
+        } else {
+            0.0
+        }
+    }
+}
+
+// Field names double as the sentiment.response_format = "json" schema; values are 0..1 fractions
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct DetailedSentiment {
     pub current_value: f64,
     pub daily_average: f64,
@@ -23,22 +125,66 @@ pub struct DetailedSentiment {
     pub one_year_value: f64,
     pub one_year_change: f64,
     pub one_year_high: f64,
-    pub one_year_high_date: String,
+    pub one_year_high_date: SentimentDate,
     pub one_year_low: f64,
-    pub one_year_low_date: String,
+    pub one_year_low_date: SentimentDate,
     pub supportive_themes: Vec<Theme>,
     pub critical_themes: Vec<Theme>,
     pub network_engagement: HashMap<String, NetworkEngagement>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Theme {
     pub name: String,
     pub weight: f64,
     pub description: String,
 }
 
-#[derive(Debug, Clone)]
+// A date from the sentiment markdown; `raw` is kept so unrecognized formats still display
+// JSON responses give the date as a string and go through the same parse
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(from = "String")]
+pub struct SentimentDate {
+    pub date: Option<NaiveDate>,
+    pub raw: String,
+}
+
+// Formats seen in LunarCrush summaries, e.g., "Mar 11, 2024", "March 11, 2024", "2024-03-11"
+const SENTIMENT_DATE_FORMATS: [&str; 4] = ["%b %d, %Y", "%B %d, %Y", "%Y-%m-%d", "%m/%d/%Y"];
+
+impl SentimentDate {
+    pub fn parse(text: &str) -> Self {
+        let raw = text.trim().trim_end_matches('.').to_string();
+        let date = SENTIMENT_DATE_FORMATS
+            .iter()
+            .find_map(|format| NaiveDate::parse_from_str(&raw, format).ok());
+        SentimentDate { date, raw }
+    }
+
+    // Whole days between the date and `today`; None when the date didn't parse
+    pub fn days_before(&self, today: NaiveDate) -> Option<i64> {
+        self.date.map(|date| (today - date).num_days())
+    }
+}
+
+impl From<String> for SentimentDate {
+    fn from(text: String) -> Self {
+        SentimentDate::parse(&text)
+    }
+}
+
+impl fmt::Display for SentimentDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.date {
+            Some(date) => write!(f, "{}", date.format("%Y-%m-%d")),
+            None => write!(f, "{}", self.raw),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct NetworkEngagement {
     pub positive: String,
     pub positive_percentage: f64,
@@ -48,77 +194,226 @@ pub struct NetworkEngagement {
     pub negative_percentage: f64,
 }
 
+impl NetworkEngagement {
+    pub fn total_engagement(&self) -> f64 {
+        [&self.positive, &self.neutral, &self.negative]
+            .iter()
+            .filter_map(|count| count.parse::<f64>().ok())
+            .sum()
+    }
+
+    // Positive share of the opinionated (non-neutral) engagement, 0.5 when there is none
+    pub fn score(&self) -> f64 {
+        let opinionated = self.positive_percentage + self.negative_percentage;
+        if opinionated > 0.0 {
+            self.positive_percentage / opinionated
+        } else {
+            0.5
+        }
+    }
+}
+
+impl DetailedSentiment {
+    // Blend of per-network scores by sentiment.network_weights (network names match
+    // case-insensitively); None when no weighted network was reported
+    pub fn network_weighted_score(&self, weights: &HashMap<String, f64>) -> Option<f64> {
+        let mut weighted_sum = 0.0;
+        let mut total_weight = 0.0;
+        for (network, engagement) in &self.network_engagement {
+            let weight = weights
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(network))
+                .map(|(_, weight)| *weight)
+                .unwrap_or(0.0);
+            if weight > 0.0 {
+                weighted_sum += weight * engagement.score();
+                total_weight += weight;
+            }
+        }
+        (total_weight > 0.0).then(|| weighted_sum / total_weight)
+    }
+
+    // Share of the summary that was actually populated, 0..1: period values, themes, networks and a
+    // dated 1-year high/low. A sparse summary still yields a score, just one not worth trusting
+    pub fn data_quality(&self) -> f64 {
+        let checks = [
+            self.current_value != 0.0,
+            self.daily_average != 0.0,
+            self.one_week_value != 0.0,
+            self.one_month_value != 0.0,
+            self.six_months_value != 0.0,
+            self.one_year_value != 0.0,
+            self.one_year_high != 0.0 && self.one_year_high_date.date.is_some(),
+            self.one_year_low != 0.0 && self.one_year_low_date.date.is_some(),
+            self.supportive_themes
+                .iter()
+                .any(|theme| theme.weight > 0.0),
+            self.critical_themes.iter().any(|theme| theme.weight > 0.0),
+            !self.network_engagement.is_empty(),
+        ];
+        checks.iter().filter(|populated| **populated).count() as f64 / checks.len() as f64
+    }
+}
+
 pub struct LunarCrushProvider {
     client: reqwest::Client,
     base_url: String,
     api_key: String,
+    scale: String,
+    network_weights: HashMap<String, f64>,
+    debug_raw: bool,
+    response_format: String,
 }
 
 impl LunarCrushProvider {
-    pub fn new(api_url: &str, api_key: &str) -> Self {
+    pub fn new(
+        api_url: &str,
+        api_key: &str,
+        scale: &str,
+        network_weights: HashMap<String, f64>,
+        debug_raw: bool,
+        response_format: &str,
+    ) -> Self {
         LunarCrushProvider {
             client: reqwest::Client::new(),
             base_url: api_url.to_string(),
             api_key: api_key.to_string(),
+            scale: scale.to_string(),
+            network_weights,
+            debug_raw,
+            response_format: response_format.to_string(),
         }
     }
-}
-
-impl SentimentProvider for LunarCrushProvider {
-    async fn fetch_sentiment(&self, symbol: &str) -> Result<f64, PortfolioError> {
-        let detailed = self.fetch_detailed_sentiment(symbol).await?;
-        Ok(detailed.current_value)
-    }
 
-    async fn fetch_detailed_sentiment(
-        &self,
-        symbol: &str,
-    ) -> Result<DetailedSentiment, PortfolioError> {
-        let url = format!(
+    fn sentiment_url(&self, symbol: &str) -> String {
+        format!(
             "{}/topic/{}/sentiment?key={}",
             self.base_url,
             symbol.to_lowercase(),
             self.api_key
-        );
+        )
+    }
+
+    // Same URL with the API key masked, safe to print or log
+    pub fn redacted_sentiment_url(&self, symbol: &str) -> String {
+        self.sentiment_url(symbol)
+            .replace(&format!("key={}", self.api_key), "key=REDACTED")
+    }
+
+    // Raw response body, exactly as fetch_detailed_sentiment receives it before parsing
+    pub async fn fetch_raw_sentiment(&self, symbol: &str) -> Result<String, PortfolioError> {
+        let url = self.sentiment_url(symbol);
+        let _permit = request_permit().await;
+        // without_url: reqwest's message would otherwise echo the ?key= query
         let response = self.client.get(&url).send().await.map_err(|e| {
-            PortfolioError::ApiError(format!("Failed to fetch sentiment for {}: {}", symbol, e))
+            PortfolioError::ApiError(format!(
+                "Failed to fetch sentiment for {}: {}",
+                symbol,
+                e.without_url()
+            ))
         })?;
 
-        let html_text = response.text().await.map_err(|e| {
-            PortfolioError::ApiError(format!("Failed to parse HTML for {}: {}", symbol, e))
-        })?;
+        let status = response.status();
+        let html_text = read_text(response, &format!("Sentiment for {}", symbol)).await?;
+        // Rate-limit and auth failures come back as HTML error pages
+        if !status.is_success() {
+            return Err(PortfolioError::ApiError(format!(
+                "Sentiment request for {} failed with {}: {}",
+                symbol,
+                status,
+                body_snippet(&html_text)
+            )));
+        }
 
         let _ = log_action(
             &format!(
-                "Fetched sentiment for symbol: {} \n Raw: {}",
-                symbol, html_text
+                "Fetched sentiment for symbol: {} ({} bytes)",
+                symbol,
+                html_text.len()
             ),
             None,
         );
+        // Full bodies only with sentiment.debug_raw, at debug level, with the key masked
+        if self.debug_raw {
+            let body = if self.api_key.is_empty() {
+                html_text.clone()
+            } else {
+                html_text.replace(&self.api_key, "REDACTED")
+            };
+            debug!("Raw sentiment body for {}:\n{}", symbol, body);
+        }
 
-        let html = Html::parse_document(&html_text);
+        Ok(html_text)
+    }
 
-        let _ = log_action(
-            &format!(
-                "Fetched sentiment for symbol: {} \n Pre: {}",
-                symbol,
-                html.html()
-            ),
-            None,
-        );
+    // Parses a body from fetch_raw_sentiment according to sentiment.response_format
+    pub fn parse_sentiment(
+        &self,
+        symbol: &str,
+        body: &str,
+    ) -> Result<DetailedSentiment, PortfolioError> {
+        match self.response_format.as_str() {
+            "json" => Self::parse_json_sentiment(symbol, body),
+            _ => Self::parse_detailed_sentiment(symbol, body),
+        }
+    }
 
-        let html = Html::parse_document(&html.html());
+    // JSON bodies deserialize straight into DetailedSentiment; missing fields default
+    pub fn parse_json_sentiment(
+        symbol: &str,
+        body: &str,
+    ) -> Result<DetailedSentiment, PortfolioError> {
+        let value: serde_json::Value = serde_json::from_str(body).map_err(|e| {
+            PortfolioError::ApiError(format!(
+                "Sentiment response for {} is not JSON ({}): {}",
+                symbol,
+                e,
+                body_snippet(body)
+            ))
+        })?;
+        // Same guard as the markdown marker: without current_value every score would default to zero
+        if value.get("current_value").is_none() {
+            return Err(PortfolioError::ApiError(format!(
+                "Sentiment response for {} has no current_value: {}",
+                symbol,
+                body_snippet(body)
+            )));
+        }
+        serde_json::from_value(value).map_err(|e| {
+            PortfolioError::ApiError(format!("Invalid sentiment JSON for {}: {}", symbol, e))
+        })
+    }
 
-        // Extract text from <pre> tag
-        let pre_selector = Selector::parse("body").unwrap();
-        let pre_text = html
-            .select(&pre_selector)
+    pub fn parse_detailed_sentiment(
+        symbol: &str,
+        html_text: &str,
+    ) -> Result<DetailedSentiment, PortfolioError> {
+        // Parsed once; fetch_raw_sentiment already logged the size (and the body with debug_raw)
+        let html = Html::parse_document(html_text);
+        let body_selector = Selector::parse("body").unwrap();
+        let body_text = html
+            .select(&body_selector)
             .next()
-            .ok_or_else(|| PortfolioError::ApiError("Missing <pre> tag in HTML".to_string()))?
-            .inner_html();
+            .map(|body| body.inner_html())
+            .filter(|text| !text.trim().is_empty())
+            .ok_or_else(|| {
+                PortfolioError::ApiError(format!(
+                    "Empty or bodyless sentiment response for {}",
+                    symbol
+                ))
+            })?;
+
+        // Without the marker this isn't sentiment data, and parsing it would fabricate zeros
+        if !body_text.contains("**Current Value**") {
+            return Err(PortfolioError::ApiError(format!(
+                "Sentiment response for {} has no **Current Value** data: {}",
+                symbol,
+                body_snippet(&body_text)
+            )));
+        }
 
-        // Split pre_text into lines for parsing
-        let lines: Vec<&str> = pre_text.lines().collect();
+        // Split the body into lines for parsing
+        let lines: Vec<&str> = body_text.lines().collect();
 
         let mut current_value = 0.0;
         let mut daily_average = 0.0;
@@ -131,9 +426,9 @@ impl SentimentProvider for LunarCrushProvider {
         let mut one_year_value = 0.0;
         let mut one_year_change = 0.0;
         let mut one_year_high = 0.0;
-        let mut one_year_high_date = String::new();
+        let mut one_year_high_date = SentimentDate::default();
         let mut one_year_low = 0.0;
-        let mut one_year_low_date = String::new();
+        let mut one_year_low_date = SentimentDate::default();
         let mut supportive_themes = Vec::new();
         let mut critical_themes = Vec::new();
         //let mut network_engagement: HashMap<String, NetworkEngagement> = HashMap::new();
@@ -148,41 +443,48 @@ impl SentimentProvider for LunarCrushProvider {
 
         let parse_number = |text: &str| -> String { text.replace(",", "").trim().to_string() };
 
+        // "45% (+3%)": a period's value and its change; a missing part parses as 0
+        let parse_value_change = |text: &str| -> (f64, f64) {
+            let mut parts = text.split_whitespace();
+            let value = parts.next().map_or(0.0, &parse_percentage);
+            let change = parts.next().map_or(0.0, |change| {
+                parse_percentage(change.trim_matches(|c| c == '(' || c == ')'))
+            });
+            (value, change)
+        };
+
+        // "72% on 2024-03-11"; a missing date stays the default
+        let parse_value_date = |text: &str| -> (f64, SentimentDate) {
+            match text.split_once(" on ") {
+                Some((value, date)) => (parse_percentage(value), SentimentDate::parse(date)),
+                None => (parse_percentage(text), SentimentDate::default()),
+            }
+        };
+
         let mut in_supportive_themes = false;
         let mut in_critical_themes = false;
         let mut in_network_table = false;
         let mut network_table_lines = Vec::new();
 
+        // Prefixes are stripped rather than sliced by byte offset, so multibyte text can't panic
         for line in lines {
             let line_trim = line.trim();
-            if line_trim.starts_with("**Current Value**:") {
-                current_value = parse_percentage(&line_trim[18..]);
-            } else if line_trim.starts_with("**Daily Average**:") {
-                daily_average = parse_percentage(&line_trim[18..]);
-            } else if line_trim.starts_with("**1 Week**:") {
-                let parts: Vec<&str> = line_trim[11..].split_whitespace().collect();
-                one_week_value = parse_percentage(parts[0]);
-                one_week_change = parse_percentage(&parts[1][0..parts[1].len() - 1]);
-            } else if line_trim.starts_with("**1 Month**:") {
-                let parts: Vec<&str> = line_trim[12..].split_whitespace().collect();
-                one_month_value = parse_percentage(parts[0]);
-                one_month_change = parse_percentage(&parts[1][0..parts[1].len() - 1]);
-            } else if line_trim.starts_with("**6 Months**:") {
-                let parts: Vec<&str> = line_trim[13..].split_whitespace().collect();
-                six_months_value = parse_percentage(parts[0]);
-                six_months_change = parse_percentage(&parts[1][0..parts[1].len() - 1]);
-            } else if line_trim.starts_with("**1 Year**:") {
-                let parts: Vec<&str> = line_trim[11..].split_whitespace().collect();
-                one_year_value = parse_percentage(parts[0]);
-                one_year_change = parse_percentage(&parts[1][0..parts[1].len() - 1]);
-            } else if line_trim.starts_with("**1-Year High**:") {
-                let parts: Vec<&str> = line_trim[16..].split(" on ").collect();
-                one_year_high = parse_percentage(parts[0]);
-                one_year_high_date = parts[1].to_string();
-            } else if line_trim.starts_with("**1-Year Low**:") {
-                let parts: Vec<&str> = line_trim[15..].split(" on ").collect();
-                one_year_low = parse_percentage(parts[0]);
-                one_year_low_date = parts[1].to_string();
+            if let Some(rest) = line_trim.strip_prefix("**Current Value**:") {
+                current_value = parse_percentage(rest);
+            } else if let Some(rest) = line_trim.strip_prefix("**Daily Average**:") {
+                daily_average = parse_percentage(rest);
+            } else if let Some(rest) = line_trim.strip_prefix("**1 Week**:") {
+                (one_week_value, one_week_change) = parse_value_change(rest);
+            } else if let Some(rest) = line_trim.strip_prefix("**1 Month**:") {
+                (one_month_value, one_month_change) = parse_value_change(rest);
+            } else if let Some(rest) = line_trim.strip_prefix("**6 Months**:") {
+                (six_months_value, six_months_change) = parse_value_change(rest);
+            } else if let Some(rest) = line_trim.strip_prefix("**1 Year**:") {
+                (one_year_value, one_year_change) = parse_value_change(rest);
+            } else if let Some(rest) = line_trim.strip_prefix("**1-Year High**:") {
+                (one_year_high, one_year_high_date) = parse_value_date(rest);
+            } else if let Some(rest) = line_trim.strip_prefix("**1-Year Low**:") {
+                (one_year_low, one_year_low_date) = parse_value_date(rest);
             } else if line_trim.starts_with("**Most Supportive Themes**") {
                 in_supportive_themes = true;
                 in_critical_themes = false;
@@ -195,38 +497,14 @@ impl SentimentProvider for LunarCrushProvider {
                 in_network_table = true;
             } else if in_supportive_themes {
                 if line_trim.starts_with("- **") {
-                    let theme_end = line_trim.find(":**").unwrap_or(line_trim.len());
-                    let name = &line_trim[4..theme_end - 3];
-                    let weight_start = line_trim.find("(").unwrap_or(line_trim.len());
-                    let weight_end = line_trim.find("%)").unwrap_or(line_trim.len());
-                    let weight_str = &line_trim[weight_start + 1..weight_end];
-                    let weight = parse_percentage(weight_str);
-                    let description = &line_trim[weight_end + 3..].trim();
-                    supportive_themes.push(Theme {
-                        name: name.to_string(),
-                        weight,
-                        description: description.to_string(),
-                    });
+                    supportive_themes.extend(parse_theme(symbol, line_trim));
                 }
             } else if in_critical_themes {
                 if line_trim.starts_with("- **") {
-                    let theme_end = line_trim.find(":**").unwrap_or(line_trim.len());
-                    let name = &line_trim[4..theme_end - 3];
-                    let weight_start = line_trim.find("(").unwrap_or(line_trim.len());
-                    let weight_end = line_trim.find("%)").unwrap_or(line_trim.len());
-                    let weight_str = &line_trim[weight_start + 1..weight_end];
-                    let weight = parse_percentage(weight_str);
-                    let description = &line_trim[weight_end + 3..].trim();
-                    critical_themes.push(Theme {
-                        name: name.to_string(),
-                        weight,
-                        description: description.to_string(),
-                    });
-                }
-            } else if in_network_table {
-                if line_trim.starts_with("|") {
-                    network_table_lines.push(line_trim.to_string());
+                    critical_themes.extend(parse_theme(symbol, line_trim));
                 }
+            } else if in_network_table && line_trim.starts_with("|") {
+                network_table_lines.push(line_trim.to_string());
             }
         }
 
@@ -280,8 +558,200 @@ impl SentimentProvider for LunarCrushProvider {
     }
 }
 
-pub fn create_sentiment_provider(api_url: &str, api_key: &str) -> LunarCrushProvider {
-    LunarCrushProvider::new(api_url, api_key)
+#[async_trait]
+impl SentimentProvider for LunarCrushProvider {
+    fn name(&self) -> &str {
+        "lunarcrush"
+    }
+
+    async fn fetch_sentiment(&self, symbol: &str) -> Result<f64, PortfolioError> {
+        let detailed = self.fetch_detailed_sentiment(symbol).await?;
+        // Network scores are already 0..1, so only current_value goes through the provider scale
+        match detailed.network_weighted_score(&self.network_weights) {
+            Some(score) => Ok(score),
+            None => Ok(normalize_sentiment(detailed.current_value, &self.scale)),
+        }
+    }
+
+    async fn fetch_detailed_sentiment(
+        &self,
+        symbol: &str,
+    ) -> Result<DetailedSentiment, PortfolioError> {
+        let started = Instant::now();
+        let html_text = self.fetch_raw_sentiment(symbol).await;
+        log_elapsed(&format!("fetch_detailed_sentiment({})", symbol), started);
+        record_api_call("lunarcrush", started);
+        self.parse_sentiment(symbol, &html_text?)
+    }
+}
+
+// A theme line such as "- **Adoption:** (45%) Growing use..."; dropped (logged at debug level)
+// rather than kept as a zero-weight theme when it doesn't parse
+fn parse_theme(symbol: &str, line: &str) -> Option<Theme> {
+    let theme = parse_theme_line(line);
+    if theme.is_none() {
+        debug!(
+            "Dropped unparseable sentiment theme for {}: {}",
+            symbol, line
+        );
+    }
+    theme
+}
+
+// None when the name or weight is missing or the weight is outside 0..100%
+fn parse_theme_line(line: &str) -> Option<Theme> {
+    let (name, rest) = line.strip_prefix("- **")?.split_once(":**")?;
+    // Some summaries put the weight inside the bold name, e.g., "- **Adoption (45%):** ..."
+    let name = name.split(" (").next()?.trim();
+    // Split on the ASCII delimiters rather than byte offsets, so accented names and dashes are safe
+    let (_, after_paren) = line.split_once('(')?;
+    let (weight, after_weight) = after_paren.split_once("%)")?;
+    let weight = weight.trim().parse::<f64>().ok()? / 100.0;
+    if name.is_empty() || !(0.0..=1.0).contains(&weight) {
+        return None;
+    }
+    let description = if rest.contains("%)") {
+        after_weight
+    } else {
+        rest
+    };
+    Some(Theme {
+        name: name.to_string(),
+        weight,
+        description: description
+            .trim_start_matches([':', '*'])
+            .trim()
+            .to_string(),
+    })
+}
+
+// First 200 characters of a response body on one line, for error messages
+fn body_snippet(body: &str) -> String {
+    body.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(200)
+        .collect()
+}
+
+// Maps a provider score onto the 0..1 scale that thresholds and displays assume
+pub fn normalize_sentiment(value: f64, scale: &str) -> f64 {
+    let unit = match scale {
+        "signed" => (value + 1.0) / 2.0,
+        _ => value,
+    };
+    unit.clamp(0.0, 1.0)
+}
+
+pub const NEUTRAL_SENTIMENT: f64 = 0.5;
+
+// sentiment.on_missing for a failed fetch: "neutral" scores it 0.5, "error" aborts the cycle,
+// and "skip" leaves the symbol without a score
+pub fn resolve_missing_sentiment(
+    config: &SentimentConfig,
+    symbol: &str,
+    error: PortfolioError,
+) -> Result<Option<f64>, PortfolioError> {
+    match config.on_missing.as_str() {
+        "neutral" => {
+            let _ = log_action(
+                &format!(
+                    "{}: No sentiment available, using neutral: {}",
+                    symbol, error
+                ),
+                None,
+            );
+            Ok(Some(NEUTRAL_SENTIMENT))
+        }
+        "error" => Err(PortfolioError::ApiError(format!(
+            "{}: No sentiment available: {}",
+            symbol, error
+        ))),
+        _ => {
+            let _ = log_action(
+                &format!("{}: No sentiment available: {}", symbol, error),
+                None,
+            );
+            Ok(None)
+        }
+    }
+}
+
+// Used when sentiment is disabled or no API key is configured; every symbol reads as neutral
+pub struct NullSentimentProvider;
+
+#[async_trait]
+impl SentimentProvider for NullSentimentProvider {
+    async fn fetch_sentiment(&self, _symbol: &str) -> Result<f64, PortfolioError> {
+        Ok(NEUTRAL_SENTIMENT)
+    }
+
+    async fn fetch_detailed_sentiment(
+        &self,
+        _symbol: &str,
+    ) -> Result<DetailedSentiment, PortfolioError> {
+        Ok(DetailedSentiment {
+            current_value: NEUTRAL_SENTIMENT,
+            ..Default::default()
+        })
+    }
+
+    fn is_enabled(&self) -> bool {
+        false
+    }
+
+    fn name(&self) -> &str {
+        "none"
+    }
+}
+
+pub fn create_sentiment_provider(config: &SentimentConfig) -> Box<dyn SentimentProvider> {
+    if !config.enabled || config.api_key.is_empty() {
+        let _ = log_action(
+            "Sentiment provider disabled, using neutral sentiment for all symbols",
+            None,
+        );
+        Box::new(NullSentimentProvider)
+    } else {
+        Box::new(LunarCrushProvider::new(
+            &config.api_url,
+            &config.api_key,
+            &config.scale,
+            config.network_weights.clone(),
+            config.debug_raw,
+            &config.response_format,
+        ))
+    }
+}
+
+// Order increments for one Binance pair, from the LOT_SIZE filter; only market orders are
+// placed, so PRICE_FILTER's tick size never applies
+#[derive(Debug, Clone)]
+pub struct SymbolFilters {
+    pub step_size: f64,
+    pub min_qty: f64,
+    pub quantity_decimals: usize, // Places in step_size, so rounded quantities print without float noise
+}
+
+impl SymbolFilters {
+    // Rounds down to a whole number of steps; None when that falls below min_qty
+    pub fn round_quantity(&self, quantity: f64) -> Option<f64> {
+        let rounded = if self.step_size > 0.0 {
+            (quantity / self.step_size + 1e-9).floor() * self.step_size
+        } else {
+            quantity
+        };
+        (rounded >= self.min_qty && rounded > 0.0).then_some(rounded)
+    }
+}
+
+// "0.00100000" -> 3
+fn decimal_places(value: &str) -> usize {
+    value
+        .split_once('.')
+        .map(|(_, fraction)| fraction.trim_end_matches('0').len())
+        .unwrap_or(0)
 }
 
 pub struct BinanceExchange {
@@ -289,7 +759,11 @@ pub struct BinanceExchange {
     pub api_key: String,
     pub api_secret: String,
     pub api_url: String,
+    pub taker_fee_pct: f64,
     symbol_map: HashMap<String, String>, // Maps app symbols (e.g., "PHA") to Binance symbols (e.g., "PHAUSDT")
+    quote_assets: Vec<String>, // Fallback quotes tried as {SYMBOL}{QUOTE}, e.g., ["USDT", "BTC"]
+    exchange_info: OnceCell<HashMap<String, SymbolFilters>>, // Trading pairs from /api/v3/exchangeInfo, fetched once
+    decimal_prices: bool, // Parse ticker prices straight into Decimal rather than through f64
 }
 
 impl BinanceExchange {
@@ -297,28 +771,243 @@ impl BinanceExchange {
         api_url: &str,
         api_key: &str,
         api_secret: &str,
+        taker_fee_pct: f64,
         symbol_map: HashMap<String, String>,
+        quote_assets: Vec<String>,
+        decimal_prices: bool,
     ) -> Self {
         BinanceExchange {
             api_url: api_url.to_string(),
             api_key: api_key.to_string(),
             api_secret: api_secret.to_string(),
+            taker_fee_pct,
             client: Client::new(),
             symbol_map,
+            quote_assets: quote_assets.iter().map(|q| q.to_uppercase()).collect(),
+            exchange_info: OnceCell::new(),
+            decimal_prices,
         }
     }
-}
 
-impl Exchange for BinanceExchange {
-    async fn fetch_price(&self, symbol: &str) -> Result<f64, PortfolioError> {
-        let binance_symbol = self.symbol_map.get(symbol).ok_or_else(|| {
-            PortfolioError::ApiError(format!("Symbol {} not supported by Binance", symbol))
+    // Orders always trade the USDT pair so fills are in USD terms
+    fn order_pair(&self, symbol: &str) -> String {
+        self.symbol_map
+            .get(symbol)
+            .cloned()
+            .unwrap_or_else(|| format!("{}USDT", symbol.to_uppercase()))
+    }
+
+    // Pairs currently TRADING with their filters; requested on first use and reused, as it rarely changes
+    pub async fn load_exchange_info(
+        &self,
+    ) -> Result<&HashMap<String, SymbolFilters>, PortfolioError> {
+        self.exchange_info
+            .get_or_try_init(|| async {
+                #[derive(Deserialize)]
+                struct ExchangeInfo {
+                    symbols: Vec<ExchangeInfoSymbol>,
+                }
+
+                #[derive(Deserialize)]
+                struct ExchangeInfoSymbol {
+                    symbol: String,
+                    status: String,
+                    filters: Vec<serde_json::Value>,
+                }
+
+                let url = format!("{}/api/v3/exchangeInfo", self.api_url);
+                let _permit = request_permit().await;
+                let started = Instant::now();
+                let response = self.client.get(&url).send().await.map_err(|e| {
+                    PortfolioError::ApiError(format!(
+                        "Failed to fetch Binance exchange info: {}",
+                        e.without_url()
+                    ))
+                });
+                log_elapsed("binance fetch_exchange_info", started);
+                record_api_call("binance", started);
+                let response = response?;
+                if !response.status().is_success() {
+                    let status = response.status();
+                    let body = response.text().await.unwrap_or_default();
+                    return Err(PortfolioError::ApiError(format!(
+                        "Binance exchange info request failed with {}: {}",
+                        status,
+                        describe_binance_error(&body)
+                    )));
+                }
+                let info: ExchangeInfo = response.json().await.map_err(|e| {
+                    PortfolioError::ApiError(format!(
+                        "Failed to parse Binance exchange info: {}",
+                        e.without_url()
+                    ))
+                })?;
+
+                let filter_value = |filters: &[serde_json::Value], kind: &str, field: &str| {
+                    filters
+                        .iter()
+                        .find(|filter| filter["filterType"] == kind)
+                        .and_then(|filter| filter[field].as_str())
+                        .map(str::to_string)
+                };
+                let mut pairs = HashMap::new();
+                for symbol in info.symbols {
+                    if symbol.status != "TRADING" {
+                        continue;
+                    }
+                    let step = filter_value(&symbol.filters, "LOT_SIZE", "stepSize");
+                    let number = |value: Option<String>| {
+                        value.and_then(|v| v.parse::<f64>().ok()).unwrap_or(0.0)
+                    };
+                    pairs.insert(
+                        symbol.symbol,
+                        SymbolFilters {
+                            quantity_decimals: step.as_deref().map(decimal_places).unwrap_or(8),
+                            step_size: number(step),
+                            min_qty: number(filter_value(&symbol.filters, "LOT_SIZE", "minQty")),
+                        },
+                    );
+                }
+                Ok::<_, PortfolioError>(pairs)
+            })
+            .await
+    }
+
+    // Candidate (pair, quote) list: the mapped USDT pair first, then each configured quote asset
+    fn candidate_pairs(&self, symbol: &str) -> Vec<(String, String)> {
+        let mut pairs = Vec::new();
+        if let Some(mapped) = self.symbol_map.get(symbol) {
+            pairs.push((mapped.clone(), "USDT".to_string()));
+        }
+        for quote in &self.quote_assets {
+            let pair = format!("{}{}", symbol.to_uppercase(), quote);
+            if !pairs.iter().any(|(existing, _)| *existing == pair) {
+                pairs.push((pair, quote.clone()));
+            }
+        }
+        pairs
+    }
+
+    async fn request_price(&self, symbol: &str) -> Result<Decimal, PortfolioError> {
+        let pairs = self.candidate_pairs(symbol);
+        if pairs.is_empty() {
+            return Err(PortfolioError::UnknownSymbolError(format!(
+                "Symbol {} not supported by Binance",
+                symbol
+            )));
+        }
+
+        let mut errors = Vec::new();
+        let mut all_unknown = true; // Every pair was rejected as an invalid symbol
+        for (pair, quote) in &pairs {
+            let price = match self.fetch_pair_price(symbol, pair).await {
+                Ok(price) => price,
+                Err(e) => {
+                    all_unknown &= matches!(e, PortfolioError::UnknownSymbolError(_));
+                    errors.push(e.to_string());
+                    continue;
+                }
+            };
+            // Non-USDT quotes are converted through the quote's own USDT pair, e.g., BTCUSDT;
+            // a failed conversion counts against this pair like a failed price
+            let usd_price = if quote == "USDT" {
+                price
+            } else {
+                let quote_pair = format!("{}USDT", quote);
+                match self.fetch_pair_price(quote, &quote_pair).await {
+                    Ok(quote_price) => price * quote_price,
+                    Err(e) => {
+                        all_unknown = false;
+                        errors.push(e.to_string());
+                        continue;
+                    }
+                }
+            };
+            let _ = log_action(
+                &format!("{}: Binance price ${:.6} via {}", symbol, usd_price, pair),
+                None,
+            );
+            return Ok(usd_price);
+        }
+        let message = format!(
+            "No Binance pair could price {}: {}",
+            symbol,
+            errors.join("; ")
+        );
+        Err(if all_unknown {
+            PortfolioError::UnknownSymbolError(message)
+        } else {
+            PortfolioError::ApiError(message)
+        })
+    }
+
+    // Always the USDT pair, like orders, so the stats are in USD terms
+    async fn request_24h_stats(&self, symbol: &str) -> Result<Ticker24h, PortfolioError> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct BinanceTicker {
+            price_change: String,
+            price_change_percent: String,
+            high_price: String,
+            low_price: String,
+            quote_volume: String,
+        }
+
+        let pair = self.order_pair(symbol);
+        let url = format!("{}/api/v3/ticker/24hr?symbol={}", self.api_url, pair);
+        let _permit = request_permit().await;
+        let response = self.client.get(&url).send().await.map_err(|e| {
+            PortfolioError::ApiError(format!(
+                "Failed to fetch 24h stats for {}: {}",
+                symbol,
+                e.without_url()
+            ))
+        })?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(PortfolioError::ApiError(format!(
+                "Binance 24h ticker request for {} ({}) failed with {}: {}",
+                symbol,
+                pair,
+                status,
+                describe_binance_error(&body)
+            )));
+        }
+        let ticker: BinanceTicker = response.json().await.map_err(|e| {
+            PortfolioError::ApiError(format!(
+                "Failed to parse Binance 24h ticker for {}: {}",
+                symbol,
+                e.without_url()
+            ))
         })?;
+        let number = |value: &str| {
+            value.parse::<f64>().map_err(|e| {
+                PortfolioError::ApiError(format!(
+                    "Invalid 24h ticker value {:?} for {}: {}",
+                    value, symbol, e
+                ))
+            })
+        };
+        Ok(Ticker24h {
+            price_change: number(&ticker.price_change)?,
+            price_change_percent: number(&ticker.price_change_percent)?,
+            high: number(&ticker.high_price)?,
+            low: number(&ticker.low_price)?,
+            quote_volume: number(&ticker.quote_volume)?,
+        })
+    }
 
+    async fn fetch_pair_price(
+        &self,
+        symbol: &str,
+        binance_symbol: &str,
+    ) -> Result<Decimal, PortfolioError> {
         let url = format!(
             "{}/api/v3/ticker/price?symbol={}",
             self.api_url, binance_symbol
         );
+        let _permit = request_permit().await;
         let response = self
             .client
             .get(&url)
@@ -326,67 +1015,1234 @@ impl Exchange for BinanceExchange {
             .send()
             .await
             .map_err(|e| {
-                PortfolioError::ApiError(format!("Failed to fetch price for {}: {}", symbol, e))
+                PortfolioError::ApiError(format!(
+                    "Failed to fetch price for {}: {}",
+                    symbol,
+                    e.without_url()
+                ))
             })?;
 
         #[derive(Deserialize)]
         struct BinancePrice {
-            symbol: String,
             price: String,
         }
 
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            let message = format!(
+                "Binance price request for {} ({}) failed with {}: {}",
+                symbol,
+                binance_symbol,
+                status,
+                describe_binance_error(&body)
+            );
+            return Err(if is_invalid_symbol(&body) {
+                PortfolioError::UnknownSymbolError(message)
+            } else {
+                PortfolioError::ApiError(message)
+            });
+        }
+
         let price_data: BinancePrice = response.json().await.map_err(|e| {
             PortfolioError::ApiError(format!(
                 "Failed to parse Binance price JSON for {}: {}",
-                symbol, e
+                symbol,
+                e.without_url()
             ))
         })?;
 
-        let price = price_data.price.parse::<f64>().map_err(|e| {
+        let parse_error = |e: String| {
             PortfolioError::ApiError(format!("Failed to parse price for {}: {}", symbol, e))
-        })?;
-
-        Ok(price)
+        };
+        if self.decimal_prices {
+            price_data
+                .price
+                .parse::<Decimal>()
+                .map_err(|e| parse_error(e.to_string()))
+        } else {
+            let price = price_data
+                .price
+                .parse::<f64>()
+                .map_err(|e| parse_error(e.to_string()))?;
+            to_decimal(price)
+        }
     }
 }
 
-pub fn create_exchange(config: &ExchangeConfig) -> BinanceExchange {
-    match config.name.as_str() {
-        "binance" => {
-            // Define symbol mappings for Binance
-            let mut symbol_map = HashMap::new();
-            symbol_map.insert("PHA".to_string(), "PHAUSDT".to_string());
-            symbol_map.insert("SUI".to_string(), "SUIUSDT".to_string());
-            symbol_map.insert("DUSK".to_string(), "DUSKUSDT".to_string());
-
-            BinanceExchange::new(
-                &config.base_url,
-                &config.api_key,
-                &config.api_secret,
-                symbol_map,
-            )
-        }
-        _ => {
-            let _ = log_action(&format!("Unsupported exchange: {}", config.name), None);
-            panic!("Unsupported exchange: {}", config.name)
-        }
+impl BinanceExchange {
+    // HMAC-SHA256 of the query string keyed by api_secret, hex-encoded as Binance expects
+    pub fn sign_query(&self, query: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.api_secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(query.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
     }
-}
 
-pub trait SentimentProvider {
-    async fn fetch_sentiment(&self, symbol: &str) -> Result<f64, PortfolioError>;
-    async fn fetch_detailed_sentiment(
-        &self,
-        symbol: &str,
-    ) -> Result<DetailedSentiment, PortfolioError>;
+    // Free plus locked amount per asset (e.g., "SUI" -> 10.0), skipping empty balances
+    pub async fn fetch_account_balances(&self) -> Result<HashMap<String, f64>, PortfolioError> {
+        let query = format!("timestamp={}", chrono::Utc::now().timestamp_millis());
+        let url = format!(
+            "{}/api/v3/account?{}&signature={}",
+            self.api_url,
+            query,
+            self.sign_query(&query)
+        );
+        let _permit = request_permit().await;
+        let started = Instant::now();
+        let response = self
+            .client
+            .get(&url)
+            .header("X-MBX-APIKEY", &self.api_key)
+            .send()
+            .await
+            .map_err(|e| {
+                PortfolioError::ApiError(format!(
+                    "Failed to fetch Binance account: {}",
+                    e.without_url()
+                ))
+            });
+        log_elapsed("binance fetch_account_balances", started);
+        record_api_call("binance", started);
+        let response = response?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(PortfolioError::ApiError(format!(
+                "Binance account request failed with {}: {}",
+                status,
+                describe_binance_error(&body)
+            )));
+        }
+
+        #[derive(Deserialize)]
+        struct BinanceBalance {
+            asset: String,
+            free: String,
+            locked: String,
+        }
+
+        #[derive(Deserialize)]
+        struct BinanceAccount {
+            balances: Vec<BinanceBalance>,
+        }
+
+        let account: BinanceAccount = response.json().await.map_err(|e| {
+            PortfolioError::ApiError(format!(
+                "Failed to parse Binance account JSON: {}",
+                e.without_url()
+            ))
+        })?;
+        let mut balances = HashMap::new();
+        for balance in account.balances {
+            let total = balance.free.parse::<f64>().unwrap_or(0.0)
+                + balance.locked.parse::<f64>().unwrap_or(0.0);
+            if total > 0.0 {
+                balances.insert(balance.asset, total);
+            }
+        }
+        Ok(balances)
+    }
+
+    async fn place_market_order(
+        &self,
+        symbol: &str,
+        side: &str,
+        quantity: f64,
+    ) -> Result<OrderFill, PortfolioError> {
+        let pair = self.order_pair(symbol);
+        // Binance rejects quantities that aren't whole LOT_SIZE steps
+        let filters = self.load_exchange_info().await?.get(&pair).ok_or_else(|| {
+            PortfolioError::ExchangeError(format!("Binance pair {} is not trading", pair))
+        })?;
+        let rounded = filters.round_quantity(quantity).ok_or_else(|| {
+            PortfolioError::ExchangeError(format!(
+                "{} {} is below Binance's minimum order quantity {} for {}",
+                quantity, symbol, filters.min_qty, pair
+            ))
+        })?;
+        let query = format!(
+            "symbol={}&side={}&type=MARKET&quantity={:.*}&timestamp={}",
+            pair,
+            side,
+            filters.quantity_decimals,
+            rounded,
+            chrono::Utc::now().timestamp_millis()
+        );
+        let url = format!(
+            "{}/api/v3/order?{}&signature={}",
+            self.api_url,
+            query,
+            self.sign_query(&query)
+        );
+        let _permit = request_permit().await;
+        let response = self
+            .client
+            .post(&url)
+            .header("X-MBX-APIKEY", &self.api_key)
+            .send()
+            .await
+            .map_err(|e| {
+                PortfolioError::ExchangeError(format!(
+                    "Failed to place {} order for {}: {}",
+                    side,
+                    symbol,
+                    e.without_url()
+                ))
+            })?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(PortfolioError::ExchangeError(format!(
+                "Binance {} order for {} ({}) failed with {}: {}",
+                side,
+                symbol,
+                pair,
+                status,
+                describe_binance_error(&body)
+            )));
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct BinanceOrder {
+            order_id: i64,
+            executed_qty: String,
+            cummulative_quote_qty: String, // Binance's spelling
+        }
+
+        let order: BinanceOrder = response.json().await.map_err(|e| {
+            PortfolioError::ExchangeError(format!(
+                "Failed to parse Binance order response for {}: {}",
+                symbol,
+                e.without_url()
+            ))
+        })?;
+        let parse = |value: &str| {
+            value.parse::<f64>().map_err(|e| {
+                PortfolioError::ExchangeError(format!(
+                    "Failed to parse order fill for {}: {}",
+                    symbol, e
+                ))
+            })
+        };
+        let fill = OrderFill {
+            order_id: order.order_id,
+            executed_quantity: parse(&order.executed_qty)?,
+            quote_quantity: parse(&order.cummulative_quote_qty)?,
+        };
+        let _ = log_action(
+            &format!(
+                "Binance {} order {} for {} {} filled at ${:.6}",
+                side,
+                fill.order_id,
+                fill.executed_quantity,
+                pair,
+                fill.average_price()
+            ),
+            None,
+        );
+        Ok(fill)
+    }
 }
 
-#[derive(serde::Deserialize)]
-struct SentimentResponse {
-    sentiment: f64,
+#[async_trait]
+impl OrderExecutor for BinanceExchange {
+    async fn place_market_sell(
+        &self,
+        symbol: &str,
+        quantity: f64,
+    ) -> Result<OrderFill, PortfolioError> {
+        self.place_market_order(symbol, "SELL", quantity).await
+    }
+
+    async fn place_market_buy(
+        &self,
+        symbol: &str,
+        quantity: f64,
+    ) -> Result<OrderFill, PortfolioError> {
+        self.place_market_order(symbol, "BUY", quantity).await
+    }
+
+    async fn validate_symbols(&self, symbols: &[String]) -> Result<(), PortfolioError> {
+        let pairs = self.load_exchange_info().await?;
+        let unknown: Vec<String> = symbols
+            .iter()
+            .map(|symbol| (symbol, self.order_pair(symbol)))
+            .filter(|(_, pair)| !pairs.contains_key(pair))
+            .map(|(symbol, pair)| format!("{} ({})", symbol, pair))
+            .collect();
+        if unknown.is_empty() {
+            return Ok(());
+        }
+        Err(PortfolioError::ConfigError(format!(
+            "Binance has no trading USDT pair for: {} (live orders need one)",
+            unknown.join(", ")
+        )))
+    }
+}
+
+#[async_trait]
+impl Exchange for BinanceExchange {
+    async fn fetch_price(&self, symbol: &str) -> Result<f64, PortfolioError> {
+        self.fetch_price_exact(symbol).await.map(to_f64)
+    }
+
+    async fn fetch_price_exact(&self, symbol: &str) -> Result<Decimal, PortfolioError> {
+        let started = Instant::now();
+        let result = self.request_price(symbol).await;
+        log_elapsed(&format!("binance fetch_price({})", symbol), started);
+        record_api_call("binance", started);
+        result
+    }
+
+    async fn fetch_24h_stats(&self, symbol: &str) -> Result<Option<Ticker24h>, PortfolioError> {
+        let started = Instant::now();
+        let result = self.request_24h_stats(symbol).await;
+        log_elapsed(&format!("binance fetch_24h_stats({})", symbol), started);
+        record_api_call("binance", started);
+        result.map(Some)
+    }
+
+    fn name(&self) -> &str {
+        "binance"
+    }
+
+    fn taker_fee_pct(&self) -> f64 {
+        self.taker_fee_pct
+    }
 }
 
+// Binance reports failures as {"code":-1121,"msg":"Invalid symbol."}
 #[derive(Deserialize)]
-struct PriceResponse {
-    price: f64,
+struct BinanceError {
+    code: i64,
+    msg: String,
+}
+
+// The error code for a pair Binance doesn't list
+const BINANCE_INVALID_SYMBOL: i64 = -1121;
+
+// Message and code from the error body; falls back to the raw body
+fn describe_binance_error(body: &str) -> String {
+    match serde_json::from_str::<BinanceError>(body) {
+        Ok(err) => format!("{} (code {})", err.msg, err.code),
+        Err(_) => body.trim().to_string(),
+    }
+}
+
+fn is_invalid_symbol(body: &str) -> bool {
+    serde_json::from_str::<BinanceError>(body).is_ok_and(|err| err.code == BINANCE_INVALID_SYMBOL)
+}
+
+pub struct CoinGeckoExchange {
+    client: Client,
+    api_url: String,
+    api_key: String,
+    id_map: HashMap<String, String>, // Maps app symbols (e.g., "PHA") to CoinGecko ids (e.g., "phala-network")
+}
+
+impl CoinGeckoExchange {
+    pub fn new(api_url: &str, api_key: &str, id_map: HashMap<String, String>) -> Self {
+        CoinGeckoExchange {
+            client: Client::new(),
+            api_url: api_url.to_string(),
+            api_key: api_key.to_string(),
+            id_map,
+        }
+    }
+
+    async fn request_price(&self, symbol: &str) -> Result<f64, PortfolioError> {
+        let id = self
+            .id_map
+            .get(symbol)
+            .cloned()
+            .unwrap_or_else(|| symbol.to_lowercase());
+        let url = format!("{}/simple/price?ids={}&vs_currencies=usd", self.api_url, id);
+        let mut request = self
+            .client
+            .get(&url)
+            .header(USER_AGENT, "crypto_portfolio/0.1");
+        if !self.api_key.is_empty() {
+            request = request.header("x-cg-demo-api-key", &self.api_key);
+        }
+        let _permit = request_permit().await;
+        let response = request.send().await.map_err(|e| {
+            PortfolioError::ApiError(format!(
+                "Failed to fetch price for {}: {}",
+                symbol,
+                e.without_url()
+            ))
+        })?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(PortfolioError::ApiError(format!(
+                "CoinGecko price request for {} ({}) failed with {}: {}",
+                symbol,
+                id,
+                status,
+                body.trim()
+            )));
+        }
+
+        // {"phala-network":{"usd":0.12}}
+        let prices: HashMap<String, HashMap<String, f64>> = response.json().await.map_err(|e| {
+            PortfolioError::ApiError(format!(
+                "Failed to parse CoinGecko price JSON for {}: {}",
+                symbol,
+                e.without_url()
+            ))
+        })?;
+
+        prices
+            .get(&id)
+            .and_then(|quotes| quotes.get("usd"))
+            .copied()
+            .ok_or_else(|| {
+                PortfolioError::UnknownSymbolError(format!(
+                    "CoinGecko returned no USD price for {}",
+                    id
+                ))
+            })
+    }
+}
+
+#[async_trait]
+impl Exchange for CoinGeckoExchange {
+    async fn fetch_price(&self, symbol: &str) -> Result<f64, PortfolioError> {
+        let started = Instant::now();
+        let result = self.request_price(symbol).await;
+        log_elapsed(&format!("coingecko fetch_price({})", symbol), started);
+        record_api_call("coingecko", started);
+        result
+    }
+
+    fn name(&self) -> &str {
+        "coingecko"
+    }
+}
+
+// Tries each source in order until one answers; orders and fees follow the first source
+pub struct PriceChain {
+    sources: Vec<Box<dyn Exchange>>,
+    name: String, // Source names joined by "+", so differently ordered chains cache apart
+    answered: Mutex<HashMap<String, String>>, // Symbol -> source that priced it last
+}
+
+impl PriceChain {
+    pub fn new(sources: Vec<Box<dyn Exchange>>) -> Self {
+        let name = sources
+            .iter()
+            .map(|source| source.name())
+            .collect::<Vec<_>>()
+            .join("+");
+        PriceChain {
+            sources,
+            name,
+            answered: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl Exchange for PriceChain {
+    async fn fetch_price(&self, symbol: &str) -> Result<f64, PortfolioError> {
+        self.fetch_price_exact(symbol).await.map(to_f64)
+    }
+
+    async fn fetch_price_exact(&self, symbol: &str) -> Result<Decimal, PortfolioError> {
+        let mut errors = Vec::new();
+        let mut all_unknown = true; // Every source answered that it doesn't list the symbol
+        for source in &self.sources {
+            match source.fetch_price_exact(symbol).await {
+                Ok(price) => {
+                    let _ = log_action(
+                        &format!("{}: Price ${:.2} from {}", symbol, price, source.name()),
+                        None,
+                    );
+                    self.answered
+                        .lock()
+                        .unwrap()
+                        .insert(symbol.to_string(), source.name().to_string());
+                    return Ok(price);
+                }
+                Err(e) => {
+                    let _ = log_action(
+                        &format!(
+                            "{}: {} failed, trying the next source: {}",
+                            symbol,
+                            source.name(),
+                            e
+                        ),
+                        None,
+                    );
+                    all_unknown &= matches!(e, PortfolioError::UnknownSymbolError(_));
+                    errors.push(format!("{}: {}", source.name(), e));
+                }
+            }
+        }
+        let message = format!(
+            "No price source could price {}: {}",
+            symbol,
+            errors.join("; ")
+        );
+        Err(if all_unknown {
+            PortfolioError::UnknownSymbolError(message)
+        } else {
+            PortfolioError::ApiError(message)
+        })
+    }
+
+    // First source with stats; a failing source is logged and skipped like a failed price
+    async fn fetch_24h_stats(&self, symbol: &str) -> Result<Option<Ticker24h>, PortfolioError> {
+        for source in &self.sources {
+            match source.fetch_24h_stats(symbol).await {
+                Ok(Some(stats)) => return Ok(Some(stats)),
+                Ok(None) => {}
+                Err(e) => {
+                    let _ = log_action(
+                        &format!("{}: {} has no 24h stats: {}", symbol, source.name(), e),
+                        None,
+                    );
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn taker_fee_pct(&self) -> f64 {
+        self.sources
+            .first()
+            .map(|source| source.taker_fee_pct())
+            .unwrap_or(0.0)
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn answered_by(&self, symbol: &str) -> String {
+        self.answered
+            .lock()
+            .unwrap()
+            .get(symbol)
+            .cloned()
+            .unwrap_or_else(|| self.name.clone())
+    }
+}
+
+fn binance_exchange(config: &ExchangeConfig) -> BinanceExchange {
+    // Define symbol mappings for Binance
+    let mut symbol_map = HashMap::new();
+    symbol_map.insert("PHA".to_string(), "PHAUSDT".to_string());
+    symbol_map.insert("SUI".to_string(), "SUIUSDT".to_string());
+    symbol_map.insert("DUSK".to_string(), "DUSKUSDT".to_string());
+
+    BinanceExchange::new(
+        &config.base_url,
+        &config.api_key,
+        &config.api_secret,
+        config.taker_fee_pct,
+        symbol_map,
+        config.quote_assets.clone(),
+        config.decimal_prices,
+    )
+}
+
+pub fn create_exchange(config: &ExchangeConfig) -> Box<dyn Exchange> {
+    match config.name.as_str() {
+        "binance" => Box::new(binance_exchange(config)),
+        "coingecko" => {
+            // Define id mappings for CoinGecko
+            let mut id_map = HashMap::new();
+            id_map.insert("PHA".to_string(), "phala-network".to_string());
+            id_map.insert("SUI".to_string(), "sui".to_string());
+            id_map.insert("DUSK".to_string(), "dusk-network".to_string());
+
+            Box::new(CoinGeckoExchange::new(
+                &config.base_url,
+                &config.api_key,
+                id_map,
+            ))
+        }
+        _ => {
+            let _ = log_action(&format!("Unsupported exchange: {}", config.name), None);
+            panic!("Unsupported exchange: {}", config.name)
+        }
+    }
+}
+
+// None keeps trading simulated; live trading needs a Binance entry with API credentials
+pub fn create_order_executor(
+    config: &Config,
+) -> Result<Option<Box<dyn OrderExecutor>>, PortfolioError> {
+    if !config.portfolio.live_trading {
+        return Ok(None);
+    }
+    let binance = create_binance_account(config)?;
+    let _ = log_action(
+        &format!("LIVE TRADING ENABLED: orders go to {}", binance.api_url),
+        None,
+    );
+    Ok(Some(Box::new(binance)))
+}
+
+// Binance client for signed endpoints (orders, balances); needs api_key and api_secret
+pub fn create_binance_account(config: &Config) -> Result<BinanceExchange, PortfolioError> {
+    let binance = config
+        .exchanges
+        .iter()
+        .find(|exchange| exchange.name == "binance")
+        .ok_or_else(|| {
+            PortfolioError::ConfigError(
+                "Signed Binance requests need an [[exchanges]] entry named 'binance'".to_string(),
+            )
+        })?;
+    if binance.api_key.is_empty() || binance.api_secret.is_empty() {
+        return Err(PortfolioError::ConfigError(
+            "Signed Binance requests need the Binance api_key and api_secret".to_string(),
+        ));
+    }
+    Ok(binance_exchange(binance))
+}
+
+// Symbols no configured price source lists, e.g., a "SUII" typo; checked before loops start.
+// Outages and rate limits say nothing about the symbol, so those are only logged
+pub async fn unresolvable_symbols(exchange: &dyn Exchange, symbols: &[String]) -> Vec<String> {
+    let mut missing = Vec::new();
+    for symbol in symbols {
+        match exchange.fetch_price(symbol).await {
+            Ok(_) => {}
+            Err(e @ PortfolioError::UnknownSymbolError(_)) => {
+                let _ = log_action(
+                    &format!(
+                        "{}: Unresolvable symbol on {}: {}",
+                        symbol,
+                        exchange.name(),
+                        e
+                    ),
+                    None,
+                );
+                missing.push(symbol.clone());
+            }
+            Err(e) => {
+                let _ = log_action(
+                    &format!(
+                        "{}: Couldn't check the symbol on {}, continuing: {}",
+                        symbol,
+                        exchange.name(),
+                        e
+                    ),
+                    None,
+                );
+            }
+        }
+    }
+    missing
+}
+
+// Builds the pricing.sources chain; without it, the first configured exchange prices everything
+pub fn create_price_source(config: &Config) -> Result<Box<dyn Exchange>, PortfolioError> {
+    if config.pricing.sources.is_empty() {
+        return Ok(create_exchange(&config.exchanges[0]));
+    }
+
+    let mut sources = Vec::new();
+    for name in &config.pricing.sources {
+        let exchange_config = config
+            .exchanges
+            .iter()
+            .chain(std::iter::once(&config.marketprovider))
+            .find(|exchange| &exchange.name == name)
+            .ok_or_else(|| {
+                PortfolioError::ConfigError(format!(
+                    "Pricing source '{}' has no matching [[exchanges]] or [marketprovider] entry",
+                    name
+                ))
+            })?;
+        sources.push(create_exchange(exchange_config));
+    }
+    Ok(Box::new(PriceChain::new(sources)))
+}
+
+#[async_trait]
+pub trait SentimentProvider: Send + Sync {
+    async fn fetch_sentiment(&self, symbol: &str) -> Result<f64, PortfolioError>;
+    async fn fetch_detailed_sentiment(
+        &self,
+        symbol: &str,
+    ) -> Result<DetailedSentiment, PortfolioError>;
+
+    // Whether scores reflect real data and may drive sell decisions
+    fn is_enabled(&self) -> bool {
+        true
+    }
+
+    // Provider identifier for cache keys, e.g., "lunarcrush"
+    fn name(&self) -> &str;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::testing::mock_server;
+
+    // Bound and dropped straight away, so connecting to it is refused
+    fn closed_port_url() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}", listener.local_addr().unwrap())
+    }
+
+    #[tokio::test]
+    async fn sentiment_fetch_error_omits_api_key() {
+        let provider = LunarCrushProvider::new(
+            &closed_port_url(),
+            "secret-key",
+            "unit",
+            HashMap::new(),
+            false,
+            "markdown",
+        );
+        let error = provider.fetch_raw_sentiment("SUI").await.unwrap_err();
+        assert!(!error.to_string().contains("secret-key"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn signed_request_error_omits_query() {
+        let binance = BinanceExchange::new(
+            &closed_port_url(),
+            "key",
+            "secret",
+            0.1,
+            HashMap::new(),
+            Vec::new(),
+            false,
+        );
+        let error = binance.fetch_account_balances().await.unwrap_err();
+        assert!(!error.to_string().contains("signature="), "{}", error);
+    }
+
+    #[test]
+    fn sign_query_matches_binance_example() {
+        // The HMAC example from Binance's signed endpoint documentation
+        let binance = BinanceExchange::new(
+            "http://unused",
+            "vmPUZE6mv9SD5VNHk4HlWFsOr6aKE2zvsw0MuIgwCIPy6utIco14y7Ju91duEh8A",
+            "NhqPtmdSJYdKjVHjA7PZj4Mge3R5YNiP1e3UZjInClVN65XAbvqqM6A7H5fATj0j",
+            0.1,
+            HashMap::new(),
+            Vec::new(),
+            false,
+        );
+        let query = "symbol=LTCBTC&side=BUY&type=LIMIT&timeInForce=GTC&quantity=1&price=0.1\
+                     &recvWindow=5000&timestamp=1499827319559";
+        assert_eq!(
+            binance.sign_query(query),
+            "c8db56825ae71d6d79447849e617115f4a920fa2acdcab2b053c4b2838bd6b71"
+        );
+    }
+
+    const EXCHANGE_INFO: &str = r#"{"symbols":[
+        {"symbol":"SUIUSDT","status":"TRADING","filters":[
+            {"filterType":"PRICE_FILTER","tickSize":"0.00010000"},
+            {"filterType":"LOT_SIZE","stepSize":"0.10000000","minQty":"0.10000000"}]},
+        {"symbol":"DUSKUSDT","status":"BREAK","filters":[
+            {"filterType":"LOT_SIZE","stepSize":"1.00000000","minQty":"1.00000000"}]}
+    ]}"#;
+
+    fn mock_binance(url: &str) -> BinanceExchange {
+        BinanceExchange::new(
+            url,
+            "key",
+            "secret",
+            0.1,
+            HashMap::new(),
+            vec!["USDT".to_string()],
+            false,
+        )
+    }
+
+    #[tokio::test]
+    async fn exchange_info_keeps_trading_pairs_and_lot_sizes() {
+        let (url, _) = mock_server(|_| (200, EXCHANGE_INFO.to_string())).await;
+        let binance = mock_binance(&url);
+        let pairs = binance.load_exchange_info().await.unwrap();
+        assert!(!pairs.contains_key("DUSKUSDT"));
+        let sui = &pairs["SUIUSDT"];
+        assert_eq!(sui.quantity_decimals, 1);
+        assert!((sui.round_quantity(1.27).unwrap() - 1.2).abs() < 1e-9);
+        assert_eq!(sui.round_quantity(0.05), None);
+
+        let error = binance
+            .validate_symbols(&["SUI".to_string(), "DUSK".to_string()])
+            .await
+            .unwrap_err();
+        assert!(matches!(error, PortfolioError::ConfigError(_)));
+        assert!(error.to_string().contains("DUSK (DUSKUSDT)"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn failed_quote_conversion_falls_through_to_the_next_pair() {
+        let (url, _) = mock_server(|request| {
+            if request.contains("symbol=SUIBTC ") {
+                (200, r#"{"symbol":"SUIBTC","price":"0.00004"}"#.to_string())
+            } else if request.contains("symbol=SUIUSDT ") {
+                (200, r#"{"symbol":"SUIUSDT","price":"3.50"}"#.to_string())
+            } else {
+                (400, r#"{"code":-1121,"msg":"Invalid symbol."}"#.to_string())
+            }
+        })
+        .await;
+        let mut binance = mock_binance(&url);
+        binance.quote_assets = vec!["BTC".to_string(), "USDT".to_string()];
+        let price = binance.fetch_price_exact("SUI").await.unwrap();
+        assert_eq!(price, Decimal::new(350, 2));
+    }
+
+    #[tokio::test]
+    async fn market_orders_round_to_the_lot_size_and_report_the_fill() {
+        let (url, mut requests) = mock_server(|request| {
+            if request.starts_with("GET /api/v3/exchangeInfo") {
+                (200, EXCHANGE_INFO.to_string())
+            } else {
+                (
+                    200,
+                    r#"{"orderId":42,"executedQty":"10.20000000","cummulativeQuoteQty":"35.70000000"}"#
+                        .to_string(),
+                )
+            }
+        })
+        .await;
+        let fill = mock_binance(&url)
+            .place_market_sell("SUI", 10.27)
+            .await
+            .unwrap();
+        assert_eq!(fill.order_id, 42);
+        assert_eq!(fill.executed_quantity, 10.2);
+        assert_eq!(fill.quote_quantity, 35.7);
+
+        requests.recv().await.unwrap(); // exchangeInfo
+        let order = requests.recv().await.unwrap();
+        assert!(order
+            .starts_with("POST /api/v3/order?symbol=SUIUSDT&side=SELL&type=MARKET&quantity=10.2&"));
+        assert!(order.contains("&signature="));
+    }
+
+    // Real orders on the Binance spot testnet; run with BINANCE_TESTNET_API_KEY and
+    // BINANCE_TESTNET_API_SECRET set and `cargo test -- --ignored`
+    #[tokio::test]
+    #[ignore]
+    async fn testnet_market_orders_fill() {
+        let var =
+            |name: &str| std::env::var(name).unwrap_or_else(|_| panic!("{} is not set", name));
+        let binance = BinanceExchange::new(
+            "https://testnet.binance.vision",
+            &var("BINANCE_TESTNET_API_KEY"),
+            &var("BINANCE_TESTNET_API_SECRET"),
+            0.1,
+            HashMap::new(),
+            vec!["USDT".to_string()],
+            true,
+        );
+        binance
+            .validate_symbols(&["BNB".to_string()])
+            .await
+            .unwrap();
+        let bought = binance.place_market_buy("BNB", 0.05).await.unwrap();
+        assert!(bought.executed_quantity > 0.0);
+        assert!(bought.quote_quantity > 0.0);
+        let sold = binance
+            .place_market_sell("BNB", bought.executed_quantity)
+            .await
+            .unwrap();
+        assert!(sold.executed_quantity > 0.0);
+        assert!(sold.quote_quantity > 0.0);
+    }
+
+    #[tokio::test]
+    async fn decimal_prices_trigger_a_stop_loss_f64_rounds_away() {
+        // The nearest f64 to this price is exactly 1.0, level with the stop
+        let (url, _) = mock_server(|_| {
+            (
+                200,
+                r#"{"symbol":"SUIUSDT","price":"0.99999999999999999"}"#.to_string(),
+            )
+        })
+        .await;
+        let stop_loss = to_decimal(1.0).unwrap();
+        let mut binance = mock_binance(&url);
+        assert!(binance.fetch_price_exact("SUI").await.unwrap() >= stop_loss);
+        binance.decimal_prices = true;
+        assert!(binance.fetch_price_exact("SUI").await.unwrap() < stop_loss);
+    }
+
+    // Prices every symbol at `price`, or fails when it is None
+    struct StubExchange {
+        name: &'static str,
+        price: Option<f64>,
+    }
+
+    #[async_trait]
+    impl Exchange for StubExchange {
+        async fn fetch_price(&self, _symbol: &str) -> Result<f64, PortfolioError> {
+            self.price
+                .ok_or_else(|| PortfolioError::ApiError(format!("{} is down", self.name)))
+        }
+
+        fn name(&self) -> &str {
+            self.name
+        }
+    }
+
+    #[test]
+    fn exchanges_report_their_names() {
+        assert_eq!(mock_binance("http://localhost").name(), "binance");
+        let coingecko = CoinGeckoExchange::new("http://localhost", "", HashMap::new());
+        assert_eq!(coingecko.name(), "coingecko");
+    }
+
+    #[tokio::test]
+    async fn chain_reports_the_source_that_answered() {
+        let chain = PriceChain::new(vec![
+            Box::new(StubExchange {
+                name: "binance",
+                price: None,
+            }),
+            Box::new(StubExchange {
+                name: "coingecko",
+                price: Some(3.5),
+            }),
+        ]);
+        assert_eq!(chain.name(), "binance+coingecko");
+        assert_eq!(chain.answered_by("SUI"), "binance+coingecko");
+        assert_eq!(chain.fetch_price("SUI").await.unwrap(), 3.5);
+        assert_eq!(chain.answered_by("SUI"), "coingecko");
+    }
+
+    #[tokio::test]
+    async fn chain_takes_the_first_source_that_answers() {
+        let stub = |name, price| -> Box<dyn Exchange> { Box::new(StubExchange { name, price }) };
+        let chain = PriceChain::new(vec![
+            stub("kraken", None),
+            stub("binance", Some(3.5)),
+            stub("coingecko", Some(3.6)),
+        ]);
+        assert_eq!(chain.fetch_price("SUI").await.unwrap(), 3.5);
+        assert_eq!(chain.answered_by("SUI"), "binance");
+
+        let down = PriceChain::new(vec![stub("binance", None), stub("coingecko", None)]);
+        let error = down.fetch_price("SUI").await.unwrap_err().to_string();
+        assert!(
+            error.contains("binance is down; coingecko: ") && error.ends_with("coingecko is down"),
+            "{}",
+            error
+        );
+    }
+
+    fn sentiment_config(on_missing: &str) -> SentimentConfig {
+        toml::from_str(&format!(
+            "enabled = true\napi_key = \"\"\ncache_ttl_secs = 60\npositive_threshold = 0.7\n\
+             negative_threshold = 0.3\non_missing = \"{}\"",
+            on_missing
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn both_scales_land_in_the_same_buckets() {
+        let bands = sentiment_config("skip").bands_for("SUI");
+        let bucket = |value, scale| {
+            crate::display::recommendation(normalize_sentiment(value, scale), &bands).0
+        };
+        for (unit, signed, expected) in [
+            (0.9, 0.8, "Hold/Buy"),
+            (0.5, 0.0, "Monitor"),
+            (0.1, -0.8, "Sell"),
+        ] {
+            assert_eq!(bucket(unit, "unit"), expected);
+            assert_eq!(bucket(signed, "signed"), expected);
+        }
+        assert_eq!(normalize_sentiment(-1.0, "signed"), 0.0);
+        assert_eq!(normalize_sentiment(1.0, "signed"), 1.0);
+    }
+
+    #[test]
+    fn missing_sentiment_follows_on_missing() {
+        let missing = || PortfolioError::ApiError("timed out".to_string());
+        let resolve =
+            |policy: &str| resolve_missing_sentiment(&sentiment_config(policy), "SUI", missing());
+        assert_eq!(resolve("neutral").unwrap(), Some(NEUTRAL_SENTIMENT));
+        assert_eq!(resolve("skip").unwrap(), None);
+        assert!(resolve("error").is_err());
+    }
+
+    #[tokio::test]
+    async fn binance_error_bodies_are_surfaced() {
+        let (url, _) = mock_server(|_| {
+            (
+                429,
+                r#"{"code":-1003,"msg":"Too many requests; current limit is 6000."}"#.to_string(),
+            )
+        })
+        .await;
+        let error = mock_binance(&url).fetch_price("SUI").await.unwrap_err();
+        assert!(matches!(error, PortfolioError::ApiError(_)), "{:?}", error);
+        let message = error.to_string();
+        assert!(message.contains("429"), "{}", message);
+        assert!(
+            message.contains("Too many requests; current limit is 6000. (code -1003)"),
+            "{}",
+            message
+        );
+    }
+
+    #[tokio::test]
+    async fn only_unlisted_symbols_are_unresolvable() {
+        let (url, _) = mock_server(|request| {
+            if request.contains("symbol=SUIUSDT ") {
+                (200, r#"{"symbol":"SUIUSDT","price":"3.50"}"#.to_string())
+            } else if request.contains("symbol=SUIIUSDT ") {
+                (400, r#"{"code":-1121,"msg":"Invalid symbol."}"#.to_string())
+            } else {
+                (503, "Service Unavailable".to_string())
+            }
+        })
+        .await;
+        let binance = mock_binance(&url);
+        let symbols = ["SUI", "SUII", "DUSK"].map(String::from);
+        assert_eq!(unresolvable_symbols(&binance, &symbols).await, vec!["SUII"]);
+    }
+
+    #[test]
+    fn network_weights_shift_the_score() {
+        let engagement = |positive: f64, negative: f64| NetworkEngagement {
+            positive_percentage: positive,
+            negative_percentage: negative,
+            ..Default::default()
+        };
+        let detailed = DetailedSentiment {
+            network_engagement: HashMap::from([
+                ("X".to_string(), engagement(80.0, 20.0)),
+                ("Reddit".to_string(), engagement(20.0, 80.0)),
+            ]),
+            ..Default::default()
+        };
+        let weights = |x: f64, reddit: f64| {
+            HashMap::from([("x".to_string(), x), ("reddit".to_string(), reddit)])
+        };
+        let score =
+            |weights: &HashMap<String, f64>| detailed.network_weighted_score(weights).unwrap();
+
+        assert!((score(&weights(1.0, 1.0)) - 0.5).abs() < 1e-9);
+        assert!((score(&weights(3.0, 1.0)) - 0.65).abs() < 1e-9);
+        assert!((score(&weights(1.0, 3.0)) - 0.35).abs() < 1e-9);
+        // A network weighted zero drops out entirely
+        assert!((score(&weights(1.0, 0.0)) - 0.8).abs() < 1e-9);
+        assert_eq!(detailed.network_weighted_score(&HashMap::new()), None);
+    }
+
+    #[tokio::test]
+    async fn html_error_pages_are_errors_and_data_pages_parse() {
+        let (url, _) = mock_server(|request| {
+            let page = |body: &str| format!("<html><body>\n{}\n</body></html>", body);
+            if request.starts_with("GET /topic/down/") {
+                (503, page("<h1>503 Service Unavailable</h1>"))
+            } else if request.starts_with("GET /topic/login/") {
+                (200, page("<h1>Please sign in</h1>"))
+            } else {
+                (200, page("**Current Value**: 72%\n**Daily Average**: 65%"))
+            }
+        })
+        .await;
+        let provider =
+            LunarCrushProvider::new(&url, "key", "unit", HashMap::new(), false, "markdown");
+
+        let error = provider.fetch_sentiment("DOWN").await.unwrap_err();
+        assert!(error.to_string().contains("503"), "{}", error);
+        assert!(
+            error.to_string().contains("Service Unavailable"),
+            "{}",
+            error
+        );
+        let error = provider.fetch_sentiment("LOGIN").await.unwrap_err();
+        assert!(
+            error.to_string().contains("no **Current Value**"),
+            "{}",
+            error
+        );
+
+        let detailed = provider.fetch_detailed_sentiment("SUI").await.unwrap();
+        assert!((detailed.current_value - 0.72).abs() < 1e-9);
+        assert!((detailed.daily_average - 0.65).abs() < 1e-9);
+        assert!((provider.fetch_sentiment("SUI").await.unwrap() - 0.72).abs() < 1e-9);
+    }
+
+    #[test]
+    fn sentiment_dates_parse_or_keep_their_text() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 21).unwrap();
+        for text in [
+            "Mar 11, 2024",
+            "March 11, 2024.",
+            "2024-03-11",
+            "03/11/2024",
+        ] {
+            let date = SentimentDate::parse(text);
+            assert_eq!(date.date, NaiveDate::from_ymd_opt(2024, 3, 11), "{}", text);
+            assert_eq!(date.days_before(today), Some(10));
+            assert_eq!(date.to_string(), "2024-03-11");
+        }
+
+        // Shown as LunarCrush wrote it, with no age to report
+        let date = SentimentDate::parse(" sometime last spring ");
+        assert_eq!(date.date, None);
+        assert_eq!(date.days_before(today), None);
+        assert_eq!(date.to_string(), "sometime last spring");
+    }
+
+    #[test]
+    fn json_sentiment_fills_the_struct() {
+        let body = r#"{
+            "current_value": 0.72,
+            "daily_average": 0.65,
+            "one_week_value": 0.7,
+            "one_week_change": 0.03,
+            "one_year_high": 0.91,
+            "one_year_high_date": "2024-03-11",
+            "supportive_themes": [{"name": "Adoption", "weight": 0.45, "description": "Growing use"}],
+            "network_engagement": {
+                "X": {"positive": "1200", "positive_percentage": 60.0, "negative_percentage": 10.0}
+            }
+        }"#;
+        let sentiment = LunarCrushProvider::parse_json_sentiment("SUI", body).unwrap();
+        assert_eq!(sentiment.current_value, 0.72);
+        assert_eq!(sentiment.one_week_change, 0.03);
+        assert_eq!(sentiment.one_year_high_date.to_string(), "2024-03-11");
+        assert_eq!(sentiment.supportive_themes[0].name, "Adoption");
+        assert_eq!(sentiment.supportive_themes[0].weight, 0.45);
+        assert!(sentiment.critical_themes.is_empty());
+        assert_eq!(sentiment.network_engagement["X"].total_engagement(), 1200.0);
+
+        let error = LunarCrushProvider::parse_json_sentiment("SUI", r#"{"daily_average": 0.5}"#)
+            .unwrap_err();
+        assert!(error.to_string().contains("no current_value"), "{}", error);
+    }
+
+    #[test]
+    fn bodyless_sentiment_responses_are_errors() {
+        for body in [
+            "",
+            "<html><head><title>LunarCrush</title></head></html>",
+            "<body>  </body>",
+        ] {
+            let error = LunarCrushProvider::parse_detailed_sentiment("SUI", body).unwrap_err();
+            assert!(
+                error
+                    .to_string()
+                    .contains("Empty or bodyless sentiment response for SUI"),
+                "{:?}: {}",
+                body,
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn malformed_theme_lines_are_dropped() {
+        let theme = parse_theme_line("- **Adoption:** (45%) Growing use").unwrap();
+        assert_eq!(
+            (
+                theme.name.as_str(),
+                theme.weight,
+                theme.description.as_str()
+            ),
+            ("Adoption", 0.45, "Growing use")
+        );
+        let theme = parse_theme_line("- **Adoption (45%):** Growing use").unwrap();
+        assert_eq!((theme.name.as_str(), theme.weight), ("Adoption", 0.45));
+
+        for line in [
+            "- **Adoption:** Growing use",         // No weight
+            "- **Adoption:** (lots%) Growing use", // Weight isn't a number
+            "- **Adoption:** (145%) Growing use",  // Weight over 100%
+            "- **:** (45%) Growing use",           // No name
+            "- **Adoption (45%) Growing use",      // Unclosed name
+        ] {
+            assert!(parse_theme_line(line).is_none(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn multibyte_theme_text_parses_without_panicking() {
+        let page = "<html><body>\n**Current Value**: 61%\n**Most Supportive Themes**:\n\
+                    - **Adoption — DeFi:** (45%) Growing use — especially in Zürich\n\
+                    - **Écosystème (30%):** Café payments\n\
+                    **Most Critical Themes**:\n\
+                    - **Régulation:** (—%) Unclear rules\n\
+                    </body></html>";
+        let sentiment = LunarCrushProvider::parse_detailed_sentiment("SUI", page).unwrap();
+        let supportive: Vec<(&str, f64, &str)> = sentiment
+            .supportive_themes
+            .iter()
+            .map(|theme| {
+                (
+                    theme.name.as_str(),
+                    theme.weight,
+                    theme.description.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            supportive,
+            vec![
+                (
+                    "Adoption — DeFi",
+                    0.45,
+                    "Growing use — especially in Zürich"
+                ),
+                ("Écosystème", 0.3, "Café payments"),
+            ]
+        );
+        // The unparseable weight drops the theme rather than panicking on it
+        assert!(sentiment.critical_themes.is_empty());
+    }
+
+    #[test]
+    fn data_quality_scores_how_much_was_populated() {
+        let theme = Theme {
+            name: "Adoption".to_string(),
+            weight: 0.4,
+            description: String::new(),
+        };
+        let full = DetailedSentiment {
+            current_value: 0.7,
+            daily_average: 0.65,
+            one_week_value: 0.6,
+            one_month_value: 0.55,
+            six_months_value: 0.5,
+            one_year_value: 0.45,
+            one_year_high: 0.9,
+            one_year_high_date: SentimentDate::parse("2024-03-11"),
+            one_year_low: 0.2,
+            one_year_low_date: SentimentDate::parse("2023-09-02"),
+            supportive_themes: vec![theme.clone()],
+            critical_themes: vec![theme],
+            network_engagement: HashMap::from([("X".to_string(), NetworkEngagement::default())]),
+            ..Default::default()
+        };
+        assert_eq!(full.data_quality(), 1.0);
+
+        // Only the current value, plus a high whose date didn't parse
+        let sparse = DetailedSentiment {
+            current_value: 0.7,
+            one_year_high: 0.9,
+            one_year_high_date: SentimentDate::parse("last spring"),
+            ..Default::default()
+        };
+        assert!((sparse.data_quality() - 1.0 / 11.0).abs() < 1e-9);
+        assert_eq!(DetailedSentiment::default().data_quality(), 0.0);
+    }
 }
@@ -0,0 +1,203 @@
+use crate::config::HttpConfig;
+use crate::errors::PortfolioError;
+use serde::de::DeserializeOwned;
+use std::sync::OnceLock;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+// Shared cap on in-flight requests to Binance, CoinGecko and LunarCrush
+static LIMITER: OnceLock<Semaphore> = OnceLock::new();
+
+// Largest response body read into memory; see read_body
+static MAX_RESPONSE_BYTES: OnceLock<usize> = OnceLock::new();
+
+// Applies the [http] limits; screens sharing a process load the same config, so the first wins
+pub fn configure(config: &HttpConfig) {
+    let _ = LIMITER.set(limiter(config));
+    let _ = MAX_RESPONSE_BYTES.set(config.max_response_bytes);
+}
+
+fn limiter(config: &HttpConfig) -> Semaphore {
+    let permits = config
+        .max_concurrency
+        .unwrap_or(Semaphore::MAX_PERMITS)
+        .max(1);
+    Semaphore::new(permits)
+}
+
+// Held for the lifetime of one request; unbounded until configure is called
+pub async fn request_permit() -> SemaphorePermit<'static> {
+    LIMITER
+        .get_or_init(|| Semaphore::new(Semaphore::MAX_PERMITS))
+        .acquire()
+        .await
+        .expect("request limiter is never closed")
+}
+
+// Reads the body chunk by chunk, giving up once it passes http.max_response_bytes (0 = no limit),
+// so a misbehaving endpoint can't make us buffer an arbitrarily large response
+pub async fn read_body(response: reqwest::Response, what: &str) -> Result<Vec<u8>, PortfolioError> {
+    let limit = MAX_RESPONSE_BYTES.get().copied().unwrap_or(0);
+    read_body_within(response, what, limit).await
+}
+
+async fn read_body_within(
+    mut response: reqwest::Response,
+    what: &str,
+    limit: usize,
+) -> Result<Vec<u8>, PortfolioError> {
+    let too_large = |size: u64| {
+        PortfolioError::ApiError(format!(
+            "{} response is {} bytes or more, over http.max_response_bytes ({})",
+            what, size, limit
+        ))
+    };
+    if let Some(length) = response.content_length() {
+        if limit > 0 && length > limit as u64 {
+            return Err(too_large(length));
+        }
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| {
+        PortfolioError::ApiError(format!(
+            "Failed to read {} response: {}",
+            what,
+            e.without_url()
+        ))
+    })? {
+        body.extend_from_slice(&chunk);
+        if limit > 0 && body.len() > limit {
+            return Err(too_large(body.len() as u64));
+        }
+    }
+    Ok(body)
+}
+
+// read_body as text; invalid UTF-8 is replaced rather than failing the request
+pub async fn read_text(response: reqwest::Response, what: &str) -> Result<String, PortfolioError> {
+    let body = read_body(response, what).await?;
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+pub async fn read_json<T: DeserializeOwned>(
+    response: reqwest::Response,
+    what: &str,
+) -> Result<T, PortfolioError> {
+    let body = read_body(response, what).await?;
+    serde_json::from_slice(&body)
+        .map_err(|e| PortfolioError::ApiError(format!("Invalid {} response: {}", what, e)))
+}
+
+// Local HTTP/1.1 server for tests: each request (head and body) goes to the handler, whose
+// (status, body) is sent back; the requests are also forwarded to the returned receiver
+#[cfg(test)]
+pub mod testing {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::sync::mpsc;
+
+    pub async fn mock_server<F>(handler: F) -> (String, mpsc::UnboundedReceiver<String>)
+    where
+        F: Fn(&str) -> (u16, String) + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (requests, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let request = read_request(&mut stream).await;
+                let (status, body) = handler(&request);
+                let _ = requests.send(request);
+                let head = format!(
+                    "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                );
+                let _ = stream.write_all(head.as_bytes()).await;
+                let _ = stream.write_all(body.as_bytes()).await;
+            }
+        });
+        (url, receiver)
+    }
+
+    async fn read_request(stream: &mut TcpStream) -> String {
+        let mut request = Vec::new();
+        let mut buffer = [0u8; 4096];
+        loop {
+            let read = stream.read(&mut buffer).await.unwrap_or(0);
+            if read == 0 {
+                break;
+            }
+            request.extend_from_slice(&buffer[..read]);
+            let text = String::from_utf8_lossy(&request);
+            if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                let length = head
+                    .lines()
+                    .filter_map(|line| line.split_once(':'))
+                    .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+                    .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+                    .unwrap_or(0);
+                if body.len() >= length {
+                    break;
+                }
+            }
+        }
+        String::from_utf8_lossy(&request).into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn oversized_bodies_are_rejected() {
+        let (url, _) = testing::mock_server(|_| (200, "x".repeat(64))).await;
+        let response = reqwest::get(&url).await.unwrap();
+        let error = read_body_within(response, "Test", 32).await.unwrap_err();
+        assert!(error.to_string().contains("64 bytes or more"), "{}", error);
+
+        let response = reqwest::get(&url).await.unwrap();
+        assert_eq!(
+            read_body_within(response, "Test", 64).await.unwrap().len(),
+            64
+        );
+    }
+
+    #[tokio::test]
+    async fn in_flight_requests_stay_under_the_cap() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let config = HttpConfig {
+            max_concurrency: Some(2),
+            ..Default::default()
+        };
+        let permits = Arc::new(limiter(&config));
+        let (url, _) = testing::mock_server(|_| (200, "ok".to_string())).await;
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let mut tasks = Vec::new();
+        for _ in 0..6 {
+            let (permits, url) = (permits.clone(), url.clone());
+            let (in_flight, peak) = (in_flight.clone(), peak.clone());
+            tasks.push(tokio::spawn(async move {
+                let _permit = permits.acquire().await.unwrap();
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                reqwest::get(&url).await.unwrap();
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+            }));
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+
+        // Unset leaves requests unbounded
+        assert_eq!(
+            limiter(&HttpConfig::default()).available_permits(),
+            Semaphore::MAX_PERMITS
+        );
+    }
+}
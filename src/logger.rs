@@ -1,27 +1,172 @@
 use crate::errors::PortfolioError;
-use env_logger::Builder;
-use hex;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use env_logger::{Builder, Target};
 use hmac::{Hmac, Mac};
-use log::{info, LevelFilter};
+use log::{debug, info, warn, LevelFilter};
 use sha2::Sha256;
+use std::collections::BTreeMap;
 use std::fs::OpenOptions;
 use std::io::Write;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+tokio::task_local! {
+    // Name of the screen a task belongs to, used to prefix log lines in single-process mode
+    pub static SCREEN: &'static str;
+}
+
+// Zone for displayed timestamps and log lines; UTC until set_timezone is called
+static TIMEZONE: OnceLock<Tz> = OnceLock::new();
+
+// Applies display.timezone (an IANA name like "America/New_York"), falling back to UTC
+pub fn set_timezone(name: &str) {
+    // Screens sharing a process load the same config; the first one wins
+    let _ = TIMEZONE.set(parse_timezone(name));
+}
+
+fn parse_timezone(name: &str) -> Tz {
+    name.parse::<Tz>().unwrap_or_else(|_| {
+        warn!("Invalid display.timezone '{}', using UTC", name);
+        Tz::UTC
+    })
+}
+
+pub fn to_display_zone(instant: DateTime<Utc>) -> DateTime<Tz> {
+    instant.with_timezone(TIMEZONE.get().unwrap_or(&Tz::UTC))
+}
+
+pub fn display_now() -> DateTime<Tz> {
+    to_display_zone(Utc::now())
+}
 
 pub fn init_logger(env: &str) -> Result<(), PortfolioError> {
-    let level = if env == "dev" {
+    init_logger_with_target(env, Target::Stderr)
+}
+
+// Level from -v/--verbose or -q/--quiet, taking precedence over the environment default
+static LEVEL_OVERRIDE: OnceLock<LevelFilter> = OnceLock::new();
+
+// -v => Debug, -vv => Trace, -q => Warn, -qq => Error; conflicting flags keep the default
+pub fn level_override(verbose: usize, quiet: usize) -> Option<LevelFilter> {
+    match (verbose, quiet) {
+        (0, 0) => None,
+        (1, 0) => Some(LevelFilter::Debug),
+        (_, 0) => Some(LevelFilter::Trace),
+        (0, 1) => Some(LevelFilter::Warn),
+        (0, _) => Some(LevelFilter::Error),
+        _ => None,
+    }
+}
+
+pub fn set_level_override(level: LevelFilter) {
+    let _ = LEVEL_OVERRIDE.set(level);
+}
+
+pub fn init_logger_with_target(env: &str, target: Target) -> Result<(), PortfolioError> {
+    let level = log_level(env, LEVEL_OVERRIDE.get().copied());
+    // Screens running in one process each call this; only the first registration wins
+    let _ = Builder::new().filter_level(level).target(target).try_init();
+    Ok(())
+}
+
+// The flag's level when one was given, otherwise Debug in dev and Info elsewhere
+fn log_level(env: &str, level_override: Option<LevelFilter>) -> LevelFilter {
+    level_override.unwrap_or(if env == "dev" {
         LevelFilter::Debug
     } else {
         LevelFilter::Info
-    };
-    Builder::new()
-        .filter_level(level)
-        .try_init()
-        .map_err(|e| PortfolioError::NotificationError(e.to_string()))?;
-    Ok(())
+    })
+}
+
+// logger.dedup: identical consecutive lines within DEDUP_WINDOW are counted instead of written
+static DEDUP: OnceLock<bool> = OnceLock::new();
+const DEDUP_WINDOW: Duration = Duration::from_secs(60);
+
+struct LastLine {
+    text: String,
+    first_seen: Instant,
+    repeats: usize,
+    env: Option<String>, // For writing the pending repeat count at exit
+}
+
+// Keyed by screen ("" outside one), so screens sharing a process don't break up each other's repeats
+static LAST_LINES: Mutex<BTreeMap<&'static str, LastLine>> = Mutex::new(BTreeMap::new());
+
+pub fn set_log_dedup(enabled: bool) {
+    let _ = DEDUP.set(enabled);
+}
+
+// None when the line repeats the previous one and should be swallowed; otherwise how many
+// repeats of the previous line were swallowed and still need reporting
+fn dedup(screen: &'static str, action: &str, env: Option<&str>) -> Option<usize> {
+    let mut lines = LAST_LINES.lock().unwrap();
+    if let Some(line) = lines.get_mut(screen) {
+        if line.text == action && line.first_seen.elapsed() < DEDUP_WINDOW {
+            line.repeats += 1;
+            return None;
+        }
+    }
+    let previous = lines.insert(
+        screen,
+        LastLine {
+            text: action.to_string(),
+            first_seen: Instant::now(),
+            repeats: 0,
+            env: env.map(str::to_string),
+        },
+    );
+    Some(previous.map_or(0, |line| line.repeats))
+}
+
+fn repeated_line(screen: &str, repeats: usize) -> String {
+    let line = format!("(last message repeated {} times)", repeats);
+    if screen.is_empty() {
+        line
+    } else {
+        format!("[{}] {}", screen, line)
+    }
+}
+
+// Writes the repeat counts dedup is still holding back; called on the way out so none are lost
+pub fn flush_log_dedup() {
+    let pending: Vec<(&'static str, usize, Option<String>)> = LAST_LINES
+        .lock()
+        .unwrap()
+        .iter_mut()
+        .filter(|(_, line)| line.repeats > 0)
+        .map(|(screen, line)| (*screen, std::mem::take(&mut line.repeats), line.env.clone()))
+        .collect();
+    for (screen, repeats, env) in pending {
+        let _ = write_log_line(&repeated_line(screen, repeats), env.as_deref());
+    }
+}
+
+// The current task's screen ("" outside one) and `action` prefixed with it, so interleaved
+// single-process output says which screen wrote each line
+fn prefixed(action: &str) -> (&'static str, String) {
+    let screen = SCREEN.try_with(|screen| *screen).unwrap_or("");
+    if screen.is_empty() {
+        (screen, action.to_string())
+    } else {
+        (screen, format!("[{}] {}", screen, action))
+    }
 }
 
 pub fn log_action(action: &str, env: Option<&str>) -> Result<(), PortfolioError> {
-    let timestamp = chrono::Utc::now().to_rfc3339();
+    let (screen, action) = prefixed(action);
+    if DEDUP.get().copied().unwrap_or(false) {
+        match dedup(screen, &action, env) {
+            None => return Ok(()),
+            Some(0) => {}
+            Some(repeats) => write_log_line(&repeated_line(screen, repeats), env)?,
+        }
+    }
+    write_log_line(&action, env)
+}
+
+fn write_log_line(action: &str, env: Option<&str>) -> Result<(), PortfolioError> {
+    let timestamp = display_now().to_rfc3339();
     let log = format!("[{}] {}\n", timestamp, action);
     let env = env.unwrap_or("production");
     info!("{}", action);
@@ -44,3 +189,95 @@ pub fn log_action(action: &str, env: Option<&str>) -> Result<(), PortfolioError>
     }
     Ok(())
 }
+
+// Debug-level latency line for an external call, e.g., "fetch_price(SUI) took 312ms"; returns
+// the milliseconds logged
+pub fn log_elapsed(call: &str, started: Instant) -> u128 {
+    let elapsed = started.elapsed().as_millis();
+    match SCREEN.try_with(|screen| *screen) {
+        Ok(screen) => debug!("[{}] {} took {}ms", screen, call, elapsed),
+        Err(_) => debug!("{} took {}ms", call, elapsed),
+    }
+    elapsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn elapsed_time_covers_a_slow_response() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        // Answers its one request after 150ms
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request).await;
+            tokio::time::sleep(Duration::from_millis(150)).await;
+            let response = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}";
+            let _ = stream.write_all(response.as_bytes()).await;
+        });
+
+        let started = Instant::now();
+        reqwest::get(&url).await.unwrap().text().await.unwrap();
+        let logged = log_elapsed("slow call", started);
+        assert!((150..1000).contains(&logged), "{}ms", logged);
+    }
+
+    #[tokio::test]
+    async fn screen_tasks_prefix_their_lines() {
+        assert_eq!(prefixed("tick"), ("", "tick".to_string()));
+        let line = SCREEN.scope("portfolio", async { prefixed("tick") }).await;
+        assert_eq!(line, ("portfolio", "[portfolio] tick".to_string()));
+    }
+
+    #[test]
+    fn dedup_tracks_each_screen_separately() {
+        assert_eq!(dedup("test-a", "[test-a] tick", None), Some(0));
+        assert_eq!(dedup("test-b", "[test-b] tock", None), Some(0));
+        assert_eq!(dedup("test-a", "[test-a] tick", None), None);
+        assert_eq!(dedup("test-b", "[test-b] tock", None), None);
+        assert_eq!(dedup("test-a", "[test-a] tick", None), None);
+        assert_eq!(dedup("test-a", "[test-a] done", None), Some(2));
+        assert_eq!(dedup("test-b", "[test-b] done", None), Some(1));
+    }
+
+    #[test]
+    fn flush_clears_pending_repeats() {
+        dedup("test-flush", "[test-flush] tick", None);
+        dedup("test-flush", "[test-flush] tick", None);
+        flush_log_dedup();
+        assert_eq!(LAST_LINES.lock().unwrap()["test-flush"].repeats, 0);
+        assert_eq!(dedup("test-flush", "[test-flush] next", None), Some(0));
+    }
+
+    #[test]
+    fn timestamps_convert_to_the_configured_zone() {
+        let instant = "2024-01-15T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let tz = parse_timezone("America/New_York");
+        assert_eq!(
+            instant.with_timezone(&tz).to_rfc3339(),
+            "2024-01-15T07:00:00-05:00"
+        );
+        let tz = parse_timezone("Asia/Tokyo");
+        assert_eq!(
+            instant.with_timezone(&tz).to_rfc3339(),
+            "2024-01-15T21:00:00+09:00"
+        );
+        assert_eq!(parse_timezone("Mars/Olympus_Mons"), Tz::UTC);
+    }
+
+    #[test]
+    fn level_flags_override_the_environment_default() {
+        assert_eq!(log_level("dev", None), LevelFilter::Debug);
+        assert_eq!(log_level("prod", None), LevelFilter::Info);
+        assert_eq!(log_level("prod", level_override(1, 0)), LevelFilter::Debug);
+        assert_eq!(log_level("prod", level_override(2, 0)), LevelFilter::Trace);
+        assert_eq!(log_level("dev", level_override(0, 1)), LevelFilter::Warn);
+        assert_eq!(log_level("dev", level_override(0, 2)), LevelFilter::Error);
+        // -v and -q together cancel out
+        assert_eq!(log_level("dev", level_override(1, 1)), LevelFilter::Debug);
+    }
+}
@@ -1,15 +1,32 @@
-use crate::config::load_config;
-use crate::database::Database;
-use crate::display::{display_portfolio, display_sentiment_screen};
+use crate::config::{load_config, write_example_config, Config, DisplayConfig};
+use crate::database::{ChangeBaseline, Database};
+use crate::display::{
+    changed_symbols, display_pending_sells, display_prices, display_reconciliation,
+    display_sentiment_screen, display_stats, fetch_progress, render_portfolio,
+};
 use crate::errors::PortfolioError;
-use crate::exchange::{create_exchange, create_sentiment_provider, Exchange, SentimentProvider};
-use crate::logger::{init_logger, log_action};
-use crate::market::{display_market_screen, MarketProvider};
+use crate::exchange::{
+    create_binance_account, create_order_executor, create_price_source, create_sentiment_provider,
+    normalize_sentiment, resolve_missing_sentiment, to_decimal, to_f64, unresolvable_symbols,
+    Exchange, LunarCrushProvider, QUOTE_CURRENCY,
+};
+use crate::logger::{
+    flush_log_dedup, init_logger, init_logger_with_target, level_override, log_action,
+    set_level_override, set_log_dedup, set_timezone, SCREEN,
+};
+use crate::market::{display_market_screen, display_watchlist_screen, MarketProvider};
 use crate::notification::Notifier;
-use crate::portfolio::Portfolio;
+use crate::portfolio::{Portfolio, Quote, TradeContext};
+use crate::report::{compute_report, display_report, write_trade_ledger};
+use crate::suggest::{display_suggestions, suggest, SuggestInput};
+use chrono::{DateTime, NaiveDate, Utc};
 use dotenv::dotenv;
-use std::collections::HashMap;
+use env_logger::Target;
+use indicatif::ProgressBar;
+use std::collections::{HashMap, HashSet};
 use std::process::{Child, Command};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinSet;
 use tokio::time::{sleep, Duration};
 
 mod config;
@@ -17,30 +34,142 @@ mod database;
 mod display;
 mod errors;
 mod exchange;
+mod http;
 mod logger;
 mod market;
 mod notification;
 mod portfolio;
+mod report;
+mod server;
+mod stats;
+mod suggest;
 
-async fn portfolio_screen() -> Result<(), PortfolioError> {
+async fn portfolio_screen(diff: bool) -> Result<(), PortfolioError> {
     let config = load_config()?;
     init_logger(&config.environment)?;
+    set_timezone(&config.display.timezone);
+    set_log_dedup(config.logger.dedup);
+    http::configure(&config.http);
     let env = Some(config.environment.as_str());
-    let db = Database::new(&config.database.postgres_url, &config.redis.url).await?;
-    let exchange = create_exchange(&config.exchanges[0]);
-    let sentiment_provider =
-        create_sentiment_provider(&config.sentiment.api_url, &config.sentiment.api_key);
+    let db = Database::new(&config.database, &config.redis, true).await?;
+    let exchange = create_price_source(&config)?;
+    let orders = create_order_executor(&config)?;
+    let sentiment_provider = create_sentiment_provider(&config.sentiment);
     let notifier = Notifier::new(config.notification.clone());
-    let mut portfolio = Portfolio::new(config.portfolio.clone());
-    let mut previous_value = 0.0;
-    let mut previous_prices = HashMap::new();
-    let mut previous_sentiments = HashMap::new();
+    let mut portfolio = Portfolio::load(config.portfolio.clone(), &db).await?;
+    let symbols: Vec<String> = portfolio
+        .holdings
+        .iter()
+        .map(|holding| holding.symbol.clone())
+        .collect();
+    let missing = unresolvable_symbols(exchange.as_ref(), &symbols).await;
+    if !missing.is_empty() {
+        return Err(PortfolioError::ConfigError(format!(
+            "No configured price source resolves: {} (check portfolio.holdings for typos)",
+            missing.join(", ")
+        )));
+    }
+    if let Some(orders) = &orders {
+        orders.validate_symbols(&symbols).await?;
+    }
+    // The push server lives with the screen that produces the events
+    if config.server.enabled {
+        let bind = config.server.bind.clone();
+        tokio::spawn(async move {
+            if let Err(e) = server::serve(&bind).await {
+                let _ = log_action(&format!("Push server stopped: {}", e), None);
+            }
+        });
+    }
+    // With portfolio.persist_baseline, change detection picks up where the last run left off
+    let mut baseline = if config.portfolio.persist_baseline {
+        db.load_change_baseline().await?.unwrap_or_default()
+    } else {
+        ChangeBaseline::default()
+    };
+    // Restored prices stand in for failed fetches, so one older than
+    // notification.max_data_age_secs would already be stale data
+    let baseline_age = (Utc::now() - baseline.saved_at)
+        .to_std()
+        .unwrap_or_default();
+    let max_data_age = config.notification.max_data_age_secs;
+    if baseline.value > 0.0 && max_data_age > 0 && baseline_age.as_secs() > max_data_age {
+        log_action(
+            &format!(
+                "Ignoring change baseline saved {}s ago (limit {}s)",
+                baseline_age.as_secs(),
+                max_data_age
+            ),
+            env,
+        )?;
+        baseline = ChangeBaseline::default();
+    }
+    if baseline.value > 0.0 {
+        log_action(
+            &format!(
+                "Restored change baseline: portfolio value ${:.2}",
+                baseline.value
+            ),
+            env,
+        )?;
+    }
+    // Last time each symbol got a price that wasn't a carried-over fallback; a restored price
+    // is as old as the baseline it came from
+    let restored_at = Instant::now()
+        .checked_sub(baseline_age)
+        .unwrap_or_else(Instant::now);
+    let mut last_fresh: HashMap<String, Instant> = symbols
+        .iter()
+        .map(|symbol| {
+            let fresh = if baseline.prices.contains_key(symbol) {
+                restored_at
+            } else {
+                Instant::now()
+            };
+            (symbol.clone(), fresh)
+        })
+        .collect();
 
     loop {
+        // portfolio.trading_enabled can be flipped in config.toml without a restart
+        if let Ok(latest) = load_config() {
+            if latest.portfolio.trading_enabled != portfolio.config.trading_enabled {
+                log_action(
+                    &format!(
+                        "Trading {}",
+                        if latest.portfolio.trading_enabled {
+                            "resumed"
+                        } else {
+                            "paused"
+                        }
+                    ),
+                    env,
+                )?;
+                portfolio.config.trading_enabled = latest.portfolio.trading_enabled;
+            }
+        }
+        let ctx = TradeContext {
+            exchange: exchange.as_ref(),
+            orders: orders.as_deref(),
+            db: &db,
+            notifier: &notifier,
+        };
         let mut sentiments = HashMap::new();
-        let mut current_prices = HashMap::new();
+        let mut substituted = HashSet::new(); // Scored by sentiment.on_missing rather than fetched
+        let mut cycle_error = None;
+        // Fetched once here; check_portfolio trades on and values the portfolio at the same quotes
+        let mut quotes = HashMap::new();
+        // Headless checks (portfolio.display_enabled = false) skip the progress bar too
+        let progress = if config.portfolio.display_enabled {
+            fetch_progress(portfolio.holdings.len(), &config.display)
+        } else {
+            ProgressBar::hidden()
+        };
         for holding in &portfolio.holdings {
-            if let Some(cached_price) = db.get_cached_price(&holding.symbol).await? {
+            if let Some(cached_price) = db
+                .get_cached_price(exchange.name(), QUOTE_CURRENCY, &holding.symbol)
+                .await?
+            {
                 log_action(
                     &format!(
                         "{}: Using cached price ${:.2}",
@@ -48,17 +177,76 @@ async fn portfolio_screen() -> Result<(), PortfolioError> {
                     ),
                     env,
                 )?;
-                current_prices.insert(holding.symbol.clone(), cached_price);
+                quotes.insert(
+                    holding.symbol.clone(),
+                    Quote {
+                        price: to_decimal(cached_price)?,
+                        live: true,
+                    },
+                );
             } else {
-                let price = exchange.fetch_price(&holding.symbol).await?;
-                db.cache_price(&holding.symbol, price).await?;
-                log_action(
-                    &format!("{}: Fetched price ${:.2}", holding.symbol, price),
-                    env,
-                )?;
-                current_prices.insert(holding.symbol.clone(), price);
+                match exchange.fetch_price_exact(&holding.symbol).await {
+                    Ok(exact_price) => {
+                        let price = to_f64(exact_price);
+                        db.cache_price(exchange.name(), QUOTE_CURRENCY, &holding.symbol, price)
+                            .await?;
+                        db.record_price(&holding.symbol, price).await?;
+                        log_action(
+                            &format!(
+                                "{}: Fetched price ${:.2} from {}",
+                                holding.symbol,
+                                price,
+                                exchange.answered_by(&holding.symbol)
+                            ),
+                            env,
+                        )?;
+                        last_fresh.insert(holding.symbol.clone(), Instant::now());
+                        quotes.insert(
+                            holding.symbol.clone(),
+                            Quote {
+                                price: exact_price,
+                                live: true,
+                            },
+                        );
+                    }
+                    // Keep showing the last known price, without trading on it; the staleness
+                    // alert below flags it
+                    Err(e) => match baseline.prices.get(&holding.symbol) {
+                        Some(&last_price) => {
+                            log_action(
+                                &format!(
+                                    "{}: Failed to fetch price from {}, keeping ${:.2}: {}",
+                                    holding.symbol,
+                                    exchange.name(),
+                                    last_price,
+                                    e
+                                ),
+                                env,
+                            )?;
+                            quotes.insert(
+                                holding.symbol.clone(),
+                                Quote {
+                                    price: to_decimal(last_price)?,
+                                    live: false,
+                                },
+                            );
+                        }
+                        // Nothing to value it at, so this cycle is skipped once the alerts have run
+                        None => {
+                            cycle_error.get_or_insert(e);
+                        }
+                    },
+                }
             }
-            if let Some(cached_sentiment) = db.get_cached_sentiment(&holding.symbol).await? {
+            let age = last_fresh
+                .get(&holding.symbol)
+                .map(Instant::elapsed)
+                .unwrap_or_default();
+            notifier.notify_stale_data(&holding.symbol, age).await?;
+            if let Some(cached_sentiment) = db
+                .get_cached_sentiment(sentiment_provider.name(), &holding.symbol)
+                .await?
+            {
                 sentiments.insert(holding.symbol.clone(), cached_sentiment);
                 log_action(
                     &format!(
@@ -68,212 +256,1401 @@ async fn portfolio_screen() -> Result<(), PortfolioError> {
                     env,
                 )?;
             } else {
-                let sentiment = sentiment_provider.fetch_sentiment(&holding.symbol).await?;
-                db.cache_sentiment(&holding.symbol, sentiment, config.sentiment.cache_ttl_secs)
-                    .await?;
-                sentiments.insert(holding.symbol.clone(), sentiment);
+                match sentiment_provider.fetch_sentiment(&holding.symbol).await {
+                    Ok(sentiment) => {
+                        db.cache_sentiment(
+                            sentiment_provider.name(),
+                            &holding.symbol,
+                            sentiment,
+                            config.sentiment.cache_ttl_secs,
+                        )
+                        .await?;
+                        sentiments.insert(holding.symbol.clone(), sentiment);
+                        log_action(
+                            &format!("{}: Fetched sentiment {:.2}", holding.symbol, sentiment),
+                            env,
+                        )?;
+                    }
+                    // "skip" leaves it out of the map so it renders as N/A
+                    Err(e) => {
+                        match resolve_missing_sentiment(&config.sentiment, &holding.symbol, e) {
+                            Ok(Some(sentiment)) => {
+                                sentiments.insert(holding.symbol.clone(), sentiment);
+                                substituted.insert(holding.symbol.clone());
+                            }
+                            Ok(None) => {}
+                            Err(e) => {
+                                cycle_error = Some(e);
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+            progress.inc(1);
+        }
+
+        let checked = match cycle_error {
+            Some(e) => Err(e),
+            None => {
+                portfolio
+                    .check_portfolio(
+                        &ctx,
+                        &quotes,
+                        sentiment_provider.as_ref(),
+                        &config.sentiment,
+                        &baseline,
+                    )
+                    .await
+            }
+        };
+        // e.g., sentiment.on_missing = "error": only this cycle is dropped, and the baseline
+        // stays as it was so the next cycle compares against the last complete one
+        let total_value = match checked {
+            Ok(total_value) => total_value,
+            Err(e) => {
+                progress.finish_and_clear();
                 log_action(
-                    &format!("{}: Fetched sentiment {:.2}", holding.symbol, sentiment),
+                    &format!("Portfolio check failed, retrying next cycle: {}", e),
                     env,
                 )?;
+                persist_stats();
+                wait_for_next_cycle(&config.refresh_mode, config.portfolio.check_interval_secs)
+                    .await;
+                continue;
             }
-        }
+        };
 
-        let total_value = portfolio
-            .check_portfolio(
-                &exchange,
-                &sentiment_provider,
-                &db,
-                &notifier,
-                config.sentiment.negative_threshold,
-                previous_value,
-                &previous_prices,
-                &previous_sentiments,
+        // The same quotes total_value came from; sold holdings drop out of both
+        let current_prices: HashMap<String, f64> = portfolio
+            .holdings
+            .iter()
+            .filter_map(|holding| {
+                let quote = quotes.get(&holding.symbol)?;
+                Some((holding.symbol.clone(), to_f64(quote.price)))
+            })
+            .collect();
+        let changed = diff.then(|| {
+            changed_symbols(
+                &baseline.prices,
+                &current_prices,
+                &baseline.sentiments,
+                &sentiments,
             )
-            .await?;
+        });
+        baseline = ChangeBaseline {
+            value: total_value,
+            prices: current_prices.clone(),
+            // A stand-in score isn't a reading; kept as the baseline it would fake a change next cycle
+            sentiments: sentiments
+                .iter()
+                .filter(|(symbol, _)| !substituted.contains(*symbol))
+                .map(|(symbol, sentiment)| (symbol.clone(), *sentiment))
+                .collect(),
+            saved_at: Utc::now(),
+        };
+        if config.portfolio.persist_baseline {
+            db.save_change_baseline(&baseline).await?;
+        }
 
-        previous_value = total_value;
-        previous_prices = current_prices.clone();
-        previous_sentiments = sentiments.clone();
+        if let Some(path) = &config.portfolio.snapshot_path {
+            portfolio.write_snapshot(path, total_value, &current_prices, &sentiments)?;
+        }
+        server::publish(server::Event::Cycle(portfolio.snapshot(
+            total_value,
+            &current_prices,
+            &sentiments,
+        )));
 
-        display_portfolio(&portfolio, total_value, &sentiments);
+        progress.finish_and_clear();
+        if let Some(screen) = cycle_screen(
+            &config,
+            &portfolio,
+            total_value,
+            &current_prices,
+            &sentiments,
+            changed.as_ref(),
+        ) {
+            println!("{}", screen);
+        }
         log_action(&format!("Portfolio value: ${:.2}", total_value), env)?;
 
-        sleep(Duration::from_secs(config.portfolio.check_interval_secs)).await;
+        persist_stats();
+        wait_for_next_cycle(&config.refresh_mode, config.portfolio.check_interval_secs).await;
     }
 }
 
-async fn sentiment_screen() -> Result<(), PortfolioError> {
+// This cycle's portfolio table, or None for headless checks (portfolio.display_enabled = false)
+fn cycle_screen(
+    config: &Config,
+    portfolio: &Portfolio,
+    total_value: f64,
+    prices: &HashMap<String, f64>,
+    sentiments: &HashMap<String, f64>,
+    changed: Option<&HashSet<String>>,
+) -> Option<String> {
+    config.portfolio.display_enabled.then(|| {
+        render_portfolio(
+            portfolio,
+            total_value,
+            prices,
+            sentiments,
+            changed,
+            &config.display,
+        )
+    })
+}
+
+async fn sentiment_screen(symbols: Option<Vec<String>>) -> Result<(), PortfolioError> {
     let config = load_config()?;
     init_logger(&config.environment)?;
+    set_timezone(&config.display.timezone);
+    set_log_dedup(config.logger.dedup);
+    http::configure(&config.http);
     let env = Some(config.environment.as_str());
-    let db = Database::new(&config.database.postgres_url, &config.redis.url).await?;
-    let sentiment_provider =
-        create_sentiment_provider(&config.sentiment.api_url, &config.sentiment.api_key);
-    let portfolio = Portfolio::new(config.portfolio.clone());
+    let db = Database::new(&config.database, &config.redis, false).await?;
+    let sentiment_provider = create_sentiment_provider(&config.sentiment);
+    // --symbols replaces the holdings for one-off lookups
+    let symbols = match symbols {
+        Some(symbols) => symbols,
+        None => holding_symbols(&config).await?,
+    };
 
     loop {
         let mut sentiments = HashMap::new();
-        for holding in &portfolio.holdings {
-            if let Some(cached_sentiment) = db.get_cached_sentiment(&holding.symbol).await? {
-                sentiments.insert(holding.symbol.clone(), cached_sentiment);
+        let progress = fetch_progress(symbols.len(), &config.display);
+        for symbol in &symbols {
+            if let Some(cached_sentiment) = db
+                .get_cached_sentiment(sentiment_provider.name(), symbol)
+                .await?
+            {
+                sentiments.insert(symbol.clone(), cached_sentiment);
                 log_action(
-                    &format!(
-                        "{}: Using cached sentiment {:.2}",
-                        holding.symbol, cached_sentiment
-                    ),
+                    &format!("{}: Using cached sentiment {:.2}", symbol, cached_sentiment),
                     env,
                 )?;
             } else {
-                let sentiment = sentiment_provider.fetch_sentiment(&holding.symbol).await?;
-                db.cache_sentiment(&holding.symbol, sentiment, config.sentiment.cache_ttl_secs)
-                    .await?;
-                sentiments.insert(holding.symbol.clone(), sentiment);
+                let sentiment = sentiment_provider.fetch_sentiment(symbol).await?;
+                db.cache_sentiment(
+                    sentiment_provider.name(),
+                    symbol,
+                    sentiment,
+                    config.sentiment.cache_ttl_secs,
+                )
+                .await?;
+                sentiments.insert(symbol.clone(), sentiment);
                 log_action(
-                    &format!("{}: Fetched sentiment {:.2}", holding.symbol, sentiment),
+                    &format!("{}: Fetched sentiment {:.2}", symbol, sentiment),
                     env,
                 )?;
             }
+            progress.inc(1);
         }
+        progress.finish_and_clear();
 
         display_sentiment_screen(
-            &portfolio,
+            &symbols,
             &sentiments,
             &db,
-            &sentiment_provider,
-            config.sentiment.positive_threshold,
-            config.sentiment.negative_threshold,
-            config.display.use_colors,
+            sentiment_provider.as_ref(),
+            &config.sentiment,
+            &config.display,
         )
         .await?;
 
-        sleep(Duration::from_secs(config.display.sentiment_refresh_secs)).await;
+        persist_stats();
+        wait_for_next_cycle(&config.refresh_mode, config.display.sentiment_refresh_secs).await;
     }
 }
 
-async fn market_screen() -> Result<(), PortfolioError> {
+async fn prices_screen(symbols: Option<Vec<String>>) -> Result<(), PortfolioError> {
     let config = load_config()?;
     init_logger(&config.environment)?;
+    set_timezone(&config.display.timezone);
+    set_log_dedup(config.logger.dedup);
+    http::configure(&config.http);
+    let exchange = create_price_source(&config)?;
+    let symbols = match symbols {
+        Some(symbols) => symbols,
+        None => holding_symbols(&config).await?,
+    };
+
+    let prices = fetch_prices(exchange.as_ref(), &symbols, &config.display).await?;
+    display_prices(&prices, &config.display);
+    Ok(())
+}
+
+// One price per symbol, in order; None where the fetch failed
+async fn fetch_prices(
+    exchange: &dyn Exchange,
+    symbols: &[String],
+    display: &DisplayConfig,
+) -> Result<Vec<(String, Option<f64>)>, PortfolioError> {
+    let mut prices = Vec::new();
+    let progress = fetch_progress(symbols.len(), display);
+    for symbol in symbols {
+        match exchange.fetch_price(symbol).await {
+            Ok(price) => prices.push((symbol.clone(), Some(price))),
+            Err(e) => {
+                log_action(
+                    &format!(
+                        "{}: Failed to fetch price from {}: {}",
+                        symbol,
+                        exchange.name(),
+                        e
+                    ),
+                    None,
+                )?;
+                prices.push((symbol.clone(), None));
+            }
+        }
+        progress.inc(1);
+    }
+    progress.finish_and_clear();
+    Ok(prices)
+}
+
+async fn holding_symbols(config: &Config) -> Result<Vec<String>, PortfolioError> {
+    let portfolio = if config.portfolio.state_source == "database" {
+        let db = Database::new(&config.database, &config.redis, true).await?;
+        Portfolio::load(config.portfolio.clone(), &db).await?
+    } else {
+        Portfolio::new(config.portfolio.clone())
+    };
+    Ok(portfolio
+        .holdings
+        .into_iter()
+        .map(|holding| holding.symbol)
+        .collect())
+}
+
+async fn market_screen(diff: bool) -> Result<(), PortfolioError> {
+    let config = load_config()?;
+    init_logger(&config.environment)?;
+    set_timezone(&config.display.timezone);
+    set_log_dedup(config.logger.dedup);
+    http::configure(&config.http);
     //let db = Database::new(&config.database.postgres_url, &config.redis.url).await?;
-    let exchange = create_exchange(&config.exchanges[0]);
+    let exchange = create_price_source(&config)?;
     let market_provider = MarketProvider::new(
         &config.marketprovider.base_url,
         &config.marketprovider.api_key,
-        &exchange,
+        config.market.top_n,
+        config.market.exchange_fallback,
+        &config.market.fallback_stats_source,
+        config.market.show_sparkline,
+        exchange.as_ref(),
     );
+    let sentiment_provider = config
+        .market
+        .show_sentiment
+        .then(|| create_sentiment_provider(&config.sentiment));
+    let excluded_symbols = config.market.excluded_symbols();
+    let mut previous = HashMap::new();
+
+    // Pinned symbols CoinGecko doesn't list are priced by the exchange, so check those there
+    let listed = market_provider
+        .fetch_listed(&config.market.pinned_symbols)
+        .await?;
+    let unlisted: Vec<String> = config
+        .market
+        .pinned_symbols
+        .iter()
+        .filter(|symbol| {
+            !listed
+                .iter()
+                .any(|d| d.symbol == **symbol || d.id == **symbol)
+        })
+        .cloned()
+        .collect();
+    let missing = unresolvable_symbols(exchange.as_ref(), &unlisted).await;
+    if !missing.is_empty() {
+        return Err(PortfolioError::ConfigError(format!(
+            "No configured price source resolves: {} (check market.pinned_symbols for typos)",
+            missing.join(", ")
+        )));
+    }
 
     loop {
-        display_market_screen(
+        // A failed refresh (e.g., sentiment.on_missing = "error") only skips this cycle
+        if let Err(e) = display_market_screen(
             &market_provider,
-            &config.market.pinned_symbols,
-            &config.market.sort_by,
-            config.display.use_colors,
+            &config.market,
+            &excluded_symbols,
+            sentiment_provider.as_deref(),
+            &config.sentiment,
+            diff.then_some(&mut previous),
+            &config.display,
         )
-        .await?;
+        .await
+        {
+            log_action(
+                &format!("Market refresh failed, retrying next cycle: {}", e),
+                None,
+            )?;
+        }
+
+        persist_stats();
+        wait_for_next_cycle(&config.refresh_mode, config.market.refresh_secs).await;
+    }
+}
+
+async fn watchlist_screen() -> Result<(), PortfolioError> {
+    let config = load_config()?;
+    init_logger(&config.environment)?;
+    set_timezone(&config.display.timezone);
+    set_log_dedup(config.logger.dedup);
+    http::configure(&config.http);
+    if config.watchlist.symbols.is_empty() {
+        return Err(PortfolioError::ConfigError(
+            "watchlist.symbols is empty; add the coins to monitor".to_string(),
+        ));
+    }
+    let exchange = create_price_source(&config)?;
+    let sentiment_provider = create_sentiment_provider(&config.sentiment);
+    let market_provider = MarketProvider::new(
+        &config.marketprovider.base_url,
+        &config.marketprovider.api_key,
+        config.market.top_n,
+        config.market.exchange_fallback,
+        &config.market.fallback_stats_source,
+        config.market.show_sparkline,
+        exchange.as_ref(),
+    );
+
+    loop {
+        if let Err(e) = display_watchlist_screen(
+            &market_provider,
+            sentiment_provider.as_ref(),
+            &config.watchlist.symbols,
+            &config.sentiment,
+            &config.display,
+        )
+        .await
+        {
+            log_action(
+                &format!("Watchlist refresh failed, retrying next cycle: {}", e),
+                None,
+            )?;
+        }
+
+        persist_stats();
+        wait_for_next_cycle(&config.refresh_mode, config.market.refresh_secs).await;
+    }
+}
+
+async fn debug_sentiment(symbol: &str) -> Result<(), PortfolioError> {
+    let config = load_config()?;
+    init_logger(&config.environment)?;
+    let provider = LunarCrushProvider::new(
+        &config.sentiment.api_url,
+        &config.sentiment.api_key,
+        &config.sentiment.scale,
+        config.sentiment.network_weights.clone(),
+        config.sentiment.debug_raw,
+        &config.sentiment.response_format,
+    );
 
-        sleep(Duration::from_secs(config.market.refresh_secs)).await;
+    println!("=== Raw Sentiment Response ===");
+    println!("URL: {}", provider.redacted_sentiment_url(symbol));
+    let raw = provider.fetch_raw_sentiment(symbol).await?;
+    println!("{}", raw);
+
+    println!("\n=== Parsed DetailedSentiment ===");
+    let detailed = provider.parse_sentiment(symbol, &raw)?;
+    let json = serde_json::to_string_pretty(&detailed)
+        .map_err(|e| PortfolioError::ApiError(e.to_string()))?;
+    println!("{}", json);
+    Ok(())
+}
+
+async fn import_command(path: &str, save: bool) -> Result<(), PortfolioError> {
+    let config = load_config()?;
+    let csv = std::fs::read_to_string(path)
+        .map_err(|e| PortfolioError::IoError(format!("Failed to read {}: {}", path, e)))?;
+    let portfolio = Portfolio::from_csv(config.portfolio.clone(), &csv)?;
+    let block = portfolio.holdings_toml()?;
+
+    if !save {
+        println!("{}", block);
+        return Ok(());
+    }
+    append_holdings(&config, &block)?;
+    println!(
+        "Saved {} holdings from {} to config.toml",
+        portfolio.holdings.len(),
+        path
+    );
+    Ok(())
+}
+
+// Appends [[portfolio.holdings]] blocks; refuses to add a second set next to existing ones
+fn append_holdings(config: &Config, block: &str) -> Result<(), PortfolioError> {
+    if !config.portfolio.holdings.is_empty() {
+        return Err(PortfolioError::ConfigError(
+            "config.toml already defines [[portfolio.holdings]]; remove them before saving"
+                .to_string(),
+        ));
+    }
+    let mut contents = std::fs::read_to_string("config.toml")
+        .map_err(|e| PortfolioError::IoError(e.to_string()))?;
+    if !contents.ends_with('\n') {
+        contents.push('\n');
+    }
+    contents.push('\n');
+    contents.push_str(block);
+    std::fs::write("config.toml", contents).map_err(|e| PortfolioError::IoError(e.to_string()))
+}
+
+async fn reconcile_command(sync: bool) -> Result<(), PortfolioError> {
+    let config = load_config()?;
+    init_logger(&config.environment)?;
+    let binance = create_binance_account(&config)?;
+    let balances = binance.fetch_account_balances().await?;
+    let db = if config.portfolio.state_source == "database" {
+        Some(Database::new(&config.database, &config.redis, true).await?)
+    } else {
+        None
+    };
+    let mut portfolio = match &db {
+        Some(db) => Portfolio::load(config.portfolio.clone(), db).await?,
+        None => Portfolio::new(config.portfolio.clone()),
+    };
+
+    let discrepancies = portfolio.reconcile(&balances);
+    display_reconciliation(&discrepancies, &config.display);
+    if !sync || discrepancies.is_empty() {
+        return Ok(());
+    }
+
+    portfolio.sync_quantities(&balances);
+    if let Some(db) = &db {
+        portfolio.persist(db).await?;
+        println!("Saved synced holdings to the database");
+        return Ok(());
+    }
+    let block = portfolio.holdings_toml()?;
+    if config.portfolio.holdings.is_empty() {
+        append_holdings(&config, &block)?;
+        println!("Saved synced holdings to config.toml");
+    } else {
+        println!(
+            "Replace the [[portfolio.holdings]] in config.toml with:\n\n{}",
+            block
+        );
+    }
+    Ok(())
+}
+
+async fn report_command(
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+) -> Result<(), PortfolioError> {
+    let config = load_config()?;
+    init_logger(&config.environment)?;
+    let db = Database::new(&config.database, &config.redis, true).await?;
+    let exchange = create_price_source(&config)?;
+    let portfolio = Portfolio::load(config.portfolio.clone(), &db).await?;
+
+    // Cost basis needs every trade; the window only limits which sells count as realized
+    let trades = db.fetch_trades(None, None).await?;
+    let mut latest_prices = db.fetch_latest_prices().await?;
+    for holding in &portfolio.holdings {
+        if !latest_prices.contains_key(&holding.symbol) {
+            if let Ok(price) = exchange.fetch_price(&holding.symbol).await {
+                latest_prices.insert(holding.symbol.clone(), price);
+            }
+        }
+    }
+
+    let report = compute_report(
+        &Portfolio::opening_holdings(&config.portfolio),
+        &trades,
+        &latest_prices,
+        from,
+        to,
+        &config.portfolio.rounding_mode,
+    );
+    display_report(&report, &config.display);
+    Ok(())
+}
+
+// Decision support only: scores each holding and prints a trim/hold/add suggestion, never trades
+async fn suggest_command() -> Result<(), PortfolioError> {
+    let config = load_config()?;
+    init_logger(&config.environment)?;
+    let db = Database::new(&config.database, &config.redis, true).await?;
+    let exchange = create_price_source(&config)?;
+    let sentiment_provider = create_sentiment_provider(&config.sentiment);
+    let portfolio = Portfolio::load(config.portfolio.clone(), &db).await?;
+
+    // Live prices first; the last recorded one stands in when the exchange has none
+    let trades = db.fetch_trades(None, None).await?;
+    let mut prices = db.fetch_latest_prices().await?;
+    for holding in &portfolio.holdings {
+        if let Ok(price) = exchange.fetch_price(&holding.symbol).await {
+            prices.insert(holding.symbol.clone(), price);
+        }
+    }
+    let report = compute_report(
+        &Portfolio::opening_holdings(&config.portfolio),
+        &trades,
+        &prices,
+        None,
+        None,
+        &config.portfolio.rounding_mode,
+    );
+
+    let mut suggestions = Vec::new();
+    for holding in &portfolio.holdings {
+        let performance = report
+            .symbols
+            .iter()
+            .find(|performance| performance.symbol == holding.symbol);
+        let price = prices.get(&holding.symbol).copied();
+        let gain_pct = performance
+            .zip(price)
+            .filter(|(performance, _)| performance.average_cost > 0.0)
+            .map(|(performance, price)| {
+                (price - performance.average_cost) / performance.average_cost * 100.0
+            });
+        // Blended the same way as fetch_sentiment, from the one detailed response
+        let (sentiment, week_change) = if sentiment_provider.is_enabled() {
+            match sentiment_provider
+                .fetch_detailed_sentiment(&holding.symbol)
+                .await
+            {
+                Ok(detailed) => (
+                    Some(
+                        detailed
+                            .network_weighted_score(&config.sentiment.network_weights)
+                            .unwrap_or_else(|| {
+                                normalize_sentiment(detailed.current_value, &config.sentiment.scale)
+                            }),
+                    ),
+                    Some(detailed.one_week_change),
+                ),
+                Err(e) => {
+                    let _ = log_action(
+                        &format!("{}: No sentiment for suggestion: {}", holding.symbol, e),
+                        None,
+                    );
+                    (None, None)
+                }
+            }
+        } else {
+            (None, None)
+        };
+        suggestions.push(suggest(
+            SuggestInput {
+                symbol: holding.symbol.clone(),
+                price,
+                gain_pct,
+                sentiment,
+                week_change,
+            },
+            &config.suggest,
+        ));
+    }
+
+    display_suggestions(&suggestions, &config.suggest, &config.display);
+    Ok(())
+}
+
+// Trades as a CSV ledger for accounting; P&L uses the same average-cost basis as `report`
+async fn export_trades_command(
+    path: &str,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    symbol: Option<&str>,
+) -> Result<(), PortfolioError> {
+    let config = load_config()?;
+    init_logger(&config.environment)?;
+    let db = Database::new(&config.database, &config.redis, true).await?;
+    let trades = db.fetch_trades(None, None).await?;
+
+    let file = std::fs::File::create(path)
+        .map_err(|e| PortfolioError::IoError(format!("Failed to create {}: {}", path, e)))?;
+    let written = write_trade_ledger(
+        file,
+        &Portfolio::opening_holdings(&config.portfolio),
+        &trades,
+        from,
+        to,
+        symbol,
+        &config.portfolio.rounding_mode,
+    )?;
+    println!("Exported {} trades to {}", written, path);
+    Ok(())
+}
+
+// Flags that consume the following argument as their value
+const VALUE_FLAGS: [&str; 7] = [
+    "--from",
+    "--to",
+    "--symbols",
+    "--screens",
+    "--symbol",
+    "--channel",
+    "--older-than",
+];
+
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+// Occurrences of a repeatable short flag (`-v`, `-vv`) or its long form (`--verbose`)
+fn flag_count(args: &[String], short: char, long: &str) -> usize {
+    args.iter()
+        .map(|arg| {
+            if arg == long {
+                1
+            } else if arg.len() > 1
+                && arg.starts_with('-')
+                && !arg.starts_with("--")
+                && arg[1..].chars().all(|c| c == short)
+            {
+                arg.len() - 1
+            } else {
+                0
+            }
+        })
+        .sum()
+}
+
+// Verbosity flags are forwarded to spawned screens so they log at the same level
+fn is_level_flag(arg: &str) -> bool {
+    let single = [arg.to_string()];
+    flag_count(&single, 'v', "--verbose") + flag_count(&single, 'q', "--quiet") > 0
+}
+
+// Comma-separated `--symbols PHA,SUI,BTC` override
+fn symbols_flag(args: &[String]) -> Option<Vec<String>> {
+    flag_value(args, "--symbols").map(|value| {
+        value
+            .split(',')
+            .map(|symbol| symbol.trim().to_uppercase())
+            .filter(|symbol| !symbol.is_empty())
+            .collect()
+    })
+}
+
+// Screens started without a subcommand, in start order
+const SCREENS: [&str; 3] = ["portfolio", "sentiment", "market"];
+
+// `--screens portfolio,market` overrides the configured list; unknown names are rejected
+fn selected_screens(
+    args: &[String],
+    configured: &[String],
+) -> Result<Vec<&'static str>, PortfolioError> {
+    let requested: Vec<String> = match flag_value(args, "--screens") {
+        Some(value) => value
+            .split(',')
+            .map(|screen| screen.trim().to_lowercase())
+            .filter(|screen| !screen.is_empty())
+            .collect(),
+        None => configured.to_vec(),
+    };
+    let mut screens = Vec::new();
+    for name in &requested {
+        let screen = SCREENS
+            .into_iter()
+            .find(|screen| screen == name)
+            .ok_or_else(|| {
+                PortfolioError::ConfigError(format!(
+                    "Unknown screen '{}' (expected {})",
+                    name,
+                    SCREENS.join(", ")
+                ))
+            })?;
+        if !screens.contains(&screen) {
+            screens.push(screen);
+        }
+    }
+    if screens.is_empty() {
+        return Err(PortfolioError::ConfigError(format!(
+            "No screens selected; choose from {}",
+            SCREENS.join(", ")
+        )));
+    }
+    Ok(screens)
+}
+
+// Parses a YYYY-MM-DD date flag; `end_of_day` makes the bound inclusive of that date
+fn date_flag(
+    args: &[String],
+    flag: &str,
+    end_of_day: bool,
+) -> Result<Option<DateTime<Utc>>, PortfolioError> {
+    let Some(value) = flag_value(args, flag) else {
+        return Ok(None);
+    };
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|e| {
+        PortfolioError::ConfigError(format!(
+            "Invalid {} date '{}' (expected YYYY-MM-DD): {}",
+            flag, value, e
+        ))
+    })?;
+    let date = if end_of_day {
+        date.succ_opt().unwrap_or(date)
+    } else {
+        date
+    };
+    Ok(date.and_hms_opt(0, 0, 0).map(|dt| dt.and_utc()))
+}
+
+// "aligned" lands on the next multiple of the interval since the epoch, so a 60s screen refreshes
+// on the minute; anything else waits the full interval after the refresh
+fn next_cycle_delay(refresh_mode: &str, interval_secs: u64, now: SystemTime) -> Duration {
+    let interval = Duration::from_secs(interval_secs);
+    if refresh_mode != "aligned" || interval_secs == 0 {
+        return interval;
+    }
+    let since_epoch = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let into_cycle = since_epoch.as_millis() % interval.as_millis();
+    interval - Duration::from_millis(into_cycle as u64)
+}
+
+async fn wait_for_next_cycle(refresh_mode: &str, interval_secs: u64) {
+    sleep(next_cycle_delay(
+        refresh_mode,
+        interval_secs,
+        SystemTime::now(),
+    ))
+    .await;
+}
+
+// Written after every refresh for the `stats` subcommand; a failed write never stops a screen
+fn persist_stats() {
+    if let Err(e) = stats::persist() {
+        let _ = log_action(&format!("Failed to write stats: {}", e), None);
+    }
+}
+
+// Without an id lists the queued sells; with one approves it for the portfolio screen's next check
+async fn approve_command(id: Option<&str>) -> Result<(), PortfolioError> {
+    let config = load_config()?;
+    init_logger(&config.environment)?;
+    set_timezone(&config.display.timezone);
+    let db = Database::new(&config.database, &config.redis, false).await?;
+    let Some(id) = id else {
+        display_pending_sells(&db.pending_sells().await?, &config.display);
+        return Ok(());
+    };
+    let id = id
+        .parse::<u64>()
+        .map_err(|_| PortfolioError::ConfigError(format!("Invalid trade id '{}'", id)))?;
+    let pending = db
+        .approve_pending_sell(id)
+        .await?
+        .ok_or_else(|| PortfolioError::ConfigError(format!("No pending sell with id {}", id)))?;
+    let _ = log_action(
+        &format!(
+            "{}: Approved sell #{} of {} tokens",
+            pending.symbol, pending.id, pending.quantity
+        ),
+        None,
+    );
+    println!(
+        "Approved sell #{} of {} {}; the portfolio screen executes it on its next check.",
+        pending.id, pending.quantity, pending.symbol
+    );
+    Ok(())
+}
+
+// Sends a test message through --channel, or every enabled channel, and reports each result
+async fn test_notify_command(channel: Option<&str>) -> Result<(), PortfolioError> {
+    let config = load_config()?;
+    init_logger(&config.environment)?;
+    set_timezone(&config.display.timezone);
+    let notifier = Notifier::new(config.notification.clone());
+    let channels = match channel {
+        Some(channel) => vec![channel.to_lowercase()],
+        None => notifier
+            .enabled_channels()
+            .into_iter()
+            .map(str::to_string)
+            .collect(),
+    };
+    if channels.is_empty() {
+        println!("No notification channels are enabled; set sms_enabled or email_enabled.");
+        return Ok(());
+    }
+
+    let mut failed = Vec::new();
+    for channel in &channels {
+        match notifier.send_test(channel).await {
+            Ok(()) => println!("{}: sent", channel),
+            Err(e) => {
+                println!("{}: failed: {}", channel, e);
+                failed.push(channel.as_str());
+            }
+        }
+    }
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(PortfolioError::NotificationError(format!(
+            "Test notification failed for {}",
+            failed.join(", ")
+        )))
+    }
+}
+
+// The instant `older_than` (e.g., "90d") before `now`; rows recorded before it are pruned
+fn prune_cutoff(older_than: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, PortfolioError> {
+    let invalid = |e: String| {
+        PortfolioError::ConfigError(format!("Invalid --older-than '{}': {}", older_than, e))
+    };
+    let age = humantime::parse_duration(older_than).map_err(|e| invalid(e.to_string()))?;
+    let age = chrono::Duration::from_std(age).map_err(|e| invalid(e.to_string()))?;
+    Ok(now - age)
+}
+
+// Deletes price_history older than `older_than` (e.g., "90d"); trades are never deleted, only
+// moved to trades_archive with --archive-trades, where the cost basis still reads them
+async fn prune_command(
+    older_than: Option<&str>,
+    archive_trades: bool,
+) -> Result<(), PortfolioError> {
+    let Some(older_than) = older_than else {
+        eprintln!("Usage: prune --older-than <duration, e.g. 90d> [--archive-trades]");
+        return Ok(());
+    };
+    let cutoff = prune_cutoff(older_than, Utc::now())?;
+
+    let config = load_config()?;
+    init_logger(&config.environment)?;
+    let db = Database::new(&config.database, &config.redis, true).await?;
+    let pruned = db.prune_price_history(cutoff).await?;
+    println!(
+        "Removed {} price_history rows older than {}",
+        pruned,
+        cutoff.to_rfc3339()
+    );
+    if archive_trades {
+        let archived = db.archive_trades(cutoff).await?;
+        println!("Moved {} trades to trades_archive", archived);
+    }
+    log_action(
+        &format!(
+            "Pruned {} price_history rows older than {}",
+            pruned, older_than
+        ),
+        None,
+    )?;
+    Ok(())
+}
+
+async fn stats_command() -> Result<(), PortfolioError> {
+    let config = load_config()?;
+    set_timezone(&config.display.timezone);
+    let all = stats::load_all()?;
+    if all.is_empty() {
+        println!(
+            "No stats recorded yet; they are written to {}/ after each screen refresh.",
+            stats::STATS_DIR
+        );
+        return Ok(());
+    }
+    display_stats(&all, &config.display);
+    Ok(())
+}
+
+// Delays a screen's first fetch so screens don't all hit the APIs at once
+async fn staggered<F: std::future::Future>(delay: Duration, screen: F) -> F::Output {
+    sleep(delay).await;
+    screen.await
+}
+
+async fn run_screen(screen: &str) -> Result<(), PortfolioError> {
+    match screen {
+        "portfolio" => portfolio_screen(false).await,
+        "sentiment" => sentiment_screen(None).await,
+        _ => market_screen(false).await,
+    }
+}
+
+// One task per selected screen, each started `stagger_secs` after the one before it
+fn spawn_screens<F, Fut>(
+    screens: &[&'static str],
+    stagger_secs: u64,
+    run: F,
+) -> JoinSet<(&'static str, Result<(), PortfolioError>)>
+where
+    F: Fn(&'static str) -> Fut,
+    Fut: std::future::Future<Output = Result<(), PortfolioError>> + Send + 'static,
+{
+    let mut tasks = JoinSet::new();
+    for (i, screen) in screens.iter().copied().enumerate() {
+        let delay = Duration::from_secs(stagger_secs * i as u64);
+        let screen_task = run(screen);
+        tasks.spawn(SCREEN.scope(screen, async move {
+            (screen, staggered(delay, screen_task).await)
+        }));
+    }
+    tasks
+}
+
+async fn run_screens_in_process(
+    screens: &[&'static str],
+    stagger_secs: u64,
+) -> Result<(), PortfolioError> {
+    stats::set_process_name("screens");
+    let mut tasks = spawn_screens(screens, stagger_secs, run_screen);
+
+    // Wait for Ctrl+C or SIGTERM to terminate
+    tokio::select! {
+        Some(finished) = tasks.join_next() => match finished {
+            Ok((screen, Err(e))) => eprintln!("{} screen terminated: {}", screen, e),
+            Ok((screen, Ok(()))) => eprintln!("{} screen terminated", screen),
+            Err(e) => eprintln!("Screen task failed: {}", e),
+        },
+        _ = shutdown_signal() => println!("Received shutdown signal, shutting down"),
+    };
+    Ok(())
+}
+
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {},
+                    _ = sigterm.recv() => {},
+                }
+            }
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
     }
 }
 
 #[tokio::main]
 async fn main() -> Result<(), PortfolioError> {
     dotenv().ok();
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() > 1 {
-        match args[1].as_str() {
-            "portfolio" => portfolio_screen().await,
-            "sentiment" => sentiment_screen().await,
-            "market" => market_screen().await,
-            _ => {
-                eprintln!("Invalid subcommand. Use 'portfolio', 'sentiment', or 'market'.");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let no_terminal = args.iter().any(|arg| arg == "--no-terminal");
+    let daemon = args.iter().any(|arg| arg == "--daemon");
+    if let Some(level) = level_override(
+        flag_count(&args, 'v', "--verbose"),
+        flag_count(&args, 'q', "--quiet"),
+    ) {
+        set_level_override(level);
+    }
+    let positional: Vec<&str> = args
+        .iter()
+        .enumerate()
+        .filter(|(i, arg)| {
+            !arg.starts_with('-') && (*i == 0 || !VALUE_FLAGS.contains(&args[*i - 1].as_str()))
+        })
+        .map(|(_, arg)| arg.as_str())
+        .collect();
+    let subcommand = pick_subcommand(positional.first().copied(), no_terminal || daemon, || {
+        Ok(load_config()?.default_command)
+    })?;
+    stats::set_process_name(&subcommand);
+    let result = match subcommand.as_str() {
+        "dashboard" => dashboard(&args, no_terminal, daemon).await,
+        "portfolio" => {
+            until_shutdown(portfolio_screen(args.iter().any(|arg| arg == "--diff"))).await
+        }
+        "sentiment" => until_shutdown(sentiment_screen(symbols_flag(&args))).await,
+        "prices" => prices_screen(symbols_flag(&args)).await,
+        "market" => until_shutdown(market_screen(args.iter().any(|arg| arg == "--diff"))).await,
+        "watchlist" => until_shutdown(watchlist_screen()).await,
+        "report" => {
+            let from = date_flag(&args, "--from", false)?;
+            let to = date_flag(&args, "--to", true)?;
+            report_command(from, to).await
+        }
+        "suggest" => suggest_command().await,
+        "export-trades" => {
+            let from = date_flag(&args, "--from", false)?;
+            let to = date_flag(&args, "--to", true)?;
+            let path = positional.get(1).copied().unwrap_or("trades.csv");
+            export_trades_command(path, from, to, flag_value(&args, "--symbol")).await
+        }
+        "import" => match positional.get(1) {
+            Some(path) => import_command(path, args.iter().any(|arg| arg == "--save")).await,
+            None => {
+                eprintln!("Usage: import <holdings.csv> [--save]");
+                Ok(())
+            }
+        },
+        "init-config" => {
+            write_example_config("config.toml", args.iter().any(|arg| arg == "--force"))?;
+            println!("Wrote config.toml; fill in your API keys and holdings before starting.");
+            Ok(())
+        }
+        "stats" => stats_command().await,
+        "prune" => {
+            prune_command(
+                flag_value(&args, "--older-than"),
+                args.iter().any(|arg| arg == "--archive-trades"),
+            )
+            .await
+        }
+        "approve" => approve_command(positional.get(1).copied()).await,
+        "test-notify" => test_notify_command(flag_value(&args, "--channel")).await,
+        "reconcile" => reconcile_command(args.iter().any(|arg| arg == "--sync")).await,
+        "debug-sentiment" => match positional.get(1) {
+            Some(symbol) => debug_sentiment(symbol).await,
+            None => {
+                eprintln!("Usage: debug-sentiment <symbol>");
                 Ok(())
             }
+        },
+        _ => {
+            eprintln!("Invalid subcommand. Use 'dashboard', 'portfolio', 'sentiment', 'market', 'watchlist', 'prices', 'report', 'suggest', 'export-trades [file]', 'import <csv>', 'reconcile', 'approve [id]', 'test-notify [--channel sms|email]', 'stats', 'prune --older-than <age>', 'init-config', or 'debug-sentiment <symbol>'.");
+            Ok(())
         }
+    };
+    flush_log_dedup();
+    result
+}
+
+// The first positional argument, else "dashboard" when only its flags (--daemon, --no-terminal)
+// were given, else config.default_command ("market" unless set)
+fn pick_subcommand<F>(
+    first: Option<&str>,
+    dashboard_flags: bool,
+    default_command: F,
+) -> Result<String, PortfolioError>
+where
+    F: FnOnce() -> Result<String, PortfolioError>,
+{
+    match first {
+        Some(subcommand) => Ok(subcommand.to_string()),
+        None if dashboard_flags => Ok("dashboard".to_string()),
+        None => default_command(),
+    }
+}
+
+// A single screen stops on Ctrl+C or SIGTERM rather than being killed, so main can flush the log
+async fn until_shutdown<F>(screen: F) -> Result<(), PortfolioError>
+where
+    F: std::future::Future<Output = Result<(), PortfolioError>>,
+{
+    tokio::select! {
+        result = screen => result,
+        _ = shutdown_signal() => Ok(()),
+    }
+}
+
+// The terminal emulator (and its arguments) to open each screen in, or None with --no-terminal or
+// when `installed` finds none of them, e.g., on a headless host
+fn find_terminal(
+    no_terminal: bool,
+    executable: &'static str,
+    installed: impl Fn(&str) -> bool,
+) -> Option<(&'static str, Vec<&'static str>)> {
+    if no_terminal {
+        None
+    } else if cfg!(target_os = "windows") {
+        Some(("cmd", vec!["/C", "start", "cmd", "/K", executable]))
+    } else {
+        // Detect terminal emulator for Linux
+        let terminals = [
+            ("gnome-terminal", vec!["--", executable]),
+            ("konsole", vec!["-e", executable]),
+            ("xterm", vec!["-e", executable]),
+        ];
+        terminals.into_iter().find(|(cmd, _)| installed(cmd))
+    }
+}
+
+// Every selected screen at once: spawned terminals in prod, one process in dev, daemon mode or headless
+async fn dashboard(args: &[String], no_terminal: bool, daemon: bool) -> Result<(), PortfolioError> {
+    let config = load_config()?;
+    let daemon = daemon || config.prod_single_process;
+    let screens = selected_screens(args, &config.screens)?;
+    if daemon {
+        // Service managers (systemd, Docker) collect stdout
+        init_logger_with_target(&config.environment, Target::Stdout)?;
     } else {
-        let config = load_config()?;
         init_logger(&config.environment)?;
+    }
 
-        if config.environment == "dev" {
-            println!("Running in development mode. Use 'cargo run -- <subcommand>' to start a specific screen.");
-
-            // Run screens directly in development for easier debugging
-            println!("Running all screens in a single process for debugging. Use Ctrl+C to stop.");
-            let portfolio_handle = tokio::spawn(portfolio_screen());
-            let sentiment_handle = tokio::spawn(sentiment_screen());
-            let market_handle = tokio::spawn(market_screen());
-
-            // Wait for Ctrl+C to terminate
-            tokio::select! {
-                _ = portfolio_handle => eprintln!("Portfolio screen terminated"),
-                _ = sentiment_handle => eprintln!("Sentiment screen terminated"),
-                _ = market_handle => eprintln!("Market screen terminated"),
-                _ = tokio::signal::ctrl_c() => println!("Received Ctrl+C, shutting down"),
-            };
-            Ok(())
+    if config.environment == "dev" {
+        println!("Running in development mode. Use 'cargo run -- <subcommand>' to start a specific screen.");
+
+        // Run screens directly in development for easier debugging
+        println!("Running all screens in a single process for debugging. Use Ctrl+C to stop.");
+        run_screens_in_process(&screens, config.startup_stagger_secs).await
+    } else if daemon {
+        println!("Running in production mode as a single process. Send SIGTERM or Ctrl+C to stop.");
+        run_screens_in_process(&screens, config.startup_stagger_secs).await
+    } else {
+        println!("Running in production mode. Use 'target/release/crypto_portfolio <subcommand>' to start a specific screen.");
+
+        // Use pre-built binary to avoid file locks
+        let executable = if cfg!(target_os = "windows") {
+            "target\\release\\crypto_portfolio.exe"
         } else {
-            println!("Running in production mode. Use 'target/release/crypto_portfolio <subcommand>' to start a specific screen.");
+            "./target/release/crypto_portfolio"
+        };
 
-            // Use pre-built binary to avoid file locks
-            let executable = if cfg!(target_os = "windows") {
-                "target\\release\\crypto_portfolio.exe"
-            } else {
-                "./target/release/crypto_portfolio"
-            };
+        let terminal = find_terminal(no_terminal, executable, |cmd| {
+            Command::new(cmd).arg("--version").output().is_ok()
+        });
 
-            // Detect terminal emulator for Linux
-            let (terminal_cmd, terminal_args) = if cfg!(target_os = "windows") {
-                ("cmd", vec!["/C", "start", "cmd", "/K", executable])
-            } else {
-                let terminals = [
-                    ("gnome-terminal", vec!["--", executable]),
-                    ("konsole", vec!["-e", executable]),
-                    ("xterm", vec!["-e", executable]),
-                ];
-                terminals
-                    .into_iter()
-                    .find(|(cmd, _)| Command::new(cmd).arg("--version").output().is_ok())
-                    .unwrap_or_else(|| {
-                        eprintln!("No terminal emulator found (gnome-terminal, konsole, xterm). Falling back to xterm.");
-                        ("xterm", vec!["-e", executable])
-                    })
-            };
+        // Headless hosts (or --no-terminal) run every screen in this process instead
+        let Some((terminal_cmd, terminal_args)) = terminal else {
+            if !no_terminal {
+                eprintln!("No terminal emulator found (gnome-terminal, konsole, xterm). Running the screens in this process.");
+            }
+            return run_screens_in_process(&screens, config.startup_stagger_secs).await;
+        };
 
-            // Store child processes for cleanup
-            let mut children: Vec<Child> = Vec::new();
-
-            // Spawn console windows for each screen
-            for screen in ["portfolio", "sentiment", "market"] {
-                match Command::new(terminal_cmd)
-                    .args(&terminal_args)
-                    .arg(screen)
-                    .spawn()
-                {
-                    Ok(child) => {
-                        let pid = child.id();
-                        println!("Spawned {} screen (PID: {})", screen, pid);
-                        children.push(child);
-                    }
-                    Err(e) => eprintln!("Failed to spawn {} screen: {}", screen, e),
+        // Store child processes for cleanup
+        let mut children: Vec<Child> = Vec::new();
+        // Spawn console windows for each screen
+        for (i, screen) in screens.into_iter().enumerate() {
+            if i > 0 && config.startup_stagger_secs > 0 {
+                sleep(Duration::from_secs(config.startup_stagger_secs)).await;
+            }
+            match Command::new(terminal_cmd)
+                .args(&terminal_args)
+                .arg(screen)
+                .args(args.iter().filter(|arg| is_level_flag(arg)))
+                .spawn()
+            {
+                Ok(child) => {
+                    let pid = child.id();
+                    println!("Spawned {} screen (PID: {})", screen, pid);
+                    children.push(child);
                 }
+                Err(e) => eprintln!("Failed to spawn {} screen: {}", screen, e),
             }
+        }
 
-            // Wait for Ctrl+C to terminate
-            ctrlc::set_handler({
-                let mut children = children;
-                move || {
-                    println!("Received Ctrl+C, terminating child processes...");
-                    for child in children.iter_mut() {
-                        let _ = child.kill();
-                        let _ = child.wait();
-                    }
-                    std::process::exit(0);
+        // Wait for Ctrl+C to terminate
+        ctrlc::set_handler({
+            let mut children = children;
+            move || {
+                println!("Received Ctrl+C, terminating child processes...");
+                for child in children.iter_mut() {
+                    let _ = child.kill();
+                    let _ = child.wait();
                 }
-            })
-            .expect("Failed to set Ctrl+C handler");
+                std::process::exit(0);
+            }
+        })
+        .expect("Failed to set Ctrl+C handler");
 
-            // Keep the main process alive
-            std::thread::sleep(std::time::Duration::from_secs(3600));
-            Ok(())
+        // Keep the main process alive
+        std::thread::sleep(std::time::Duration::from_secs(3600));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prune_cutoff_subtracts_the_age() {
+        let now = Utc::now();
+        assert_eq!(
+            prune_cutoff("90d", now).unwrap(),
+            now - chrono::Duration::days(90)
+        );
+        assert_eq!(
+            prune_cutoff("36h", now).unwrap(),
+            now - chrono::Duration::hours(36)
+        );
+        assert!(prune_cutoff("ninety days", now).is_err());
+    }
+
+    #[test]
+    fn no_subcommand_runs_the_default_command() {
+        let market = || Ok("market".to_string());
+        assert_eq!(pick_subcommand(None, false, market).unwrap(), "market");
+        assert_eq!(pick_subcommand(None, true, market).unwrap(), "dashboard");
+        let unread = || -> Result<String, PortfolioError> { panic!("config read") };
+        assert_eq!(
+            pick_subcommand(Some("portfolio"), true, unread).unwrap(),
+            "portfolio"
+        );
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn headless_hosts_run_the_screens_in_process() {
+        let executable = "./target/release/crypto_portfolio";
+        assert!(find_terminal(false, executable, |_| false).is_none());
+        assert!(find_terminal(true, executable, |_| true).is_none());
+        assert_eq!(
+            find_terminal(false, executable, |cmd| cmd == "xterm"),
+            Some(("xterm", vec!["-e", executable]))
+        );
+    }
+
+    // Answers $1 for anything and records what was asked for
+    #[derive(Default)]
+    struct RecordingExchange {
+        fetched: std::sync::Mutex<Vec<String>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Exchange for RecordingExchange {
+        async fn fetch_price(&self, symbol: &str) -> Result<f64, PortfolioError> {
+            self.fetched.lock().unwrap().push(symbol.to_string());
+            Ok(1.0)
         }
+
+        fn name(&self) -> &str {
+            "recording"
+        }
+    }
+
+    #[tokio::test]
+    async fn symbols_flag_drives_the_fetch_loop() {
+        let args = ["prices", "--symbols", " pha, sui ,"].map(String::from);
+        let symbols = symbols_flag(&args).unwrap();
+        assert_eq!(symbols, ["PHA", "SUI"]);
+        let exchange = RecordingExchange::default();
+        let display: DisplayConfig =
+            toml::from_str("sentiment_refresh_secs = 60\nuse_colors = false").unwrap();
+        let prices = fetch_prices(&exchange, &symbols, &display).await.unwrap();
+        assert_eq!(*exchange.fetched.lock().unwrap(), ["PHA", "SUI"]);
+        assert_eq!(prices.len(), 2);
+        assert_eq!(symbols_flag(&["prices".to_string()]), None);
+    }
+
+    #[tokio::test]
+    async fn staggered_screens_start_in_order() {
+        let started = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut tasks = JoinSet::new();
+        let begun = Instant::now();
+        for (i, screen) in ["portfolio", "sentiment", "market"].into_iter().enumerate() {
+            let started = started.clone();
+            let delay = Duration::from_millis(50 * i as u64);
+            tasks.spawn(staggered(delay, async move {
+                started.lock().unwrap().push((screen, Instant::now()));
+            }));
+        }
+        while tasks.join_next().await.is_some() {}
+
+        let started = started.lock().unwrap();
+        let order: Vec<&str> = started.iter().map(|(screen, _)| *screen).collect();
+        assert_eq!(order, ["portfolio", "sentiment", "market"]);
+        assert!(started[2].1 - begun >= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn aligned_refreshes_land_on_clock_boundaries() {
+        // 12:00:45.250 UTC on 2024-01-15
+        let now = UNIX_EPOCH + Duration::from_millis(1_705_320_045_250);
+        assert_eq!(
+            next_cycle_delay("aligned", 60, now),
+            Duration::from_millis(14_750)
+        );
+        assert_eq!(
+            next_cycle_delay("aligned", 300, now),
+            Duration::from_millis(254_750)
+        );
+        // Exactly on a boundary waits for the next one rather than refreshing twice
+        let on_the_minute = UNIX_EPOCH + Duration::from_secs(1_705_320_060);
+        assert_eq!(
+            next_cycle_delay("aligned", 60, on_the_minute),
+            Duration::from_secs(60)
+        );
+        assert_eq!(
+            next_cycle_delay("interval", 60, now),
+            Duration::from_secs(60)
+        );
+    }
+
+    #[tokio::test]
+    async fn only_the_selected_screens_get_tasks() {
+        let args: Vec<String> = ["dashboard", "--screens", "market,portfolio"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let screens = selected_screens(&args, &["sentiment".to_string()]).unwrap();
+        let ran = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut tasks = spawn_screens(&screens, 0, |screen| {
+            let ran = ran.clone();
+            async move {
+                ran.lock().unwrap().push((screen, SCREEN.get()));
+                Ok(())
+            }
+        });
+        assert_eq!(tasks.len(), 2);
+        while tasks.join_next().await.is_some() {}
+
+        let mut ran = ran.lock().unwrap().clone();
+        ran.sort();
+        // Each runs under its own screen name, for the log prefixes
+        assert_eq!(ran, [("market", "market"), ("portfolio", "portfolio")]);
+    }
+
+    #[tokio::test]
+    async fn headless_cycles_print_nothing_but_still_notify() {
+        let mut config: Config = toml::from_str(crate::config::EXAMPLE_CONFIG).unwrap();
+        config.portfolio.display_enabled = false;
+        let (notifier, sent) =
+            crate::notification::testing::recording_notifier(config.notification.clone());
+        let db = Database::offline(
+            &std::env::temp_dir()
+                .join("crypto-portfolio-headless.jsonl")
+                .to_string_lossy(),
+        );
+        let ctx = TradeContext {
+            exchange: &RecordingExchange::default(),
+            orders: None,
+            db: &db,
+            notifier: &notifier,
+        };
+        let mut portfolio =
+            Portfolio::from_csv(config.portfolio.clone(), "SUI,10,1.5,0.5\n").unwrap();
+        let quotes = HashMap::from([(
+            "SUI".to_string(),
+            Quote {
+                price: 1.into(),
+                live: true,
+            },
+        )]);
+        // Down from $100 to $10, well past the major-change threshold
+        let baseline = ChangeBaseline {
+            value: 100.0,
+            ..ChangeBaseline::default()
+        };
+        let total_value = portfolio
+            .check_portfolio(
+                &ctx,
+                &quotes,
+                &crate::exchange::NullSentimentProvider,
+                &config.sentiment,
+                &baseline,
+            )
+            .await
+            .unwrap();
+
+        let prices = HashMap::from([("SUI".to_string(), 1.0)]);
+        let sentiments = HashMap::new();
+        let screen = cycle_screen(&config, &portfolio, total_value, &prices, &sentiments, None);
+        assert_eq!(screen, None);
+        assert!(sent
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|alert| alert.ends_with("Portfolio Value Change Alert")));
+
+        config.portfolio.display_enabled = true;
+        let screen = cycle_screen(&config, &portfolio, total_value, &prices, &sentiments, None);
+        assert!(screen.unwrap().contains("=== Portfolio Status ==="));
     }
 }
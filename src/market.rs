@@ -1,53 +1,236 @@
-use std::str::FromStr;
-
+use crate::config::{DisplayConfig, MarketConfig, SentimentConfig};
+use crate::display::{
+    change_cell, diff_row, fetch_progress, moved, new_table, percent_cell, price_decimals,
+    recommendation, recommendation_cell, Theme,
+};
 use crate::errors::PortfolioError;
-use crate::exchange::{BinanceExchange, Exchange};
-use comfy_table::{Cell, Color, Table};
+use crate::exchange::{resolve_missing_sentiment, Exchange, SentimentProvider, Ticker24h};
+use crate::http::{read_json, request_permit};
+use crate::logger::{display_now, log_action, log_elapsed};
+use crate::stats::{record_api_call, record_cache_lookup};
+use comfy_table::{Cell, Table};
+use fixed_decimal::FloatPrecision;
 use icu::decimal::input::Decimal;
 use icu::decimal::DecimalFormatter;
 use icu::locale::{locale, Locale};
 use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+
+const COMPACT_TABLE_WIDTH: u16 = 120;
+const TOP_MOVERS: usize = 5;
+// Bars per sparkline cell; CoinGecko's ~168 hourly 7-day prices are averaged down to this
+const SPARKLINE_WIDTH: usize = 24;
+const SPARKLINE_BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct MarketData {
+    #[serde(default)]
+    pub id: String,
     pub symbol: String,
+    #[serde(skip_deserializing)]
+    pub name: Option<String>, // Filled from the cached coin list when market.show_names is set
     #[serde(rename = "current_price")]
     pub price: f64,
     pub market_cap: f64,
+    #[serde(default)]
+    pub market_cap_rank: Option<u32>, // None for unranked coins and exchange-priced rows
     pub price_change_24h: f64,
     pub price_change_percentage_24h: f64,
     pub high_24h: f64,
     pub low_24h: f64,
     pub total_volume: f64,
+    #[serde(
+        default,
+        rename(deserialize = "sparkline_in_7d"),
+        deserialize_with = "deserialize_sparkline"
+    )]
+    pub sparkline: Option<Vec<f64>>, // 7-day prices, only requested with market.show_sparkline
+}
+
+// CoinGecko nests the prices as "sparkline_in_7d": {"price": [...]}
+fn deserialize_sparkline<'de, D>(deserializer: D) -> Result<Option<Vec<f64>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Sparkline {
+        price: Vec<Option<f64>>, // Gaps come through as null
+    }
+
+    let sparkline = Option::<Sparkline>::deserialize(deserializer)?;
+    Ok(sparkline.map(|sparkline| sparkline.price.into_iter().flatten().collect()))
+}
+
+impl MarketData {
+    // Row for a coin CoinGecko's markets list lacks, priced by the exchange; 24h fields the
+    // exchange couldn't supply are NaN, shown as "-" and sorted last
+    fn exchange_priced(symbol: &str, price: f64, stats: Option<Ticker24h>) -> Self {
+        let stat = |field: fn(&Ticker24h) -> f64| stats.as_ref().map(field).unwrap_or(f64::NAN);
+        MarketData {
+            id: symbol.to_string(),
+            symbol: symbol.to_string(),
+            name: None,
+            price,
+            market_cap: 0.0,
+            market_cap_rank: None,
+            price_change_24h: stat(|stats| stats.price_change),
+            price_change_percentage_24h: stat(|stats| stats.price_change_percent),
+            high_24h: stat(|stats| stats.high),
+            low_24h: stat(|stats| stats.low),
+            total_volume: stat(|stats| stats.quote_volume),
+            sparkline: None,
+        }
+    }
 }
 
 pub struct MarketProvider<'a> {
     client: Client,
     api_url: String,
     api_key: String,
-    exchange: &'a BinanceExchange,
+    top_n: usize,
+    exchange_fallback: bool, // Degrade to exchange-priced rows when the markets request fails
+    fallback_stats_source: String, // "exchange" fills exchange-priced rows from its 24h ticker; "none" leaves them blank
+    sparkline: bool,               // Requests 7-day price arrays with the markets data
+    exchange: &'a dyn Exchange,
+    coin_names: OnceCell<HashMap<String, String>>, // CoinGecko id -> name, fetched once
+    sentiment_cache: Mutex<HashMap<String, (Instant, f64)>>, // Pinned-symbol scores for the market screen
+}
+
+#[derive(Deserialize)]
+struct CoinListEntry {
+    id: String,
+    name: String,
+}
+
+// CoinGecko reports failures as {"status":{"error_code":429,"error_message":"..."}} or {"error":"..."}
+fn coingecko_error(body: &serde_json::Value) -> String {
+    let status = &body["status"];
+    if let Some(message) = status["error_message"].as_str() {
+        return match status["error_code"].as_i64() {
+            Some(code) => format!("{} (code {})", message, code),
+            None => message.to_string(),
+        };
+    }
+    match body["error"].as_str() {
+        Some(message) => message.to_string(),
+        None => format!("unexpected response {}", body),
+    }
 }
 
 impl<'a> MarketProvider<'a> {
-    pub fn new(api_url: &str, api_key: &str, exchange: &'a BinanceExchange) -> Self {
+    pub fn new(
+        api_url: &str,
+        api_key: &str,
+        top_n: usize,
+        exchange_fallback: bool,
+        fallback_stats_source: &str,
+        sparkline: bool,
+        exchange: &'a dyn Exchange,
+    ) -> Self {
         MarketProvider {
             client: Client::new(),
             api_url: api_url.to_string(),
             api_key: api_key.to_string(),
-            exchange: exchange,
+            top_n,
+            exchange_fallback,
+            fallback_stats_source: fallback_stats_source.to_string(),
+            sparkline,
+            exchange,
+            coin_names: OnceCell::new(),
+            sentiment_cache: Mutex::new(HashMap::new()),
         }
     }
 
     pub async fn fetch_market_data(
         &self,
         symbols: &[String],
+    ) -> Result<Vec<MarketData>, PortfolioError> {
+        let mut data = self.fetch_listed(symbols).await?;
+        // Anything CoinGecko still doesn't list gets a price-only row from the exchange
+        for symbol in symbols {
+            if !data.iter().any(|d| d.symbol == *symbol || d.id == *symbol) {
+                data.push(self.exchange_row(symbol).await?);
+            }
+        }
+        Ok(data)
+    }
+
+    // The top N plus whichever of `symbols` CoinGecko lists by id; empty under exchange_fallback
+    // when CoinGecko is down
+    pub async fn fetch_listed(
+        &self,
+        symbols: &[String],
     ) -> Result<Vec<MarketData>, PortfolioError> {
         let url = format!(
-            "{}/coins/markets?vs_currency=usd&per_page=1000&page=1",
-            self.api_url
+            "{}/coins/markets?vs_currency=usd&per_page={}&page=1&sparkline={}",
+            self.api_url, self.top_n, self.sparkline
         );
+        let (mut data, listed) = match self.request_markets(&url, "fetch_market_data").await {
+            Ok(data) => (data, true),
+            Err(e) if self.exchange_fallback => {
+                let _ = log_action(
+                    &format!("Market data unavailable, using exchange prices: {}", e),
+                    None,
+                );
+                (Vec::new(), false)
+            }
+            Err(e) => return Err(e),
+        };
+
+        // Pinned symbols (PHA, SUI, DUSK) outside the top N are requested by CoinGecko id
+        let missing: Vec<&str> = symbols
+            .iter()
+            .filter(|symbol| {
+                !data
+                    .iter()
+                    .any(|d| d.symbol == **symbol || d.id == **symbol)
+            })
+            .map(String::as_str)
+            .collect();
+        if listed && !missing.is_empty() {
+            let url = format!(
+                "{}/coins/markets?vs_currency=usd&ids={}&sparkline={}",
+                self.api_url,
+                missing.join(","),
+                self.sparkline
+            );
+            match self.request_markets(&url, "fetch_pinned_markets").await {
+                Ok(rows) => data.extend(rows),
+                Err(e) => {
+                    let _ = log_action(&format!("Pinned market data fetch failed: {}", e), None);
+                }
+            }
+        }
+        Ok(data)
+    }
+
+    // The price must come from the exchange; missing 24h stats only blank their columns
+    async fn exchange_row(&self, symbol: &str) -> Result<MarketData, PortfolioError> {
+        let price = self.exchange.fetch_price(symbol).await?;
+        let stats = if self.fallback_stats_source == "exchange" {
+            match self.exchange.fetch_24h_stats(symbol).await {
+                Ok(stats) => stats,
+                Err(e) => {
+                    let _ = log_action(&format!("{}: No 24h stats: {}", symbol, e), None);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        Ok(MarketData::exchange_priced(symbol, price, stats))
+    }
+
+    async fn request_markets(
+        &self,
+        url: &str,
+        call: &str,
+    ) -> Result<Vec<MarketData>, PortfolioError> {
         let mut headers = HeaderMap::new();
         headers.insert(USER_AGENT, HeaderValue::from_static("crypto_portfolio/0.1"));
         headers.insert(
@@ -55,47 +238,134 @@ impl<'a> MarketProvider<'a> {
             HeaderValue::from_str(&self.api_key)
                 .map_err(|e| PortfolioError::ExchangeError(e.to_string()))?,
         );
+        // Released on return, before any exchange fallback takes its own permit
+        let _permit = request_permit().await;
+        let started = Instant::now();
         let resp = self
             .client
-            .get(&url)
+            .get(url)
             .headers(headers)
             .header("User-Agent", "crypto_portfolio/0.1")
             .send()
             .await
-            .map_err(|e| PortfolioError::ExchangeError(e.to_string()))?;
-        let mut data: Vec<MarketData> = resp
-            .json()
-            .await
-            .map_err(|e| PortfolioError::ExchangeError(e.to_string()))?;
+            .map_err(|e| PortfolioError::ExchangeError(e.without_url().to_string()));
+        log_elapsed(call, started);
+        record_api_call("coingecko", started);
+        let body: serde_json::Value = read_json(resp?, &format!("CoinGecko {}", call)).await?;
+        if !body.is_array() {
+            return Err(PortfolioError::ApiError(format!(
+                "CoinGecko {} returned an error: {}",
+                call,
+                coingecko_error(&body)
+            )));
+        }
+        serde_json::from_value(body).map_err(|e| PortfolioError::ExchangeError(e.to_string()))
+    }
 
-        // Ensure pinned symbols (PHA, SUI, DUSK) are included
+    // Rows for just these symbols (matched case-insensitively), in the order given
+    pub async fn fetch_watchlist(
+        &self,
+        symbols: &[String],
+    ) -> Result<Vec<MarketData>, PortfolioError> {
+        let data = self.fetch_market_data(&[]).await?;
+        let mut rows = Vec::new();
         for symbol in symbols {
-            if !data.iter().any(|d| d.symbol == *symbol) {
-                //let price = self.exchange.fetch_single_price(symbol).await?;
-                let price = self.exchange.fetch_price(symbol).await?;
-                data.push(MarketData {
-                    symbol: symbol.clone(),
-                    price,
-                    market_cap: 0.0,
-                    price_change_24h: 0.0,
-                    price_change_percentage_24h: 0.0,
-                    high_24h: 0.0,
-                    low_24h: 0.0,
-                    total_volume: 0.0,
-                });
+            match data.iter().find(|d| d.symbol.eq_ignore_ascii_case(symbol)) {
+                Some(row) => rows.push(row.clone()),
+                None => rows.push(self.exchange_row(symbol).await?),
             }
         }
-        Ok(data)
+        Ok(rows)
+    }
+
+    // Scores are reused until sentiment.cache_ttl_secs passes, so refreshes don't refetch them
+    pub async fn cached_sentiment(
+        &self,
+        sentiment_provider: &dyn SentimentProvider,
+        symbol: &str,
+        ttl: Duration,
+    ) -> Result<f64, PortfolioError> {
+        let cached = self.sentiment_cache.lock().unwrap().get(symbol).copied();
+        if let Some((fetched_at, score)) = cached {
+            if fetched_at.elapsed() < ttl {
+                record_cache_lookup(true);
+                return Ok(score);
+            }
+        }
+        record_cache_lookup(false);
+        let score = sentiment_provider.fetch_sentiment(symbol).await?;
+        self.sentiment_cache
+            .lock()
+            .unwrap()
+            .insert(symbol.to_string(), (Instant::now(), score));
+        Ok(score)
+    }
+
+    // The coin list rarely changes, so it is requested on first use and reused every refresh
+    pub async fn coin_names(&self) -> Result<&HashMap<String, String>, PortfolioError> {
+        self.coin_names
+            .get_or_try_init(|| async {
+                let url = format!("{}/coins/list", self.api_url);
+                let _permit = request_permit().await;
+                let started = Instant::now();
+                let resp = self
+                    .client
+                    .get(&url)
+                    .header(USER_AGENT, "crypto_portfolio/0.1")
+                    .header("x-cg-demo-api-key", &self.api_key)
+                    .send()
+                    .await
+                    .map_err(|e| PortfolioError::ExchangeError(e.without_url().to_string()));
+                log_elapsed("fetch_coin_list", started);
+                record_api_call("coingecko", started);
+                let coins: Vec<CoinListEntry> = read_json(resp?, "CoinGecko coin list").await?;
+                Ok::<_, PortfolioError>(
+                    coins.into_iter().map(|coin| (coin.id, coin.name)).collect(),
+                )
+            })
+            .await
+    }
+}
+
+// Matches rows to names by CoinGecko id, falling back to the symbol for rows added by the exchange
+fn attach_names(data: &mut [MarketData], names: &HashMap<String, String>) {
+    for row in data.iter_mut() {
+        row.name = names
+            .get(&row.id)
+            .or_else(|| names.get(&row.symbol))
+            .cloned();
     }
 }
 
 pub async fn display_market_screen<'a>(
     market_provider: &MarketProvider<'a>,
-    pinned_symbols: &[String],
-    sort_by: &str,
-    use_colors: bool,
+    market: &MarketConfig,
+    excluded_symbols: &HashSet<String>,
+    sentiment_provider: Option<&dyn SentimentProvider>,
+    sentiment_config: &SentimentConfig,
+    previous: Option<&mut HashMap<String, (f64, Option<f64>)>>,
+    display: &DisplayConfig,
 ) -> Result<(), PortfolioError> {
-    let market_data = market_provider.fetch_market_data(pinned_symbols).await?;
+    let pinned_symbols = &market.pinned_symbols;
+    let (show_names, show_sparkline) = (market.show_names, market.show_sparkline);
+    let mut market_data = market_provider.fetch_market_data(pinned_symbols).await?;
+    // Dropped before sorting and top movers
+    drop_excluded(&mut market_data, pinned_symbols, excluded_symbols);
+    if show_names {
+        attach_names(&mut market_data, market_provider.coin_names().await?);
+    }
+    let theme = Theme::from_config(display);
+    // Taken from the whole fetched set, before pinned coins are split out
+    let movers = market
+        .show_top_movers
+        .then(|| top_movers_table(&market_data, &theme, display));
+
+    let sentiments = match sentiment_provider {
+        Some(provider) => {
+            pinned_sentiments(market_provider, provider, pinned_symbols, sentiment_config).await?
+        }
+        None => HashMap::new(),
+    };
 
     // Split into pinned and others
     let pinned: Vec<MarketData> = market_data
@@ -110,72 +380,702 @@ pub async fn display_market_screen<'a>(
 
     // Sort others by specified criterion
     let mut others = others;
-    match sort_by {
-        "market_cap" => others.sort_by(|a, b| b.market_cap.partial_cmp(&a.market_cap).unwrap()),
-        "price_change_24h" => {
-            others.sort_by(|a, b| b.price_change_24h.partial_cmp(&a.price_change_24h).unwrap())
-        }
-        _ => others.sort_by(|a, b| b.market_cap.partial_cmp(&a.market_cap).unwrap()),
-    }
+    sort_market(&mut others, &market.sort_by);
 
     // Combine pinned and others
     let final_data = [pinned, others].concat();
 
-    let mut table = Table::new();
-    table.set_header(vec![
-        "S/N",
-        "Symbol",
+    let mut table = new_table(display);
+    // Narrow terminals drop the lower-priority High/Low and volume columns
+    let compact = table
+        .width()
+        .is_some_and(|width| width < COMPACT_TABLE_WIDTH);
+    let mut header = vec!["S/N", "Rank", "Symbol"];
+    if show_names {
+        header.push("Name");
+    }
+    header.extend([
         "Price (USD)",
         "Market Cap (USD)",
         "24h Change (USD)",
         "24h Change (%)",
-        "High (24h)",
-        "Low (24h)",
-        "Total Volume (24h)",
     ]);
+    if show_sparkline {
+        header.push("7d");
+    }
+    if sentiment_provider.is_some() {
+        header.push("Sentiment");
+    }
+    if !compact {
+        header.extend(["High (24h)", "Low (24h)", "Total Volume (24h)"]);
+    }
+    table.set_header(header);
     for (i, data) in final_data.iter().enumerate() {
-        table.add_row(vec![
+        let price_places = price_decimals(data.price, display);
+        let rank = data
+            .market_cap_rank
+            .map(|rank| rank.to_string())
+            .unwrap_or_else(|| "-".to_string());
+        let mut row = vec![
             Cell::new(i + 1),
+            Cell::new(rank),
             Cell::new(data.symbol.to_uppercase()),
-            Cell::new(format!("${}", format_number(data.price, None))),
-            Cell::new(format!("${}", format_number(data.market_cap, None))),
-            set_cell_color(data.price_change_24h, use_colors, false),
-            set_cell_color(data.price_change_percentage_24h, use_colors, true),
-            Cell::new(format!("{}", format_number(data.high_24h, None))),
-            Cell::new(format!("{}", format_number(data.low_24h, None))),
-            Cell::new(format!("${}", format_number(data.total_volume, None))),
+        ];
+        if show_names {
+            row.push(Cell::new(data.name.as_deref().unwrap_or("-")));
+        }
+        row.extend([
+            Cell::new(format!(
+                "${}",
+                format_number(data.price, price_places, None)
+            )),
+            Cell::new(dollars(data.market_cap, display.value_decimals)),
+            set_cell_color(data.price_change_24h, price_places, &theme, false),
+            set_cell_color(data.price_change_percentage_24h, 2, &theme, true),
         ]);
+        if show_sparkline {
+            row.push(Cell::new(
+                data.sparkline
+                    .as_deref()
+                    .map(|prices| sparkline(prices, SPARKLINE_WIDTH))
+                    .filter(|bars| !bars.is_empty())
+                    .unwrap_or_else(|| "-".to_string()),
+            ));
+        }
+        if sentiment_provider.is_some() {
+            row.push(match sentiments.get(&data.symbol) {
+                Some(score) => sentiment_cell(*score, &data.symbol, sentiment_config, &theme),
+                None => Cell::new("-"),
+            });
+        }
+        if !compact {
+            row.extend([
+                Cell::new(format_number(data.high_24h, price_places, None)),
+                Cell::new(format_number(data.low_24h, price_places, None)),
+                Cell::new(dollars(data.total_volume, display.value_decimals)),
+            ]);
+        }
+        match &previous {
+            Some(previous) => {
+                let (price, sentiment) = match previous.get(&data.symbol) {
+                    Some((price, sentiment)) => (Some(*price), *sentiment),
+                    None => (None, None),
+                };
+                let changed = moved(price, Some(data.price))
+                    || moved(sentiment, sentiments.get(&data.symbol).copied());
+                table.add_row(diff_row(row, changed, display.use_colors))
+            }
+            None => table.add_row(row),
+        };
+    }
+    // Remember this cycle's values for the next --diff comparison
+    if let Some(previous) = previous {
+        *previous = final_data
+            .iter()
+            .map(|data| {
+                let sentiment = sentiments.get(&data.symbol).copied();
+                (data.symbol.clone(), (data.price, sentiment))
+            })
+            .collect();
     }
 
-    println!(
-        "=== Live Market Updates ===\nTimestamp: {}\n{}",
-        chrono::Utc::now(),
-        table
-    );
+    println!("=== Live Market Updates ===\nTimestamp: {}", display_now());
+    if let Some(movers) = movers {
+        println!("{}", movers);
+    }
+    println!("{}", table);
     Ok(())
 }
 
-fn set_cell_color(amount: f64, use_colors: bool, use_percentage: bool) -> Cell {
-    let percent = if use_percentage { "%" } else { "" };
-    let change = format!("{}{}", format_number(amount, None), percent);
-    let change_cell = if use_colors {
-        if amount > 0.0 {
-            Cell::new(&change).fg(Color::Green)
-        } else {
-            Cell::new(&change).fg(Color::Red)
+// Excluded symbols (lowercase, e.g., stablecoins) are removed, except any that are pinned
+fn drop_excluded(
+    market_data: &mut Vec<MarketData>,
+    pinned_symbols: &[String],
+    excluded_symbols: &HashSet<String>,
+) {
+    market_data.retain(|data| {
+        pinned_symbols.contains(&data.symbol)
+            || !excluded_symbols.contains(&data.symbol.to_lowercase())
+    });
+}
+
+// Sentiment only for pinned symbols; fetching it for every listed coin would be far too costly
+async fn pinned_sentiments(
+    market_provider: &MarketProvider<'_>,
+    provider: &dyn SentimentProvider,
+    pinned_symbols: &[String],
+    sentiment_config: &SentimentConfig,
+) -> Result<HashMap<String, f64>, PortfolioError> {
+    let mut sentiments = HashMap::new();
+    let ttl = Duration::from_secs(sentiment_config.cache_ttl_secs);
+    for symbol in pinned_symbols {
+        let score = match market_provider
+            .cached_sentiment(provider, symbol, ttl)
+            .await
+        {
+            Ok(score) => Some(score),
+            Err(e) => resolve_missing_sentiment(sentiment_config, symbol, e)?,
+        };
+        if let Some(score) = score {
+            sentiments.insert(symbol.clone(), score);
         }
-    } else {
-        Cell::new(&change)
+    }
+    Ok(sentiments)
+}
+
+// market.sort_by; anything unrecognized sorts by market cap
+fn sort_market(data: &mut [MarketData], sort_by: &str) {
+    match sort_by {
+        "price_change_24h" => sort_descending(data, |data| data.price_change_24h),
+        // Rank 1 first; unranked coins go last
+        "rank" => data.sort_by_key(|data| data.market_cap_rank.unwrap_or(u32::MAX)),
+        _ => sort_descending(data, |data| data.market_cap),
+    }
+}
+
+// Largest first; NaN (a coin CoinGecko couldn't price) sorts last instead of panicking the comparison
+fn sort_descending<T>(items: &mut [T], key: impl Fn(&T) -> f64) {
+    items.sort_by(|a, b| {
+        let (a, b) = (key(a), key(b));
+        match (a.is_nan(), b.is_nan()) {
+            (false, false) => b.total_cmp(&a),
+            (a_nan, b_nan) => a_nan.cmp(&b_nan),
+        }
+    });
+}
+
+// Prices averaged into at most `width` buckets, each drawn as a bar scaled between the low and high
+fn sparkline(prices: &[f64], width: usize) -> String {
+    if prices.is_empty() || width == 0 {
+        return String::new();
+    }
+    let bucket = prices.len().div_ceil(width);
+    let averages: Vec<f64> = prices
+        .chunks(bucket)
+        .map(|chunk| chunk.iter().sum::<f64>() / chunk.len() as f64)
+        .collect();
+    let low = averages.iter().copied().fold(f64::INFINITY, f64::min);
+    let high = averages.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let top = (SPARKLINE_BARS.len() - 1) as f64;
+    averages
+        .iter()
+        .map(|average| {
+            let level = if high > low {
+                ((average - low) / (high - low) * top).round() as usize
+            } else {
+                0
+            };
+            SPARKLINE_BARS[level.min(SPARKLINE_BARS.len() - 1)]
+        })
+        .collect()
+}
+
+// Up to `count` gainers (best first) and losers (worst first) by 24h % change; flat coins are neither
+fn top_movers(data: &[MarketData], count: usize) -> (Vec<&MarketData>, Vec<&MarketData>) {
+    let mut ranked: Vec<&MarketData> = data
+        .iter()
+        .filter(|data| !data.price_change_percentage_24h.is_nan())
+        .collect();
+    sort_descending(&mut ranked, |data| data.price_change_percentage_24h);
+    let gainers = ranked
+        .iter()
+        .take_while(|data| data.price_change_percentage_24h > 0.0)
+        .take(count)
+        .copied()
+        .collect();
+    let losers = ranked
+        .iter()
+        .rev()
+        .take_while(|data| data.price_change_percentage_24h < 0.0)
+        .take(count)
+        .copied()
+        .collect();
+    (gainers, losers)
+}
+
+// Gainers and losers side by side, one row per rank
+fn top_movers_table(data: &[MarketData], theme: &Theme, display: &DisplayConfig) -> Table {
+    let (gainers, losers) = top_movers(data, TOP_MOVERS);
+    let mut table = new_table(display);
+    table.set_header(vec!["Top Gainers", "24h (%)", "Top Losers", "24h (%)"]);
+    let mover_cells = |mover: Option<&&MarketData>| match mover {
+        Some(data) => [
+            Cell::new(data.symbol.to_uppercase()),
+            percent_cell(data.price_change_percentage_24h, 2, theme),
+        ],
+        None => [Cell::new(""), Cell::new("")],
     };
-    change_cell
+    for rank in 0..gainers.len().max(losers.len()) {
+        let mut row = Vec::from(mover_cells(gainers.get(rank)));
+        row.extend(mover_cells(losers.get(rank)));
+        table.add_row(row);
+    }
+    table
+}
+
+// Read-only view of coins that aren't held; no Portfolio or trading logic involved
+pub async fn display_watchlist_screen<'a>(
+    market_provider: &MarketProvider<'a>,
+    sentiment_provider: &dyn SentimentProvider,
+    symbols: &[String],
+    sentiment_config: &SentimentConfig,
+    display: &DisplayConfig,
+) -> Result<(), PortfolioError> {
+    // Every fetch happens before rendering, so the bar follows the requests rather than the rows
+    let progress = fetch_progress(symbols.len(), display);
+    let rows = market_provider.fetch_watchlist(symbols).await?;
+    let mut sentiments = Vec::new();
+    for symbol in symbols {
+        sentiments.push(match sentiment_provider.fetch_sentiment(symbol).await {
+            Ok(sentiment) => Some(sentiment),
+            Err(e) => resolve_missing_sentiment(sentiment_config, symbol, e)?,
+        });
+        progress.inc(1);
+    }
+    progress.finish_and_clear();
+
+    let theme = Theme::from_config(display);
+    let mut table = new_table(display);
+    table.set_header(vec![
+        "Symbol",
+        "Price (USD)",
+        "24h Change (%)",
+        "Sentiment",
+        "Recommendation",
+    ]);
+    for ((symbol, data), sentiment) in symbols.iter().zip(&rows).zip(sentiments) {
+        let bands = sentiment_config.bands_for(symbol);
+        let mut row = vec![
+            Cell::new(symbol.to_uppercase()),
+            Cell::new(format!(
+                "${}",
+                format_number(data.price, price_decimals(data.price, display), None)
+            )),
+            set_cell_color(data.price_change_percentage_24h, 2, &theme, true),
+        ];
+        match sentiment {
+            Some(sentiment) => row.extend([
+                Cell::new(format!("{:.2}", sentiment)),
+                recommendation_cell(sentiment, &bands, &theme),
+            ]),
+            None => row.extend([Cell::new("N/A"), Cell::new("-")]),
+        }
+        table.add_row(row);
+    }
+
+    println!("=== Watchlist ===\nTimestamp: {}\n{}", display_now(), table);
+    Ok(())
+}
+
+// Score colored by the same recommendation buckets as the sentiment screen
+fn sentiment_cell(
+    score: f64,
+    symbol: &str,
+    sentiment_config: &SentimentConfig,
+    theme: &Theme,
+) -> Cell {
+    let (label, tone) = recommendation(score, &sentiment_config.bands_for(symbol));
+    theme.cell(format!("{:.2} ({})", score, label), tone)
 }
 
-fn format_number(amount: f64, locale: Option<Locale>) -> String {
+fn set_cell_color(amount: f64, decimals: usize, theme: &Theme, use_percentage: bool) -> Cell {
+    // A change the source didn't report is neither up nor down
+    if amount.is_nan() {
+        return Cell::new("-");
+    }
+    if use_percentage {
+        return percent_cell(amount, decimals, theme);
+    }
+    change_cell(format_number(amount, decimals, None), amount, theme)
+}
+
+fn format_number(amount: f64, decimals: usize, locale: Option<Locale>) -> String {
     let locale = locale.unwrap_or(locale!("en-US"));
 
     let formatter = DecimalFormatter::try_new(locale.into(), Default::default())
         .expect("locale should be present");
 
-    let decimal = Decimal::from_str(&amount.to_string()).unwrap();
-    formatter.format(&decimal).to_string()
+    // Built from the f64 itself and rounded to `decimals`, so no intermediate string shapes the digits
+    let places = decimals as i16;
+    match Decimal::try_from_f64(amount, FloatPrecision::Magnitude(-places)) {
+        Ok(mut decimal) => {
+            decimal.pad_end(-places);
+            formatter.format(&decimal).to_string()
+        }
+        // NaN marks a figure the source didn't report; infinity has no decimal form
+        Err(_) if amount.is_nan() => "-".to_string(),
+        Err(_) => format!("{:.*}", decimals, amount),
+    }
+}
+
+// "$1,234.50", or "-" when the figure is unavailable
+fn dollars(amount: f64, decimals: usize) -> String {
+    if amount.is_nan() {
+        return "-".to_string();
+    }
+    format!("${}", format_number(amount, decimals, None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::{BinanceExchange, DetailedSentiment, NullSentimentProvider};
+    use crate::http::testing::mock_server;
+    use async_trait::async_trait;
+
+    struct StubExchange;
+
+    #[async_trait]
+    impl Exchange for StubExchange {
+        async fn fetch_price(&self, _symbol: &str) -> Result<f64, PortfolioError> {
+            Ok(1.0)
+        }
+
+        fn name(&self) -> &str {
+            "stub"
+        }
+    }
+
+    // Scores every symbol 0.8 except "dusk", which fails, and records what it was asked for
+    #[derive(Default)]
+    struct RecordingSentiment {
+        asked: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl SentimentProvider for RecordingSentiment {
+        async fn fetch_sentiment(&self, symbol: &str) -> Result<f64, PortfolioError> {
+            self.asked.lock().unwrap().push(symbol.to_string());
+            match symbol {
+                "dusk" => Err(PortfolioError::ApiError("no data".to_string())),
+                _ => Ok(0.8),
+            }
+        }
+
+        async fn fetch_detailed_sentiment(
+            &self,
+            _symbol: &str,
+        ) -> Result<DetailedSentiment, PortfolioError> {
+            Ok(DetailedSentiment::default())
+        }
+
+        fn name(&self) -> &str {
+            "recording"
+        }
+    }
+
+    fn sentiment_config() -> SentimentConfig {
+        toml::from_str(
+            "api_key = \"\"\ncache_ttl_secs = 60\npositive_threshold = 0.7\nnegative_threshold = 0.3",
+        )
+        .unwrap()
+    }
+
+    // A CoinGecko markets row for `id`, formatted as the API sends it
+    fn markets_json(id: &str, symbol: &str) -> String {
+        format!(
+            r#"{{"id":"{}","symbol":"{}","current_price":2.0,"market_cap":1.0e9,"price_change_24h":0.1,
+                "price_change_percentage_24h":5.0,"high_24h":2.1,"low_24h":1.9,"total_volume":1.0e7}}"#,
+            id, symbol
+        )
+    }
+
+    fn market_row(id: &str, symbol: &str, change: f64) -> MarketData {
+        let mut row = MarketData::exchange_priced(symbol, 1.0, None);
+        row.id = id.to_string();
+        row.price_change_percentage_24h = change;
+        row
+    }
+
+    #[tokio::test]
+    async fn known_coins_get_their_names() {
+        let (url, _requests) = mock_server(|request| {
+            assert!(request.starts_with("GET /coins/list"));
+            (
+                200,
+                r#"[{"id":"bitcoin","symbol":"btc","name":"Bitcoin"},
+                    {"id":"pha","symbol":"pha","name":"Phala Network"}]"#
+                    .to_string(),
+            )
+        })
+        .await;
+        let provider = MarketProvider::new(&url, "", 10, false, "none", false, &StubExchange);
+        let mut data = vec![
+            market_row("bitcoin", "btc", 0.0),
+            market_row("pha", "pha", 0.0),
+            market_row("unknowncoin", "xyz", 0.0),
+        ];
+        attach_names(&mut data, provider.coin_names().await.unwrap());
+        let names: Vec<Option<&str>> = data.iter().map(|row| row.name.as_deref()).collect();
+        assert_eq!(names, vec![Some("Bitcoin"), Some("Phala Network"), None]);
+    }
+
+    #[tokio::test]
+    async fn watchlist_rows_follow_the_requested_symbols() {
+        let (url, mut requests) = mock_server(|_| {
+            (
+                200,
+                r#"[{"id":"bitcoin","symbol":"btc","current_price":50000.0,"market_cap":1.0e12,
+                    "price_change_24h":500.0,"price_change_percentage_24h":1.0,"high_24h":51000.0,
+                    "low_24h":49000.0,"total_volume":3.0e10}]"#
+                    .to_string(),
+            )
+        })
+        .await;
+        let provider = MarketProvider::new(&url, "", 10, false, "none", false, &StubExchange);
+        let symbols = vec!["pha".to_string(), "BTC".to_string()];
+        let rows = provider.fetch_watchlist(&symbols).await.unwrap();
+        let prices: Vec<(&str, f64)> = rows
+            .iter()
+            .map(|row| (row.symbol.as_str(), row.price))
+            .collect();
+        // PHA isn't listed, so the exchange prices it
+        assert_eq!(prices, vec![("pha", 1.0), ("btc", 50000.0)]);
+        assert!(requests
+            .recv()
+            .await
+            .unwrap()
+            .starts_with("GET /coins/markets"));
+
+        let display: DisplayConfig =
+            toml::from_str("sentiment_refresh_secs = 60\nuse_colors = false").unwrap();
+        display_watchlist_screen(
+            &provider,
+            &NullSentimentProvider,
+            &symbols,
+            &sentiment_config(),
+            &display,
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn sentiment_is_fetched_for_pinned_symbols_only() {
+        let provider = MarketProvider::new(
+            "http://localhost",
+            "",
+            10,
+            false,
+            "none",
+            false,
+            &StubExchange,
+        );
+        let sentiment = RecordingSentiment::default();
+        let pinned = vec!["sui".to_string(), "dusk".to_string()];
+        let scores = pinned_sentiments(&provider, &sentiment, &pinned, &sentiment_config())
+            .await
+            .unwrap();
+        // DUSK's failed fetch is skipped under the default on_missing
+        assert_eq!(scores, HashMap::from([("sui".to_string(), 0.8)]));
+        assert_eq!(*sentiment.asked.lock().unwrap(), pinned);
+    }
+
+    #[tokio::test]
+    async fn markets_requests_follow_top_n_and_add_pinned_ids() {
+        let (url, mut requests) = mock_server(|request| {
+            let body = if request.contains("ids=pha") {
+                markets_json("pha", "pha")
+            } else {
+                markets_json("bitcoin", "btc")
+            };
+            (200, format!("[{}]", body))
+        })
+        .await;
+        let provider = MarketProvider::new(&url, "", 3, false, "none", false, &StubExchange);
+        let pinned = vec!["pha".to_string(), "bitcoin".to_string()];
+        let rows = provider.fetch_listed(&pinned).await.unwrap();
+        let ids: Vec<&str> = rows.iter().map(|row| row.id.as_str()).collect();
+        assert_eq!(ids, vec!["bitcoin", "pha"]);
+
+        let top = requests.recv().await.unwrap();
+        assert!(top.contains("per_page=3&"), "{}", top);
+        // Only the pinned symbol the top 3 missed is requested by id
+        let pinned = requests.recv().await.unwrap();
+        assert!(pinned.contains("ids=pha&"), "{}", pinned);
+    }
+
+    #[tokio::test]
+    async fn coingecko_error_bodies_are_surfaced_or_fall_back() {
+        let (url, _) = mock_server(|_| {
+            (
+                200,
+                r#"{"status":{"error_code":429,"error_message":"You've exceeded the Rate Limit"}}"#
+                    .to_string(),
+            )
+        })
+        .await;
+        let provider = MarketProvider::new(&url, "", 10, false, "none", false, &StubExchange);
+        let error = provider.fetch_market_data(&[]).await.unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("You've exceeded the Rate Limit (code 429)"),
+            "{}",
+            error
+        );
+
+        // With exchange_fallback the pinned symbols are still priced by the exchange
+        let provider = MarketProvider::new(&url, "", 10, true, "none", false, &StubExchange);
+        let rows = provider
+            .fetch_market_data(&["sui".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!((rows[0].symbol.as_str(), rows[0].price), ("sui", 1.0));
+        assert!(rows[0].price_change_percentage_24h.is_nan());
+
+        assert_eq!(
+            coingecko_error(&serde_json::json!({"error": "coin not found"})),
+            "coin not found"
+        );
+    }
+
+    #[test]
+    fn numbers_format_stably() {
+        assert_eq!(format_number(0.1 + 0.2, 2, None), "0.30");
+        assert_eq!(format_number(0.1 + 0.2, 17, None), "0.30000000000000004");
+        assert_eq!(
+            format_number(1_234_567_890_123.0, 2, None),
+            "1,234,567,890,123.00"
+        );
+        assert_eq!(format_number(0.00000123, 8, None), "0.00000123");
+        assert_eq!(format_number(0.00000123, 2, None), "0.00");
+        assert_eq!(format_number(-42.6, 0, None), "-43");
+        assert_eq!(format_number(f64::NAN, 2, None), "-");
+        assert_eq!(format_number(1234.5, 2, Some(locale!("de-DE"))), "1.234,50");
+    }
+
+    #[test]
+    fn top_movers_take_the_biggest_gains_and_losses() {
+        let data = vec![
+            market_row("a", "a", 3.0),
+            market_row("b", "b", -8.0),
+            market_row("c", "c", 12.0),
+            market_row("d", "d", 0.0),
+            market_row("e", "e", f64::NAN),
+            market_row("f", "f", -1.5),
+            market_row("g", "g", 7.0),
+            market_row("h", "h", -20.0),
+        ];
+        let symbols = |movers: Vec<&MarketData>| -> Vec<String> {
+            movers.iter().map(|data| data.symbol.clone()).collect()
+        };
+        let (gainers, losers) = top_movers(&data, 2);
+        assert_eq!(symbols(gainers), vec!["c", "g"]);
+        assert_eq!(symbols(losers), vec!["h", "b"]);
+
+        // Flat and unreported coins are never movers, even with room to spare
+        let (gainers, losers) = top_movers(&data, 5);
+        assert_eq!(symbols(gainers), vec!["c", "g", "a"]);
+        assert_eq!(symbols(losers), vec!["h", "b", "f"]);
+    }
+
+    #[test]
+    fn ranks_deserialize_and_sort_unranked_last() {
+        let with_rank = |id: &str, rank: &str| {
+            markets_json(id, id).replacen('{', &format!(r#"{{"market_cap_rank":{},"#, rank), 1)
+        };
+        let body = format!(
+            "[{},{},{},{}]",
+            with_rank("ether", "2"),
+            with_rank("newcoin", "null"),
+            markets_json("oldcoin", "oldcoin"),
+            with_rank("bitcoin", "1")
+        );
+        let mut data: Vec<MarketData> = serde_json::from_str(&body).unwrap();
+        let ranks: Vec<Option<u32>> = data.iter().map(|data| data.market_cap_rank).collect();
+        assert_eq!(ranks, vec![Some(2), None, None, Some(1)]);
+
+        sort_market(&mut data, "rank");
+        let ids: Vec<&str> = data.iter().map(|data| data.id.as_str()).collect();
+        // The sort is stable, so unranked coins keep their listed order
+        assert_eq!(ids, vec!["bitcoin", "ether", "newcoin", "oldcoin"]);
+    }
+
+    #[test]
+    fn excluded_symbols_are_dropped_unless_pinned() {
+        let mut data = vec![
+            market_row("bitcoin", "btc", 1.0),
+            market_row("tether", "USDT", 0.0),
+            market_row("usd-coin", "usdc", 0.0),
+            market_row("dai", "dai", 0.0),
+        ];
+        let excluded = HashSet::from(["usdt".to_string(), "usdc".to_string(), "dai".to_string()]);
+        drop_excluded(&mut data, &["dai".to_string()], &excluded);
+        let symbols: Vec<&str> = data.iter().map(|data| data.symbol.as_str()).collect();
+        assert_eq!(symbols, vec!["btc", "dai"]);
+    }
+
+    #[tokio::test]
+    async fn unlisted_symbols_get_binance_24h_stats() {
+        // CoinGecko lists nothing; Binance prices PHA and has its rolling 24h ticker
+        let (url, _) = mock_server(|request| {
+            if request.starts_with("GET /coins/markets") {
+                (200, "[]".to_string())
+            } else if request.starts_with("GET /api/v3/ticker/price?symbol=PHAUSDT ") {
+                (200, r#"{"symbol":"PHAUSDT","price":"0.1200"}"#.to_string())
+            } else if request.starts_with("GET /api/v3/ticker/24hr?symbol=PHAUSDT ") {
+                (
+                    200,
+                    r#"{"priceChange":"0.0060","priceChangePercent":"5.263","highPrice":"0.1250",
+                        "lowPrice":"0.1100","quoteVolume":"1500000.5"}"#
+                        .to_string(),
+                )
+            } else {
+                (404, String::new())
+            }
+        })
+        .await;
+        let binance = BinanceExchange::new(
+            &url,
+            "key",
+            "secret",
+            0.1,
+            HashMap::new(),
+            vec!["USDT".to_string()],
+            false,
+        );
+        let pha = vec!["pha".to_string()];
+        let provider = MarketProvider::new(&url, "", 10, false, "exchange", false, &binance);
+        let rows = provider.fetch_market_data(&pha).await.unwrap();
+        let row = &rows[0];
+        assert_eq!((row.symbol.as_str(), row.price), ("pha", 0.12));
+        assert_eq!(row.price_change_24h, 0.006);
+        assert_eq!(row.price_change_percentage_24h, 5.263);
+        assert_eq!((row.high_24h, row.low_24h), (0.125, 0.11));
+        assert_eq!(row.total_volume, 1500000.5);
+
+        // "none" leaves the same row's stats blank
+        let provider = MarketProvider::new(&url, "", 10, false, "none", false, &binance);
+        let rows = provider.fetch_market_data(&pha).await.unwrap();
+        assert_eq!(rows[0].price, 0.12);
+        assert!(rows[0].high_24h.is_nan());
+    }
+
+    #[test]
+    fn sparklines_deserialize_and_draw_one_bar_per_bucket() {
+        let prices: Vec<String> = (0..168).map(|hour| format!("{}.0", hour % 24)).collect();
+        let body = markets_json("sui", "sui").replacen(
+            '{',
+            &format!(
+                r#"{{"sparkline_in_7d":{{"price":[{},null]}},"#,
+                prices.join(",")
+            ),
+            1,
+        );
+        let row: MarketData = serde_json::from_str(&body).unwrap();
+        // The null gap is dropped
+        let prices = row.sparkline.unwrap();
+        assert_eq!(prices.len(), 168);
+
+        let bars = sparkline(&prices, SPARKLINE_WIDTH);
+        assert_eq!(bars.chars().count(), SPARKLINE_WIDTH);
+        assert!(bars.chars().all(|bar| SPARKLINE_BARS.contains(&bar)));
+        assert_eq!(sparkline(&[1.0, 2.0, 3.0], SPARKLINE_WIDTH), "▁▅█");
+        assert_eq!(sparkline(&[], SPARKLINE_WIDTH), "");
+
+        let row: MarketData = serde_json::from_str(&markets_json("sui", "sui")).unwrap();
+        assert!(row.sparkline.is_none());
+    }
 }
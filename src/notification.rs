@@ -1,32 +1,176 @@
 use crate::config::NotificationConfig;
 use crate::errors::PortfolioError;
+use crate::logger::{display_now, log_action};
 use crate::portfolio::Portfolio;
+use crate::server::{publish, Event};
+use async_trait::async_trait;
+use chrono::Utc;
 use reqwest::Client;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        }
+    }
+}
+
+// Every channel name notification.routing may list
+const CHANNELS: [&str; 2] = ["sms", "email"];
+
+// One way of delivering an alert; adding a channel is one impl plus a line in build_channels
+#[async_trait]
+pub trait NotificationChannel: Send + Sync {
+    // Name used in notification.routing and test-notify --channel, e.g., "sms"
+    fn name(&self) -> &str;
+
+    async fn send(
+        &self,
+        subject: &str,
+        body: &str,
+        severity: Severity,
+    ) -> Result<(), PortfolioError>;
+}
+
+// The channels enabled in [notification], in the order alerts go out
+fn build_channels(config: &NotificationConfig) -> Vec<Box<dyn NotificationChannel>> {
+    let mut channels: Vec<Box<dyn NotificationChannel>> = Vec::new();
+    if config.sms_enabled {
+        channels.push(Box::new(SmsChannel {
+            client: Client::new(),
+            config: config.clone(),
+        }));
+    }
+    if config.email_enabled {
+        channels.push(Box::new(EmailChannel {
+            client: Client::new(),
+            config: config.clone(),
+        }));
+    }
+    channels
+}
 
 pub struct Notifier {
-    client: Client,
+    channels: Vec<Box<dyn NotificationChannel>>,
     config: NotificationConfig,
+    stale_symbols: Mutex<HashSet<String>>, // Already alerted; cleared once fresh data arrives
+    overweight_alerts: Mutex<HashMap<String, Instant>>, // Last allocation alert; cleared once back under the cap
 }
 
 impl Notifier {
     pub fn new(config: NotificationConfig) -> Self {
         Notifier {
-            client: Client::new(),
+            channels: build_channels(&config),
             config,
+            stale_symbols: Mutex::new(HashSet::new()),
+            overweight_alerts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Channels from notification.routing for this severity; unrouted severities use every channel
+    pub fn channels_for(&self, severity: Severity) -> Vec<String> {
+        match self.config.routing.get(severity.as_str()) {
+            Some(channels) => channels.iter().map(|c| c.to_lowercase()).collect(),
+            None => CHANNELS.iter().map(|c| c.to_string()).collect(),
         }
     }
 
-    pub async fn notify_significant_action(&self, action: &str) -> Result<(), PortfolioError> {
-        if self.config.sms_enabled {
-            self.send_sms(action).await?;
+    // Channels switched on in [notification], in the order test-notify checks them
+    pub fn enabled_channels(&self) -> Vec<&str> {
+        self.channels.iter().map(|channel| channel.name()).collect()
+    }
+
+    // Sends a fixed message through one channel, bypassing routing, so credentials can be checked
+    pub async fn send_test(&self, channel: &str) -> Result<(), PortfolioError> {
+        let message = format!(
+            "Test notification from crypto_portfolio at {}",
+            display_now()
+        );
+        match self.channels.iter().find(|c| c.name() == channel) {
+            Some(enabled) => {
+                enabled
+                    .send("Test Notification", &message, Severity::Info)
+                    .await
+            }
+            None if CHANNELS.contains(&channel) => Err(PortfolioError::NotificationError(format!(
+                "{} is disabled in [notification]",
+                channel
+            ))),
+            None => Err(PortfolioError::NotificationError(format!(
+                "Unknown notification channel '{}'",
+                channel
+            ))),
+        }
+    }
+
+    async fn dispatch(
+        &self,
+        severity: Severity,
+        subject: &str,
+        message: &str,
+    ) -> Result<(), PortfolioError> {
+        // Connected dashboards see every alert, whatever the SMS/email routing
+        publish(Event::Alert {
+            severity: severity.as_str().to_string(),
+            subject: subject.to_string(),
+            message: message.to_string(),
+            timestamp: Utc::now(),
+        });
+        let routed = self.channels_for(severity);
+        for unknown in routed
+            .iter()
+            .filter(|name| !CHANNELS.contains(&name.as_str()))
+        {
+            let _ = log_action(
+                &format!(
+                    "Unknown notification channel '{}' for {} alerts",
+                    unknown,
+                    severity.as_str()
+                ),
+                None,
+            );
         }
-        if self.config.email_enabled {
-            self.send_email("Portfolio Action", action).await?;
+        for channel in &self.channels {
+            if routed.iter().any(|name| name == channel.name()) {
+                channel.send(subject, message, severity).await?;
+            }
         }
         Ok(())
     }
 
+    pub async fn notify_significant_action(
+        &self,
+        severity: Severity,
+        action: &str,
+    ) -> Result<(), PortfolioError> {
+        self.dispatch(severity, "Portfolio Action", action).await
+    }
+
+    // Executed buys and sells; sent regardless of the value-change thresholds unless
+    // notification.always_notify_trades is turned off
+    pub async fn notify_trade(
+        &self,
+        severity: Severity,
+        details: &str,
+    ) -> Result<(), PortfolioError> {
+        if !self.config.always_notify_trades {
+            return Ok(());
+        }
+        self.dispatch(severity, "Trade Executed", details).await
+    }
+
     pub async fn notify_major_change(
         &self,
         portfolio: &Portfolio,
@@ -35,8 +179,12 @@ impl Notifier {
         previous_prices: &HashMap<String, f64>,
         current_prices: &HashMap<String, f64>,
     ) -> Result<(), PortfolioError> {
-        let value_change_percent =
-            ((current_value - previous_value) / previous_value.abs()) * 100.0;
+        // No baseline yet (first cycle without a persisted one): nothing to compare against
+        let value_change_percent = if previous_value > 0.0 {
+            ((current_value - previous_value) / previous_value.abs()) * 100.0
+        } else {
+            0.0
+        };
         if value_change_percent.abs()
             > self
                 .config
@@ -47,13 +195,8 @@ impl Notifier {
                 "Portfolio value changed by {:.2}%: Previous ${:.2}, Current ${:.2}",
                 value_change_percent, previous_value, current_value
             );
-            if self.config.sms_enabled {
-                self.send_sms(&msg).await?;
-            }
-            if self.config.email_enabled {
-                self.send_email("Portfolio Value Change Alert", &msg)
-                    .await?;
-            }
+            self.dispatch(Severity::Warning, "Portfolio Value Change Alert", &msg)
+                .await?;
         }
 
         for holding in &portfolio.holdings {
@@ -72,18 +215,78 @@ impl Notifier {
                         "{} price changed by {:.2}%: Previous ${:.2}, Current ${:.2}",
                         holding.symbol, price_change_percent, prev_price, curr_price
                     );
-                    if self.config.sms_enabled {
-                        self.send_sms(&msg).await?;
-                    }
-                    if self.config.email_enabled {
-                        self.send_email("Holding Price Change Alert", &msg).await?;
-                    }
+                    self.dispatch(Severity::Warning, "Holding Price Change Alert", &msg)
+                        .await?;
                 }
             }
         }
         Ok(())
     }
 
+    // Alerts once when a symbol's freshest live data exceeds notification.max_data_age_secs
+    pub async fn notify_stale_data(
+        &self,
+        symbol: &str,
+        age: Duration,
+    ) -> Result<(), PortfolioError> {
+        let max_age = self.config.max_data_age_secs;
+        if max_age == 0 || age.as_secs() <= max_age {
+            self.stale_symbols.lock().unwrap().remove(symbol);
+            return Ok(());
+        }
+        if !self
+            .stale_symbols
+            .lock()
+            .unwrap()
+            .insert(symbol.to_string())
+        {
+            return Ok(());
+        }
+        let msg = format!(
+            "{} price data is {}s old (limit {}s); live fetches are failing",
+            symbol,
+            age.as_secs(),
+            max_age
+        );
+        let _ = log_action(&msg, None);
+        self.dispatch(Severity::Warning, "Stale Data Alert", &msg)
+            .await
+    }
+
+    // Alerts when a holding's share of the portfolio exceeds portfolio.max_allocation, then at most
+    // once per notification.allocation_alert_cooldown_secs while it stays overweight
+    pub async fn notify_overweight(
+        &self,
+        symbol: &str,
+        allocation: f64,
+        max_allocation: f64,
+    ) -> Result<(), PortfolioError> {
+        if allocation <= max_allocation {
+            self.overweight_alerts.lock().unwrap().remove(symbol);
+            return Ok(());
+        }
+        {
+            let mut alerts = self.overweight_alerts.lock().unwrap();
+            let cooldown = Duration::from_secs(self.config.allocation_alert_cooldown_secs);
+            if alerts
+                .get(symbol)
+                .is_some_and(|last| last.elapsed() < cooldown)
+            {
+                return Ok(());
+            }
+            alerts.insert(symbol.to_string(), Instant::now());
+        }
+        let msg = format!(
+            "{} is {:.1}% of the portfolio, above the {:.1}% max_allocation; consider rebalancing",
+            symbol,
+            allocation * 100.0,
+            max_allocation * 100.0
+        );
+        let _ = log_action(&msg, None);
+        self.dispatch(Severity::Warning, "Allocation Alert", &msg)
+            .await
+    }
+
     pub async fn notify_sentiment_change(
         &self,
         symbol: &str,
@@ -96,44 +299,86 @@ impl Notifier {
                 "{} sentiment changed by {:.2}: Previous {:.2}, Current {:.2}",
                 symbol, sentiment_change, previous_sentiment, current_sentiment
             );
-            if self.config.sms_enabled {
-                self.send_sms(&msg).await?;
-            }
-            if self.config.email_enabled {
-                self.send_email("Sentiment Change Alert", &msg).await?;
-            }
+            self.dispatch(Severity::Info, "Sentiment Change Alert", &msg)
+                .await?;
         }
         Ok(())
     }
+}
+
+// Twilio SMS, cut to 115 characters
+struct SmsChannel {
+    client: Client,
+    config: NotificationConfig,
+}
+
+#[async_trait]
+impl NotificationChannel for SmsChannel {
+    fn name(&self) -> &str {
+        "sms"
+    }
+
+    async fn send(
+        &self,
+        _subject: &str,
+        message: &str,
+        _severity: Severity,
+    ) -> Result<(), PortfolioError> {
+        // Counted in characters, so multibyte text is never cut mid-character
+        let truncated_message: String = message.chars().take(115).collect();
+        let url = format!(
+            "{}/Accounts/{}/Messages.json",
+            self.config.twilio_api_url.trim_end_matches('/'),
+            self.config.twilio_account_sid
+        );
+        let response = self
+            .client
+            .post(url)
+            .basic_auth(
+                &self.config.twilio_account_sid,
+                Some(&self.config.twilio_auth_token),
+            )
+            .form(&[
+                ("From", self.config.twilio_phone_number.as_str()),
+                ("To", self.config.recipient_phone_number.as_str()),
+                ("Body", truncated_message.as_str()),
+            ])
+            .send()
+            .await
+            .map_err(|e| {
+                PortfolioError::NotificationError(format!("SMS failed: {}", e.without_url()))
+            })?;
 
-    async fn send_sms(&self, message: &str) -> Result<(), PortfolioError> {
-        let truncated_message = message[0..message.len().min(115)].to_string(); // Convert to String
-                                                                                // let response = self
-                                                                                //     .client
-                                                                                //     .post("https://api.twilio.com/2010-04-01/Accounts")
-                                                                                //     .basic_auth(
-                                                                                //         &self.config.twilio_account_sid,
-                                                                                //         Some(&self.config.twilio_auth_token),
-                                                                                //     )
-                                                                                //     .form(&[
-                                                                                //         ("From", &self.config.twilio_phone_number),
-                                                                                //         ("To", &self.config.recipient_phone_number),
-                                                                                //         ("Body", &truncated_message), // Use String
-                                                                                //     ])
-                                                                                //     .send()
-                                                                                //     .await
-                                                                                //     .map_err(|e| PortfolioError::NotificationError(e.to_string()))?;
-
-        // if !response.status().is_success() {
-        //     return Err(PortfolioError::NotificationError(format!(
-        //         "SMS failed: {}",
-        //         response.text().await.unwrap_or_default()
-        //     )));
-        // }
+        if !response.status().is_success() {
+            let status = response.status();
+            return Err(PortfolioError::NotificationError(format!(
+                "SMS failed with {}: {}",
+                status,
+                response.text().await.unwrap_or_default()
+            )));
+        }
         Ok(())
     }
+}
 
-    async fn send_email(&self, subject: &str, body: &str) -> Result<(), PortfolioError> {
+// SendGrid email with the subject as a heading
+struct EmailChannel {
+    client: Client,
+    config: NotificationConfig,
+}
+
+#[async_trait]
+impl NotificationChannel for EmailChannel {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    async fn send(
+        &self,
+        subject: &str,
+        body: &str,
+        _severity: Severity,
+    ) -> Result<(), PortfolioError> {
         let email = serde_json::json!({
             "personalizations": [{
                 "to": [{"email": &self.config.recipient_email}]
@@ -142,25 +387,285 @@ impl Notifier {
             "subject": subject,
             "content": [{
                 "type": "text/html",
-                "value": format!("<h2>{}</h2><p>{}</p><p><strong>Timestamp:</strong> {}</p>", subject, body, chrono::Utc::now())
+                "value": format!("<h2>{}</h2><p>{}</p><p><strong>Timestamp:</strong> {}</p>", subject, body, display_now())
             }]
         });
 
-        // let response = self
-        //     .client
-        //     .post("https://api.sendgrid.com/v3/mail/send")
-        //     .bearer_auth(&self.config.sendgrid_api_key)
-        //     .json(&email)
-        //     .send()
-        //     .await
-        //     .map_err(|e| PortfolioError::NotificationError(e.to_string()))?;
-
-        // if !response.status().is_success() {
-        //     return Err(PortfolioError::NotificationError(format!(
-        //         "Email failed: {}",
-        //         response.text().await.unwrap_or_default()
-        //     )));
-        // }
+        let response = self
+            .client
+            .post(format!(
+                "{}/mail/send",
+                self.config.sendgrid_api_url.trim_end_matches('/')
+            ))
+            .bearer_auth(&self.config.sendgrid_api_key)
+            .json(&email)
+            .send()
+            .await
+            .map_err(|e| {
+                PortfolioError::NotificationError(format!("Email failed: {}", e.without_url()))
+            })?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            return Err(PortfolioError::NotificationError(format!(
+                "Email failed with {}: {}",
+                status,
+                response.text().await.unwrap_or_default()
+            )));
+        }
         Ok(())
     }
 }
+
+// Notifier whose channels record "channel: subject" instead of sending
+#[cfg(test)]
+pub mod testing {
+    use super::*;
+    use std::sync::Arc;
+
+    // Records "channel: subject" for every alert it is handed
+    struct RecordingChannel {
+        name: &'static str,
+        sent: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl NotificationChannel for RecordingChannel {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        async fn send(
+            &self,
+            subject: &str,
+            _body: &str,
+            _severity: Severity,
+        ) -> Result<(), PortfolioError> {
+            self.sent
+                .lock()
+                .unwrap()
+                .push(format!("{}: {}", self.name, subject));
+            Ok(())
+        }
+    }
+
+    pub fn recording_notifier(config: NotificationConfig) -> (Notifier, Arc<Mutex<Vec<String>>>) {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let channels = CHANNELS
+            .iter()
+            .map(|name| {
+                Box::new(RecordingChannel {
+                    name,
+                    sent: sent.clone(),
+                }) as Box<dyn NotificationChannel>
+            })
+            .collect();
+        let notifier = Notifier {
+            channels,
+            config,
+            stale_symbols: Mutex::new(HashSet::new()),
+            overweight_alerts: Mutex::new(HashMap::new()),
+        };
+        (notifier, sent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::recording_notifier;
+    use super::*;
+    use crate::http::testing::mock_server;
+
+    fn test_config() -> NotificationConfig {
+        toml::from_str(
+            r#"
+            sms_enabled = false
+            email_enabled = false
+            twilio_account_sid = "AC123"
+            twilio_auth_token = "token"
+            twilio_phone_number = "+15550001"
+            recipient_phone_number = "+15550002"
+            sendgrid_api_key = "SG.key"
+            sender_email = "portfolio@example.com"
+            recipient_email = "me@example.com"
+
+            [notification_thresholds]
+            portfolio_value_change_percent = 10.0
+            holding_value_change_percent = 15.0
+            sentiment_change = 0.2
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn unrouted_alerts_reach_every_channel() {
+        let (notifier, sent) = recording_notifier(test_config());
+        notifier
+            .notify_significant_action(Severity::Critical, "Sold SUI")
+            .await
+            .unwrap();
+        assert_eq!(
+            *sent.lock().unwrap(),
+            vec!["sms: Portfolio Action", "email: Portfolio Action"]
+        );
+    }
+
+    #[tokio::test]
+    async fn routing_limits_channels() {
+        let mut config = test_config();
+        config
+            .routing
+            .insert("info".to_string(), vec!["email".to_string()]);
+        let (notifier, sent) = recording_notifier(config);
+        notifier
+            .notify_significant_action(Severity::Info, "Bought SUI")
+            .await
+            .unwrap();
+        assert_eq!(*sent.lock().unwrap(), vec!["email: Portfolio Action"]);
+    }
+
+    #[tokio::test]
+    async fn sms_truncates_multibyte_text_by_character() {
+        let (url, mut requests) = mock_server(|_| (201, "{}".to_string())).await;
+        let mut config = test_config();
+        config.twilio_api_url = url;
+        let sms = SmsChannel {
+            client: Client::new(),
+            config,
+        };
+        sms.send("Alert", &"é".repeat(200), Severity::Info)
+            .await
+            .unwrap();
+        let request = requests.recv().await.unwrap();
+        assert_eq!(request.matches("%C3%A9").count(), 115);
+    }
+
+    #[tokio::test]
+    async fn overridden_api_urls_are_used() {
+        let (url, mut requests) = mock_server(|_| (202, String::new())).await;
+        let mut config = test_config();
+        config.twilio_api_url = format!("{}/twilio/", url);
+        config.sendgrid_api_url = format!("{}/sendgrid", url);
+        let client = Client::new();
+        SmsChannel {
+            client: client.clone(),
+            config: config.clone(),
+        }
+        .send("Alert", "SUI sold", Severity::Info)
+        .await
+        .unwrap();
+        EmailChannel { client, config }
+            .send("Alert", "SUI sold", Severity::Info)
+            .await
+            .unwrap();
+        let sms = requests.recv().await.unwrap();
+        assert!(sms.starts_with("POST /twilio/Accounts/AC123/Messages.json "));
+        let email = requests.recv().await.unwrap();
+        assert!(email.starts_with("POST /sendgrid/mail/send "));
+        assert!(email.contains("Bearer SG.key"));
+    }
+
+    #[tokio::test]
+    async fn send_test_reports_each_channel() {
+        let (twilio, mut sms_requests) = mock_server(|_| (201, "{}".to_string())).await;
+        let (sendgrid, _) =
+            mock_server(|_| (401, r#"{"errors":[{"message":"bad key"}]}"#.to_string())).await;
+        let mut config = test_config();
+        config.sms_enabled = true;
+        config.email_enabled = true;
+        config.twilio_api_url = twilio;
+        config.sendgrid_api_url = sendgrid;
+        let notifier = Notifier::new(config);
+
+        assert_eq!(notifier.enabled_channels(), vec!["sms", "email"]);
+        notifier.send_test("sms").await.unwrap();
+        assert!(sms_requests
+            .recv()
+            .await
+            .unwrap()
+            .contains("Test+notification"));
+        let error = notifier.send_test("email").await.unwrap_err().to_string();
+        assert!(
+            error.contains("401") && error.contains("bad key"),
+            "{}",
+            error
+        );
+        assert!(notifier.send_test("pager").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn trades_notify_below_the_change_thresholds() {
+        let portfolio = Portfolio::from_csv(
+            toml::from_str(
+                "check_interval_secs = 60\nmax_allocation = 0.6\nstop_loss_percentage = 0.2",
+            )
+            .unwrap(),
+            "SUI,10,1.5,1.2\n",
+        )
+        .unwrap();
+        let prices = HashMap::from([("SUI".to_string(), 2.0)]);
+        let (notifier, sent) = recording_notifier(test_config());
+        // A 1% move is well under the 10% portfolio threshold
+        notifier
+            .notify_major_change(&portfolio, 20.0, 20.2, &prices, &prices)
+            .await
+            .unwrap();
+        assert!(sent.lock().unwrap().is_empty());
+        notifier
+            .notify_trade(Severity::Info, "Sold 0.1 SUI for $0.20")
+            .await
+            .unwrap();
+        assert_eq!(
+            *sent.lock().unwrap(),
+            vec!["sms: Trade Executed", "email: Trade Executed"]
+        );
+
+        let mut config = test_config();
+        config.always_notify_trades = false;
+        let (notifier, sent) = recording_notifier(config);
+        notifier
+            .notify_trade(Severity::Info, "Sold 0.1 SUI for $0.20")
+            .await
+            .unwrap();
+        assert!(sent.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn only_overweight_holdings_alert() {
+        let portfolio = Portfolio::from_csv(
+            toml::from_str(
+                "check_interval_secs = 60\nmax_allocation = 0.6\nstop_loss_percentage = 0.2",
+            )
+            .unwrap(),
+            "SUI,10,1.5,1.2\nPHA,100,0.1,0.05\n",
+        )
+        .unwrap();
+        // SUI is $70 of $100, PHA $30
+        let prices = HashMap::from([("SUI".to_string(), 7.0), ("PHA".to_string(), 0.3)]);
+        let (notifier, sent) = recording_notifier(test_config());
+        for _ in 0..2 {
+            for (symbol, allocation) in portfolio.allocations(&prices) {
+                notifier
+                    .notify_overweight(&symbol, allocation, portfolio.config.max_allocation)
+                    .await
+                    .unwrap();
+            }
+        }
+        // Once per channel for SUI; the repeat falls inside the alert cooldown
+        assert_eq!(
+            *sent.lock().unwrap(),
+            vec!["sms: Allocation Alert", "email: Allocation Alert"]
+        );
+        assert!(notifier
+            .overweight_alerts
+            .lock()
+            .unwrap()
+            .contains_key("SUI"));
+        assert!(!notifier
+            .overweight_alerts
+            .lock()
+            .unwrap()
+            .contains_key("PHA"));
+    }
+}
@@ -1,19 +1,107 @@
-use crate::config::PortfolioConfig;
-use crate::database::Database;
+use crate::config::{HoldingConfig, PortfolioConfig, SentimentConfig};
+use crate::database::{ChangeBaseline, Database, PendingSell};
 use crate::errors::PortfolioError;
-use crate::exchange::Exchange;
-use crate::exchange::SentimentProvider;
-use crate::exchange::{BinanceExchange, LunarCrushProvider};
+use crate::exchange::{resolve_missing_sentiment, SentimentProvider};
+use crate::exchange::{to_decimal, to_f64, Exchange, OrderExecutor, OrderFill};
 use crate::logger::log_action;
-use crate::notification::Notifier;
-use std::collections::HashMap;
+use crate::notification::{Notifier, Severity};
+use crate::server::{publish, Event};
+use crate::stats::record_sell;
+use chrono::{DateTime, Utc};
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, sqlx::FromRow)]
 pub struct Holding {
     pub symbol: String,
     pub quantity: f64,
     pub purchase_price: f64,
     pub stop_loss: f64,
+    #[serde(skip_serializing_if = "is_enabled")]
+    pub stop_loss_enabled: bool, // false for HODL positions, which are priced and shown but never auto-sold
+}
+
+// Left out of holdings_toml when set, as config.toml defaults it to true
+fn is_enabled(enabled: &bool) -> bool {
+    *enabled
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortfolioSnapshot {
+    pub timestamp: DateTime<Utc>,
+    pub total_value: f64,
+    pub cash: f64,
+    pub holdings: Vec<HoldingSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HoldingSnapshot {
+    pub symbol: String,
+    pub quantity: f64,
+    pub price: f64,
+    pub value: f64,
+    pub sentiment: Option<f64>,
+}
+
+// A holding whose tracked quantity differs from the exchange balance
+#[derive(Debug)]
+pub struct Discrepancy {
+    pub symbol: String,
+    pub configured: f64,
+    pub actual: f64,
+}
+
+impl Discrepancy {
+    pub fn drift(&self) -> f64 {
+        self.actual - self.configured
+    }
+}
+
+// Differences below this are exchange rounding, not drift
+const RECONCILE_EPSILON: f64 = 1e-8;
+
+// Trigger tags stored in the trades `reason` column; `report` breaks realized P&L down by them
+pub const TRIGGER_STOP_LOSS: &str = "stop-loss";
+pub const TRIGGER_SENTIMENT: &str = "sentiment";
+pub const TRIGGER_MANUAL: &str = "manual";
+
+// Where a trade goes: the price source, the live order executor (None simulates the fill at the
+// quoted price), and the database and notifier that record and report it
+#[derive(Clone, Copy)]
+pub struct TradeContext<'a> {
+    pub exchange: &'a dyn Exchange,
+    pub orders: Option<&'a dyn OrderExecutor>,
+    pub db: &'a Database,
+    pub notifier: &'a Notifier,
+}
+
+// A holding's price for one cycle, as the portfolio screen fetched it
+#[derive(Debug, Clone, Copy)]
+pub struct Quote {
+    pub price: Decimal,
+    pub live: bool, // False for a last known price carried over a failed fetch; it never triggers a trade
+}
+
+// A sell check_portfolio decided on, run once every holding has been priced
+struct TriggeredSell {
+    symbol: String,
+    quantity: f64, // The whole holding, or no more than an approved sell's quantity
+    price: f64,
+    sentiment: Option<f64>,
+    trigger: &'static str,
+    reason: String,
+    pending: Option<PendingSell>, // The symbol's approval queue entry, if it has one
+}
+
+// `trigger` and `reason` as for sell_holding
+pub struct BuyRequest<'a> {
+    pub symbol: &'a str,
+    pub amount_usd: f64, // Spent including the fee; clamped to investable_cash
+    pub trigger: &'a str,
+    pub reason: &'a str,
 }
 
 #[derive(Debug)]
@@ -23,8 +111,27 @@ pub struct Portfolio {
     pub config: PortfolioConfig,
 }
 
+impl From<&HoldingConfig> for Holding {
+    fn from(config: &HoldingConfig) -> Self {
+        Holding {
+            symbol: config.symbol.to_uppercase(),
+            quantity: config.quantity,
+            purchase_price: config.purchase_price,
+            stop_loss: config.stop_loss,
+            stop_loss_enabled: config.stop_loss_enabled,
+        }
+    }
+}
+
 impl Portfolio {
     pub fn new(config: PortfolioConfig) -> Self {
+        if !config.holdings.is_empty() {
+            return Portfolio {
+                holdings: config.holdings.iter().map(Holding::from).collect(),
+                cash: 0.0,
+                config,
+            };
+        }
         Portfolio {
             holdings: vec![
                 Holding {
@@ -32,18 +139,21 @@ impl Portfolio {
                     quantity: 250.0,
                     purchase_price: 0.20,
                     stop_loss: 0.16,
+                    stop_loss_enabled: true,
                 },
                 Holding {
                     symbol: "SUI".to_string(),
                     quantity: 10.0,
                     purchase_price: 3.00,
                     stop_loss: 2.40,
+                    stop_loss_enabled: true,
                 },
                 Holding {
                     symbol: "DUSK".to_string(),
                     quantity: 80.0,
                     purchase_price: 0.25,
                     stop_loss: 0.20,
+                    stop_loss_enabled: true,
                 },
             ],
             cash: 0.0,
@@ -51,66 +161,339 @@ impl Portfolio {
         }
     }
 
+    // portfolio.state_source = "database" resumes from Postgres, seeding it from config on first run
+    pub async fn load(config: PortfolioConfig, db: &Database) -> Result<Self, PortfolioError> {
+        match config.state_source.as_str() {
+            "config" => Ok(Portfolio::new(config)),
+            "database" => match db.load_portfolio().await? {
+                Some((holdings, cash)) => Ok(Portfolio {
+                    holdings,
+                    cash,
+                    config,
+                }),
+                None => {
+                    let portfolio = Portfolio::new(config);
+                    db.save_portfolio(&portfolio.holdings, portfolio.cash)
+                        .await?;
+                    let _ = log_action("Seeded database portfolio state from config", None);
+                    Ok(portfolio)
+                }
+            },
+            other => Err(PortfolioError::ConfigError(format!(
+                "Unknown portfolio.state_source '{}' (expected \"config\" or \"database\")",
+                other
+            ))),
+        }
+    }
+
+    // Holdings as they stood before any logged trade, the starting point for the cost basis;
+    // the loaded state already includes every trade, so seeding from it counts buys twice
+    pub fn opening_holdings(config: &PortfolioConfig) -> Vec<Holding> {
+        Portfolio::new(config.clone()).holdings
+    }
+
+    // Writes holdings and cash back after a trade; a no-op for config-sourced portfolios
+    pub async fn persist(&self, db: &Database) -> Result<(), PortfolioError> {
+        if self.config.state_source != "database" {
+            return Ok(());
+        }
+        db.save_portfolio(&self.holdings, self.cash).await
+    }
+
+    // Builds holdings from `symbol,quantity,purchase_price,stop_loss` rows; a header row is optional
+    pub fn from_csv(config: PortfolioConfig, csv: &str) -> Result<Self, PortfolioError> {
+        let mut holdings = Vec::new();
+        for (i, line) in csv.lines().enumerate() {
+            let line_no = i + 1;
+            let line = line.trim();
+            if line.is_empty() || (line_no == 1 && line.to_lowercase().starts_with("symbol")) {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            if fields.len() != 4 {
+                return Err(PortfolioError::ConfigError(format!(
+                    "Line {}: expected 4 fields (symbol,quantity,purchase_price,stop_loss), found {}",
+                    line_no,
+                    fields.len()
+                )));
+            }
+            if fields[0].is_empty() {
+                return Err(PortfolioError::ConfigError(format!(
+                    "Line {}: symbol is empty",
+                    line_no
+                )));
+            }
+            let number = |name: &str, value: &str| -> Result<f64, PortfolioError> {
+                match value.parse::<f64>() {
+                    Ok(number) if number.is_finite() && number >= 0.0 => Ok(number),
+                    _ => Err(PortfolioError::ConfigError(format!(
+                        "Line {}: invalid {} '{}'",
+                        line_no, name, value
+                    ))),
+                }
+            };
+            holdings.push(Holding {
+                symbol: fields[0].to_uppercase(),
+                quantity: number("quantity", fields[1])?,
+                purchase_price: number("purchase_price", fields[2])?,
+                stop_loss: number("stop_loss", fields[3])?,
+                stop_loss_enabled: true,
+            });
+        }
+        if holdings.is_empty() {
+            return Err(PortfolioError::ConfigError(
+                "CSV contains no holdings".to_string(),
+            ));
+        }
+        Ok(Portfolio {
+            holdings,
+            cash: 0.0,
+            config,
+        })
+    }
+
+    // Holdings whose quantity differs from the exchange balances (missing assets count as 0)
+    pub fn reconcile(&self, balances: &HashMap<String, f64>) -> Vec<Discrepancy> {
+        self.holdings
+            .iter()
+            .filter_map(|holding| {
+                let actual = balances.get(&holding.symbol).copied().unwrap_or(0.0);
+                ((actual - holding.quantity).abs() > RECONCILE_EPSILON).then(|| Discrepancy {
+                    symbol: holding.symbol.clone(),
+                    configured: holding.quantity,
+                    actual,
+                })
+            })
+            .collect()
+    }
+
+    // Adopts the exchange quantities for tracked holdings; untracked assets are left alone, and
+    // holdings the account no longer has are dropped rather than kept at quantity 0
+    pub fn sync_quantities(&mut self, balances: &HashMap<String, f64>) {
+        self.holdings
+            .retain_mut(|holding| match balances.get(&holding.symbol).copied() {
+                Some(quantity) if quantity > 0.0 => {
+                    holding.quantity = quantity;
+                    true
+                }
+                _ => false,
+            });
+    }
+
+    // Equivalent `[[portfolio.holdings]]` blocks for config.toml; serialized, so symbols with
+    // quotes or backslashes stay valid TOML
+    pub fn holdings_toml(&self) -> Result<String, PortfolioError> {
+        #[derive(Serialize)]
+        struct Section<'a> {
+            portfolio: Holdings<'a>,
+        }
+
+        #[derive(Serialize)]
+        struct Holdings<'a> {
+            holdings: &'a [Holding],
+        }
+
+        toml::to_string(&Section {
+            portfolio: Holdings {
+                holdings: &self.holdings,
+            },
+        })
+        .map_err(|e| PortfolioError::ConfigError(format!("Failed to write holdings: {}", e)))
+    }
+
+    // Acts on the quotes the portfolio screen fetched this cycle and values the portfolio at them,
+    // so the total it notifies is the one the screen shows; `baseline` is the last cycle's
     pub async fn check_portfolio(
         &mut self,
-        exchange: &BinanceExchange,
-        sentiment_provider: &LunarCrushProvider,
-        db: &Database,
-        notifier: &Notifier,
-        negative_threshold: f64, // Add parameter
-        previous_value: f64,
-        previous_prices: &HashMap<String, f64>,
-        previous_sentiments: &HashMap<String, f64>,
+        ctx: &TradeContext<'_>,
+        quotes: &HashMap<String, Quote>,
+        sentiment_provider: &dyn SentimentProvider,
+        sentiment_config: &SentimentConfig,
+        baseline: &ChangeBaseline,
     ) -> Result<f64, PortfolioError> {
+        let TradeContext { db, notifier, .. } = *ctx;
         let mut current_prices = HashMap::new();
         let mut current_sentiments = HashMap::new();
 
         let mut to_sell = Vec::new();
         for holding in self.holdings.iter() {
-            let current_price = exchange.fetch_price(&holding.symbol).await?;
-            let sentiment = sentiment_provider.fetch_sentiment(&holding.symbol).await?;
+            let Some(&Quote {
+                price: exact_price,
+                live,
+            }) = quotes.get(&holding.symbol)
+            else {
+                return Err(PortfolioError::ExchangeError(format!(
+                    "No price for {} this cycle",
+                    holding.symbol
+                )));
+            };
+            let current_price = to_f64(exact_price);
+            // Missing sentiment follows sentiment.on_missing, but a stand-in score never triggers a sell
+            let (sentiment, fetched) =
+                match sentiment_provider.fetch_sentiment(&holding.symbol).await {
+                    Ok(sentiment) => (Some(sentiment), true),
+                    Err(e) => (
+                        resolve_missing_sentiment(sentiment_config, &holding.symbol, e)?,
+                        false,
+                    ),
+                };
             current_prices.insert(holding.symbol.clone(), current_price);
-            current_sentiments.insert(holding.symbol.clone(), sentiment);
-
-            // Check stop-loss
-            if current_price < holding.stop_loss || sentiment < negative_threshold {
-                to_sell.push((
-                    holding.symbol.clone(),
-                    holding.quantity,
-                    current_price,
+            if let Some(sentiment) = sentiment.filter(|_| fetched) {
+                current_sentiments.insert(holding.symbol.clone(), sentiment);
+            }
+            // HODL holdings are priced and shown like the rest, but no trigger applies to them, nor
+            // to a carried-over price; the stale-data alert reports that gap
+            if !self.config.trading_enabled || !holding.stop_loss_enabled || !live {
+                continue;
+            }
+
+            // Check stop-loss; sentiment only counts when the provider is backed by real data
+            let (_, negative_threshold) = sentiment_config.thresholds_for(&holding.symbol);
+            let sentiment_sell = fetched
+                && sentiment_provider.is_enabled()
+                && sentiment.is_some_and(|sentiment| sentiment < negative_threshold);
+            // An approved sell goes through on this check even if its trigger has since cleared,
+            // but never for more than the quantity that was approved
+            let pending = if self.config.manual_approval_above_usd > 0.0 {
+                self.pending_sell(&holding.symbol, db).await
+            } else {
+                None
+            };
+            let approved = pending.as_ref().is_some_and(|pending| pending.approved);
+            let quantity = match &pending {
+                Some(pending) if approved => holding.quantity.min(pending.quantity),
+                _ => holding.quantity,
+            };
+            let below_stop = exact_price < to_decimal(holding.stop_loss)?;
+            let stop_loss_sell = self
+                .stop_loss_confirmed(&holding.symbol, below_stop, db)
+                .await?;
+            if stop_loss_sell || sentiment_sell || approved {
+                let value = quantity * current_price;
+                if value < self.config.min_trade_value_usd {
+                    let _ = log_action(
+                        &format!(
+                            "{}: Skipped dust sell worth ${:.2} (minimum ${:.2})",
+                            holding.symbol, value, self.config.min_trade_value_usd
+                        ),
+                        None,
+                    );
+                    // Too small to ever sell, so a queued sell for it is dropped rather than left waiting
+                    if let Some(pending) = &pending {
+                        let _ = self.clear_pending(pending, db).await;
+                    }
+                    continue;
+                }
+                if self.config.sell_cooldown_secs > 0
+                    && db.in_sell_cooldown(&holding.symbol).await?
+                {
+                    let _ = log_action(
+                        &format!(
+                            "{}: Suppressed re-sell within the {}s sell cooldown",
+                            holding.symbol, self.config.sell_cooldown_secs
+                        ),
+                        None,
+                    );
+                    continue;
+                }
+                let (trigger, reason) = if stop_loss_sell {
+                    (
+                        TRIGGER_STOP_LOSS,
+                        format!("stop-loss ${:.2}", holding.stop_loss),
+                    )
+                } else if sentiment_sell {
+                    (
+                        TRIGGER_SENTIMENT,
+                        format!(
+                            "sentiment {} below {:.2}",
+                            format_sentiment(sentiment),
+                            negative_threshold
+                        ),
+                    )
+                } else {
+                    (TRIGGER_MANUAL, "manual approval".to_string())
+                };
+                to_sell.push(TriggeredSell {
+                    symbol: holding.symbol.clone(),
+                    quantity,
+                    price: current_price,
                     sentiment,
-                ));
+                    trigger,
+                    reason,
+                    pending,
+                });
             }
         }
 
-        for (symbol, quantity, current_price, sentiment) in to_sell {
-            let proceeds = self.sell_holding(&symbol, exchange, db, notifier).await?;
+        for sell in to_sell {
+            let TriggeredSell {
+                symbol,
+                quantity,
+                price: current_price,
+                sentiment,
+                trigger,
+                reason,
+                pending,
+            } = sell;
+            let approved = pending.as_ref().is_some_and(|pending| pending.approved);
+            if !approved && self.needs_approval(quantity * current_price) {
+                if pending.is_none() {
+                    self.queue_for_approval(&symbol, quantity, current_price, db, notifier)
+                        .await?;
+                }
+                continue;
+            }
+            // Cleared before selling, so a sell that went through can't be approved and run again;
+            // a queued sell whose value has since dropped under the threshold is cleared the same way
+            if let Some(pending) = &pending {
+                if !self.clear_pending(pending, db).await {
+                    continue;
+                }
+            }
+            let proceeds = self
+                .sell_holding(&symbol, quantity, trigger, &reason, ctx)
+                .await?;
+            if self.config.sell_cooldown_secs > 0 {
+                db.start_sell_cooldown(&symbol, self.config.sell_cooldown_secs)
+                    .await?;
+            }
+            if self.config.stop_loss_grace_secs > 0 {
+                db.clear_stop_loss_breach(&symbol).await?;
+            }
+            let sentiment = format_sentiment(sentiment);
             let _ = log_action(
                 &format!(
-                    "Sold {} {} at ${:.2} (sentiment: {:.2}) for ${:.2}",
-                    quantity, symbol, current_price, sentiment, proceeds
+                    "{}: Sell triggered at ${:.2} (sentiment: {}) settled for ${:.2}",
+                    symbol, current_price, sentiment, proceeds
                 ),
                 None,
             );
-            notifier.notify_significant_action(&format!(
-                "{}: Negative sentiment triggered at ${:.2} (sentiment: {:.2}), sold {} tokens for ${:.2}.",
-                symbol, current_price, sentiment, quantity, proceeds
-            )).await?;
         }
 
-        let total_value = self.get_value(exchange).await?;
+        // Valued at this cycle's prices so the notified and displayed totals match
+        let total_value = self.value_at(&current_prices);
         notifier
             .notify_major_change(
                 self,
-                previous_value,
+                baseline.value,
                 total_value,
-                previous_prices,
+                &baseline.prices,
                 &current_prices,
             )
             .await?;
 
-        for (symbol, sentiment) in &current_sentiments {
-            if let Some(prev_sentiment) = previous_sentiments.get(symbol) {
+        for (symbol, allocation) in self.allocations(&current_prices) {
+            notifier
+                .notify_overweight(&symbol, allocation, self.config.max_allocation)
+                .await?;
+        }
+
+        // Sorted so alerts and their log lines come out in the same order every cycle
+        let mut symbols: Vec<&String> = current_sentiments.keys().collect();
+        symbols.sort();
+        for symbol in symbols {
+            let sentiment = &current_sentiments[symbol];
+            if let Some(prev_sentiment) = baseline.sentiments.get(symbol) {
                 notifier
                     .notify_sentiment_change(symbol, *prev_sentiment, *sentiment)
                     .await?;
@@ -120,41 +503,284 @@ impl Portfolio {
         Ok(total_value)
     }
 
-    pub async fn get_value(&self, exchange: &BinanceExchange) -> Result<f64, PortfolioError> {
-        let mut total_value = self.cash;
-        for holding in &self.holdings {
-            let current_price = exchange.fetch_price(&holding.symbol).await?;
-            total_value += holding.quantity * current_price;
+    // With portfolio.stop_loss_grace_secs set, a breach only counts once it has lasted that long,
+    // so a momentary wick doesn't sell; recovering above the stop resets the clock
+    async fn stop_loss_confirmed(
+        &self,
+        symbol: &str,
+        below_stop: bool,
+        db: &Database,
+    ) -> Result<bool, PortfolioError> {
+        let grace = self.config.stop_loss_grace_secs;
+        if grace == 0 {
+            return Ok(below_stop);
         }
-        Ok(total_value)
+        if !below_stop {
+            db.clear_stop_loss_breach(symbol).await?;
+            return Ok(false);
+        }
+        // An untracked breach (Redis down) sells, so a cache outage never disables the stop-loss
+        let Some(since) = db.stop_loss_breached_since(symbol).await? else {
+            return Ok(true);
+        };
+        let breached_for = (Utc::now() - since).num_seconds();
+        if breached_for >= grace as i64 {
+            return Ok(true);
+        }
+        let _ = log_action(
+            &format!(
+                "{}: Below stop-loss for {}s, waiting out the {}s grace period",
+                symbol, breached_for, grace
+            ),
+            None,
+        );
+        Ok(false)
     }
 
-    pub async fn sell_holding(
-        &mut self,
+    // Sells worth more than portfolio.manual_approval_above_usd wait for `approve <id>`; 0 disables the gate
+    fn needs_approval(&self, value: f64) -> bool {
+        self.config.manual_approval_above_usd > 0.0 && value > self.config.manual_approval_above_usd
+    }
+
+    // The symbol's entry in the approval queue; a Redis failure reads as none, and any sell above
+    // the threshold then fails to queue and is held, so the gate never fails open
+    async fn pending_sell(&self, symbol: &str, db: &Database) -> Option<PendingSell> {
+        match db.pending_sell_for(symbol).await {
+            Ok(pending) => pending,
+            Err(e) => {
+                let _ = log_action(
+                    &format!("{}: Could not read the approval queue: {}", symbol, e),
+                    None,
+                );
+                None
+            }
+        }
+    }
+
+    // False, holding the sell, when the entry can't be removed
+    async fn clear_pending(&self, pending: &PendingSell, db: &Database) -> bool {
+        match db.clear_pending_sell(pending).await {
+            Ok(()) => true,
+            Err(e) => {
+                let _ = log_action(
+                    &format!(
+                        "{}: Holding sell, could not clear pending sell #{}: {}",
+                        pending.symbol, pending.id, e
+                    ),
+                    None,
+                );
+                false
+            }
+        }
+    }
+
+    // Queues the sell for `approve <id>` and notifies once; the sell itself is held
+    async fn queue_for_approval(
+        &self,
         symbol: &str,
-        exchange: &BinanceExchange,
+        quantity: f64,
+        price: f64,
         db: &Database,
         notifier: &Notifier,
+    ) -> Result<(), PortfolioError> {
+        match db.queue_pending_sell(symbol, quantity, price).await {
+            Ok(pending) => {
+                let _ = log_action(
+                    &format!(
+                        "{}: Sell #{} worth ${:.2} queued for approval",
+                        symbol,
+                        pending.id,
+                        pending.value()
+                    ),
+                    None,
+                );
+                notifier
+                    .notify_significant_action(
+                        Severity::Critical,
+                        &format!(
+                            "{}: Sell of {} tokens at ${:.2} (${:.2}) needs approval. Run `approve {}` to execute it.",
+                            symbol,
+                            quantity,
+                            price,
+                            pending.value(),
+                            pending.id
+                        ),
+                    )
+                    .await?;
+            }
+            Err(e) => {
+                let _ = log_action(
+                    &format!(
+                        "{}: Holding sell, could not queue it for approval: {}",
+                        symbol, e
+                    ),
+                    None,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    pub fn snapshot(
+        &self,
+        total_value: f64,
+        prices: &HashMap<String, f64>,
+        sentiments: &HashMap<String, f64>,
+    ) -> PortfolioSnapshot {
+        let holdings = self
+            .holdings
+            .iter()
+            .map(|holding| {
+                let price = prices.get(&holding.symbol).copied().unwrap_or(0.0);
+                HoldingSnapshot {
+                    symbol: holding.symbol.clone(),
+                    quantity: holding.quantity,
+                    price,
+                    value: holding.quantity * price,
+                    sentiment: sentiments.get(&holding.symbol).copied(),
+                }
+            })
+            .collect();
+        PortfolioSnapshot {
+            timestamp: Utc::now(),
+            total_value,
+            cash: self.cash,
+            holdings,
+        }
+    }
+
+    // Write to a temp file and rename so readers never see a partially written snapshot
+    pub fn write_snapshot(
+        &self,
+        path: &str,
+        total_value: f64,
+        prices: &HashMap<String, f64>,
+        sentiments: &HashMap<String, f64>,
+    ) -> Result<(), PortfolioError> {
+        let snapshot = self.snapshot(total_value, prices, sentiments);
+        let json = serde_json::to_string_pretty(&snapshot)
+            .map_err(|e| PortfolioError::IoError(e.to_string()))?;
+        let tmp_path = format!("{}.tmp", path);
+        fs::write(&tmp_path, json).map_err(|e| PortfolioError::IoError(e.to_string()))?;
+        fs::rename(&tmp_path, path).map_err(|e| PortfolioError::IoError(e.to_string()))?;
+        Ok(())
+    }
+
+    // Cash plus holdings at the given prices, rounded to cents; holdings without a price count as zero
+    pub fn value_at(&self, prices: &HashMap<String, f64>) -> f64 {
+        let total = self.holdings.iter().fold(self.cash, |total, holding| {
+            total + holding.quantity * prices.get(&holding.symbol).copied().unwrap_or(0.0)
+        });
+        round_money_f64(total, &self.config.rounding_mode)
+    }
+
+    // Each holding's share (0..1) of value_at, keyed by symbol; empty when the portfolio is worth nothing
+    pub fn allocations(&self, prices: &HashMap<String, f64>) -> BTreeMap<String, f64> {
+        let total = self.value_at(prices);
+        if total <= 0.0 {
+            return BTreeMap::new();
+        }
+        self.holdings
+            .iter()
+            .map(|holding| {
+                let value = holding.quantity * prices.get(&holding.symbol).copied().unwrap_or(0.0);
+                (holding.symbol.clone(), value / total)
+            })
+            .collect()
+    }
+
+    // Cash a buy may spend: everything above portfolio.min_cash_reserve_usd
+    pub fn investable_cash(&self) -> f64 {
+        (self.cash - self.config.min_cash_reserve_usd).max(0.0)
+    }
+
+    // True once portfolio.max_holdings distinct symbols are held; never with no cap
+    pub fn at_max_holdings(&self) -> bool {
+        self.config.max_holdings > 0 && self.holdings.len() >= self.config.max_holdings
+    }
+
+    // Sells at most `max_quantity` of the holding; whatever isn't sold stays held. `trigger` is the
+    // tag stored with the trade (one of the TRIGGER_* values); `reason` describes it (e.g.,
+    // "stop-loss $0.16") in the trade notification
+    async fn sell_holding(
+        &mut self,
+        symbol: &str,
+        max_quantity: f64,
+        trigger: &str,
+        reason: &str,
+        ctx: &TradeContext<'_>,
     ) -> Result<f64, PortfolioError> {
+        let TradeContext {
+            exchange,
+            orders,
+            db,
+            notifier,
+        } = *ctx;
         if let Some(index) = self.holdings.iter().position(|h| h.symbol == symbol) {
-            let holding = self.holdings.remove(index);
-            let price = exchange.fetch_price(&holding.symbol).await?;
-            let proceeds = holding.quantity * price;
-            self.cash += proceeds;
-            db.log_trade(&holding.symbol, holding.quantity, price, "sell")
+            let held = to_decimal(self.holdings[index].quantity)?;
+            let quantity = self.holdings[index].quantity.min(max_quantity);
+            // Converted before any order goes out, so a bad value can't strand a filled sell
+            let exact_quantity = to_decimal(quantity)?;
+            let fee_pct = to_decimal(exchange.taker_fee_pct())?;
+            // Live orders settle on what filled; LOT_SIZE rounding or a partial fill leaves the rest held
+            let (sold, gross, price) = match orders {
+                Some(executor) => {
+                    let fill = executor.place_market_sell(symbol, quantity).await?;
+                    settled_fill(&fill, "Sell", symbol)?
+                }
+                None => {
+                    let price = exchange.fetch_price_exact(symbol).await?;
+                    (exact_quantity, exact_quantity * price, price)
+                }
+            };
+            // Proceeds are worked out in decimal so large quantities at tiny prices don't drift
+            let mode = &self.config.rounding_mode;
+            let price = to_f64(price);
+            let gross = round_money(gross, mode);
+            let fee = round_money(gross * fee_pct / Decimal::ONE_HUNDRED, mode);
+            let proceeds = to_f64(gross - fee);
+            let fee = to_f64(fee);
+            let remaining = to_f64((held - sold).max(Decimal::ZERO));
+            let sold = to_f64(sold);
+            if remaining > 0.0 {
+                self.holdings[index].quantity = remaining;
+            } else {
+                self.holdings.remove(index);
+            }
+            self.cash = round_money_f64(self.cash + proceeds, mode);
+            db.log_trade(symbol, sold, price, "sell", fee, trigger)
                 .await?;
+            self.persist(db).await?;
+            record_sell();
+            publish(Event::Trade {
+                symbol: symbol.to_string(),
+                action: "sell".to_string(),
+                quantity: sold,
+                price,
+                value: proceeds,
+                reason: reason.to_string(),
+                timestamp: Utc::now(),
+            });
+            let left = if remaining > 0.0 {
+                format!("; {} left", remaining)
+            } else {
+                String::new()
+            };
             let _ = log_action(
                 &format!(
-                    "Sold {} {} at ${:.2} for ${:.2}",
-                    holding.quantity, holding.symbol, price, proceeds
+                    "Sold {} {} at ${:.2} for ${:.2} (fee ${:.2}){}",
+                    sold, symbol, price, proceeds, fee, left
                 ),
                 None,
             );
             notifier
-                .notify_significant_action(&format!(
-                    "Sold {} {} at ${:.2} for ${:.2}",
-                    holding.quantity, holding.symbol, price, proceeds
-                ))
+                .notify_trade(
+                    Severity::Critical,
+                    &format!(
+                        "{}: Sold {} tokens at ${:.2} for ${:.2} (fee ${:.2}){}, triggered by {}.",
+                        symbol, sold, price, proceeds, fee, left, reason
+                    ),
+                )
                 .await?;
             Ok(proceeds)
         } else {
@@ -164,4 +790,1022 @@ impl Portfolio {
             )))
         }
     }
+
+    // Spends request.amount_usd, fee included, on request.symbol at the quoted price; returns the cost.
+    // Nothing places buys yet; this is the one path that applies fees and max_holdings when it does
+    #[allow(dead_code)]
+    pub async fn buy_holding(
+        &mut self,
+        request: &BuyRequest<'_>,
+        ctx: &TradeContext<'_>,
+    ) -> Result<f64, PortfolioError> {
+        let BuyRequest {
+            symbol,
+            amount_usd,
+            trigger,
+            reason,
+        } = *request;
+        let TradeContext {
+            exchange,
+            orders,
+            db,
+            notifier,
+        } = *ctx;
+        let new_symbol = !self.holdings.iter().any(|h| h.symbol == symbol);
+        if new_symbol && self.at_max_holdings() {
+            return Err(PortfolioError::ExchangeError(format!(
+                "Cannot buy {}: already holding {} of {} allowed symbols (portfolio.max_holdings)",
+                symbol,
+                self.holdings.len(),
+                self.config.max_holdings
+            )));
+        }
+        let investable = self.investable_cash();
+        if amount_usd > investable {
+            return Err(PortfolioError::ExchangeError(format!(
+                "Insufficient cash to buy ${:.2} of {}: have ${:.2} above the ${:.2} reserve",
+                amount_usd, symbol, investable, self.config.min_cash_reserve_usd
+            )));
+        }
+        // Sized so quantity × price plus the fee on it comes to the amount
+        let quoted = exchange.fetch_price_exact(symbol).await?;
+        if quoted <= Decimal::ZERO {
+            return Err(PortfolioError::ExchangeError(format!(
+                "Cannot buy {} at a quoted price of ${}",
+                symbol, quoted
+            )));
+        }
+        let fee_pct = to_decimal(exchange.taker_fee_pct())?;
+        let fee_factor = Decimal::ONE + fee_pct / Decimal::ONE_HUNDRED;
+        let exact_quantity = to_decimal(amount_usd)? / (quoted * fee_factor);
+        let quantity = to_f64(exact_quantity);
+        // As for sells, a live buy adds only what filled
+        let (bought, gross, price) = match orders {
+            Some(executor) => {
+                let fill = executor.place_market_buy(symbol, quantity).await?;
+                settled_fill(&fill, "Buy", symbol)?
+            }
+            None => (exact_quantity, exact_quantity * quoted, quoted),
+        };
+        let mode = &self.config.rounding_mode;
+        let price = to_f64(price);
+        let gross = round_money(gross, mode);
+        let fee = round_money(gross * fee_pct / Decimal::ONE_HUNDRED, mode);
+        let (cost, fee) = (to_f64(gross + fee), to_f64(fee));
+        let quantity = to_f64(bought);
+        self.cash = round_money_f64(self.cash - cost, mode);
+
+        // Fees are folded into the average purchase price
+        if let Some(holding) = self.holdings.iter_mut().find(|h| h.symbol == symbol) {
+            let total_quantity = holding.quantity + quantity;
+            holding.purchase_price =
+                (holding.quantity * holding.purchase_price + cost) / total_quantity;
+            holding.quantity = total_quantity;
+        } else {
+            self.holdings.push(Holding {
+                symbol: symbol.to_string(),
+                quantity,
+                purchase_price: cost / quantity,
+                stop_loss: price * (1.0 - self.config.stop_loss_percentage),
+                stop_loss_enabled: true,
+            });
+        }
+
+        db.log_trade(symbol, quantity, price, "buy", fee, trigger)
+            .await?;
+        self.persist(db).await?;
+        publish(Event::Trade {
+            symbol: symbol.to_string(),
+            action: "buy".to_string(),
+            quantity,
+            price,
+            value: cost,
+            reason: reason.to_string(),
+            timestamp: Utc::now(),
+        });
+        let _ = log_action(
+            &format!(
+                "Bought {} {} at ${:.2} for ${:.2} (fee ${:.2})",
+                quantity, symbol, price, cost, fee
+            ),
+            None,
+        );
+        notifier
+            .notify_trade(
+                Severity::Info,
+                &format!(
+                    "{}: Bought {} tokens at ${:.2} for ${:.2} (fee ${:.2}), triggered by {}.",
+                    symbol, quantity, price, cost, fee, reason
+                ),
+            )
+            .await?;
+        Ok(cost)
+    }
+}
+
+// Exact quantity, quote amount and average price of a live order; filling nothing is an error, not a $0 trade
+fn settled_fill(
+    fill: &OrderFill,
+    side: &str,
+    symbol: &str,
+) -> Result<(Decimal, Decimal, Decimal), PortfolioError> {
+    let quantity = to_decimal(fill.executed_quantity)?;
+    if quantity <= Decimal::ZERO {
+        return Err(PortfolioError::ExchangeError(format!(
+            "{} order {} for {} filled nothing",
+            side, fill.order_id, symbol
+        )));
+    }
+    let quote = to_decimal(fill.quote_quantity)?;
+    Ok((quantity, quote, quote / quantity))
+}
+
+// Two-decimal score, or "N/A" when the provider had no data for the symbol
+pub fn format_sentiment(sentiment: Option<f64>) -> String {
+    sentiment
+        .map(|sentiment| format!("{:.2}", sentiment))
+        .unwrap_or_else(|| "N/A".to_string())
+}
+
+// Fees, proceeds, costs and realized P&L are kept to whole cents
+const MONEY_DECIMALS: u32 = 2;
+
+// portfolio.rounding_mode: "half_up", "half_even" (the default) or "down" (toward zero)
+pub fn rounding_strategy(mode: &str) -> RoundingStrategy {
+    match mode {
+        "half_up" => RoundingStrategy::MidpointAwayFromZero,
+        "down" => RoundingStrategy::ToZero,
+        _ => RoundingStrategy::MidpointNearestEven,
+    }
+}
+
+pub fn round_money(amount: Decimal, mode: &str) -> Decimal {
+    amount.round_dp_with_strategy(MONEY_DECIMALS, rounding_strategy(mode))
+}
+
+// For amounts still tracked as f64; NaN and infinity pass through unrounded
+pub fn round_money_f64(amount: f64, mode: &str) -> f64 {
+    Decimal::from_f64(amount)
+        .map(|amount| to_f64(round_money(amount, mode)))
+        .unwrap_or(amount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::NotificationConfig;
+    use crate::database::testing::mock_redis;
+    use crate::exchange::{create_sentiment_provider, DetailedSentiment, NullSentimentProvider};
+    use crate::http::testing::mock_server;
+    use async_trait::async_trait;
+
+    fn test_config() -> PortfolioConfig {
+        toml::from_str("check_interval_secs = 60\nmax_allocation = 0.6\nstop_loss_percentage = 0.2")
+            .unwrap()
+    }
+
+    fn sentiment_config() -> SentimentConfig {
+        toml::from_str(
+            "enabled = false\napi_key = \"\"\ncache_ttl_secs = 60\npositive_threshold = 0.7\n\
+             negative_threshold = 0.3",
+        )
+        .unwrap()
+    }
+
+    // Every channel off, so alerts are only logged
+    fn quiet_notifier() -> Notifier {
+        Notifier::new(notification_config())
+    }
+
+    fn notification_config() -> NotificationConfig {
+        toml::from_str(
+            r#"
+            sms_enabled = false
+            email_enabled = false
+            twilio_account_sid = ""
+            twilio_auth_token = ""
+            twilio_phone_number = ""
+            recipient_phone_number = ""
+            sendgrid_api_key = ""
+            sender_email = ""
+            recipient_email = ""
+
+            [notification_thresholds]
+            portfolio_value_change_percent = 10.0
+            holding_value_change_percent = 15.0
+            sentiment_change = 0.2
+            "#,
+        )
+        .unwrap()
+    }
+
+    // Every holding quoted at `price`
+    fn quotes_at(portfolio: &Portfolio, price: f64, live: bool) -> HashMap<String, Quote> {
+        portfolio
+            .holdings
+            .iter()
+            .map(|holding| {
+                let price = to_decimal(price).unwrap();
+                (holding.symbol.clone(), Quote { price, live })
+            })
+            .collect()
+    }
+
+    fn trade_log_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("crypto-portfolio-{}.jsonl", name));
+        let _ = fs::remove_file(&path);
+        path.to_string_lossy().into_owned()
+    }
+
+    // Quotes `price` for every symbol, or fails every fetch when it is None
+    struct StubExchange {
+        price: Option<f64>,
+    }
+
+    #[async_trait]
+    impl Exchange for StubExchange {
+        async fn fetch_price(&self, _symbol: &str) -> Result<f64, PortfolioError> {
+            self.price
+                .ok_or_else(|| PortfolioError::ApiError("exchange is down".to_string()))
+        }
+
+        fn name(&self) -> &str {
+            "stub"
+        }
+    }
+
+    // $2 for everything, with a 0.1% taker fee
+    struct FeeExchange;
+
+    #[async_trait]
+    impl Exchange for FeeExchange {
+        async fn fetch_price(&self, _symbol: &str) -> Result<f64, PortfolioError> {
+            Ok(2.0)
+        }
+
+        fn name(&self) -> &str {
+            "stub"
+        }
+
+        fn taker_fee_pct(&self) -> f64 {
+            0.1
+        }
+    }
+
+    // Fills every order with `fill`, whatever quantity was asked for
+    struct StubOrders {
+        fill: OrderFill,
+    }
+
+    #[async_trait]
+    impl OrderExecutor for StubOrders {
+        async fn place_market_sell(
+            &self,
+            _symbol: &str,
+            _quantity: f64,
+        ) -> Result<OrderFill, PortfolioError> {
+            Ok(self.fill.clone())
+        }
+
+        async fn place_market_buy(
+            &self,
+            _symbol: &str,
+            _quantity: f64,
+        ) -> Result<OrderFill, PortfolioError> {
+            Ok(self.fill.clone())
+        }
+    }
+
+    fn stub_orders(executed_quantity: f64, quote_quantity: f64) -> StubOrders {
+        StubOrders {
+            fill: OrderFill {
+                order_id: 7,
+                executed_quantity,
+                quote_quantity,
+            },
+        }
+    }
+
+    #[test]
+    fn holdings_toml_round_trips_awkward_symbols() {
+        let csv =
+            "symbol,quantity,purchase_price,stop_loss\nSU\"I\\,10,1.5,1.2\nPHA,250,0.1,0.08\n";
+        let mut portfolio = Portfolio::from_csv(test_config(), csv).unwrap();
+        portfolio.holdings[1].stop_loss_enabled = false;
+
+        #[derive(Deserialize)]
+        struct Section {
+            portfolio: PortfolioConfig,
+        }
+        let block = format!(
+            "{}{}",
+            "[portfolio]\ncheck_interval_secs = 60\nmax_allocation = 0.6\nstop_loss_percentage = 0.2\n",
+            portfolio.holdings_toml().unwrap()
+        );
+        let parsed: Section = toml::from_str(&block).unwrap();
+        let holdings = parsed.portfolio.holdings;
+        assert_eq!(holdings[0].symbol, "SU\"I\\");
+        assert_eq!(holdings[0].quantity, 10.0);
+        assert!(holdings[0].stop_loss_enabled);
+        assert_eq!(holdings[1].symbol, "PHA");
+        assert!(!holdings[1].stop_loss_enabled);
+    }
+
+    #[test]
+    fn sync_drops_holdings_missing_from_the_account() {
+        let csv = "SUI,10,1.5,1.2\nPHA,250,0.1,0.08\nDUSK,40,0.2,0.15\n";
+        let mut portfolio = Portfolio::from_csv(test_config(), csv).unwrap();
+        let balances = HashMap::from([
+            ("SUI".to_string(), 12.5),
+            ("DUSK".to_string(), 0.0),
+            ("BNB".to_string(), 1.0),
+        ]);
+        portfolio.sync_quantities(&balances);
+        let synced: Vec<(&str, f64)> = portfolio
+            .holdings
+            .iter()
+            .map(|holding| (holding.symbol.as_str(), holding.quantity))
+            .collect();
+        assert_eq!(synced, vec![("SUI", 12.5)]);
+    }
+
+    // The same score for every symbol; `enabled` as SentimentProvider::is_enabled
+    struct StubSentiment {
+        score: f64,
+        enabled: bool,
+    }
+
+    #[async_trait]
+    impl SentimentProvider for StubSentiment {
+        async fn fetch_sentiment(&self, _symbol: &str) -> Result<f64, PortfolioError> {
+            Ok(self.score)
+        }
+
+        async fn fetch_detailed_sentiment(
+            &self,
+            _symbol: &str,
+        ) -> Result<DetailedSentiment, PortfolioError> {
+            Ok(DetailedSentiment {
+                current_value: self.score,
+                ..Default::default()
+            })
+        }
+
+        fn is_enabled(&self) -> bool {
+            self.enabled
+        }
+
+        fn name(&self) -> &str {
+            "stub"
+        }
+    }
+
+    // One trading check with every price at `price` and sentiment switched off
+    async fn check_at(portfolio: &mut Portfolio, price: f64, db: &Database) -> f64 {
+        check_with(
+            portfolio,
+            price,
+            &NullSentimentProvider,
+            &sentiment_config(),
+            db,
+        )
+        .await
+    }
+
+    // check_at with sentiment from `provider`, judged against `sentiment_config`'s thresholds
+    async fn check_with(
+        portfolio: &mut Portfolio,
+        price: f64,
+        provider: &dyn SentimentProvider,
+        sentiment_config: &SentimentConfig,
+        db: &Database,
+    ) -> f64 {
+        let ctx = TradeContext {
+            exchange: &StubExchange { price: Some(price) },
+            orders: None,
+            db,
+            notifier: &quiet_notifier(),
+        };
+        let quotes = quotes_at(portfolio, price, true);
+        portfolio
+            .check_portfolio(
+                &ctx,
+                &quotes,
+                provider,
+                sentiment_config,
+                &ChangeBaseline::default(),
+            )
+            .await
+            .unwrap()
+    }
+
+    // Sells worth more than $50 wait for approval, against a test Redis
+    async fn approval_portfolio(csv: &str, name: &str) -> (Portfolio, Database) {
+        let mut config = test_config();
+        config.trading_enabled = true;
+        config.manual_approval_above_usd = 50.0;
+        let portfolio = Portfolio::from_csv(config, csv).unwrap();
+        let db = Database::offline(&trade_log_path(name)).with_redis(&mock_redis().await);
+        (portfolio, db)
+    }
+
+    #[tokio::test]
+    async fn small_sells_run_and_large_ones_wait_for_approval() {
+        let (mut portfolio, db) =
+            approval_portfolio("SUI,10,1.5,1.2\nPHA,100,1.5,1.2\n", "approval-queue").await;
+        // Both are under their stop: SUI's $10 sells, PHA's $100 is queued
+        check_at(&mut portfolio, 1.0, &db).await;
+        let held: Vec<&str> = portfolio
+            .holdings
+            .iter()
+            .map(|h| h.symbol.as_str())
+            .collect();
+        assert_eq!(held, vec!["PHA"]);
+        let pending = db.pending_sell_for("PHA").await.unwrap().unwrap();
+        assert_eq!((pending.quantity, pending.approved), (100.0, false));
+
+        // Still waiting on the next check, without queueing it twice
+        check_at(&mut portfolio, 1.0, &db).await;
+        assert_eq!(portfolio.holdings[0].quantity, 100.0);
+        assert_eq!(
+            db.pending_sell_for("PHA").await.unwrap().unwrap().id,
+            pending.id
+        );
+
+        db.approve_pending_sell(pending.id).await.unwrap();
+        check_at(&mut portfolio, 1.0, &db).await;
+        assert!(portfolio.holdings.is_empty());
+        assert!(db.pending_sell_for("PHA").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn approvals_are_cleared_when_the_sell_runs_unapproved() {
+        let (mut portfolio, db) = approval_portfolio("PHA,100,1.5,1.2\n", "approval-stale").await;
+        check_at(&mut portfolio, 1.0, &db).await;
+        let pending = db.pending_sell_for("PHA").await.unwrap().unwrap();
+        db.approve_pending_sell(pending.id).await.unwrap();
+
+        // Since then it was topped up, its stop lowered so nothing triggers, and the price fell
+        // until the holding is under the threshold: only the approved 100 sell, as a manual sell
+        portfolio.holdings[0].quantity = 150.0;
+        portfolio.holdings[0].stop_loss = 0.1;
+        check_at(&mut portfolio, 0.3, &db).await;
+        assert_eq!(portfolio.holdings[0].quantity, 50.0);
+        assert!(db.pending_sell_for("PHA").await.unwrap().is_none());
+
+        // The approval is used up, so the rest stays held
+        check_at(&mut portfolio, 0.3, &db).await;
+        assert_eq!(portfolio.holdings[0].quantity, 50.0);
+    }
+
+    #[tokio::test]
+    async fn dust_drops_a_queued_sell() {
+        let (mut portfolio, db) = approval_portfolio("PHA,100,1.5,1.2\n", "approval-dust").await;
+        portfolio.config.min_trade_value_usd = 5.0;
+        check_at(&mut portfolio, 1.0, &db).await;
+        assert!(db.pending_sell_for("PHA").await.unwrap().is_some());
+
+        check_at(&mut portfolio, 0.01, &db).await;
+        assert_eq!(portfolio.holdings[0].quantity, 100.0);
+        assert!(db.pending_sell_for("PHA").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn carried_over_prices_value_the_holding_without_selling() {
+        let mut config = test_config();
+        config.trading_enabled = true;
+        let mut portfolio = Portfolio::from_csv(config, "SUI,10,1.5,1.2\n").unwrap();
+        let db = Database::offline(&trade_log_path("failed-fetch"));
+        let notifier = quiet_notifier();
+        let ctx = TradeContext {
+            exchange: &StubExchange { price: None },
+            orders: None,
+            db: &db,
+            notifier: &notifier,
+        };
+        // The last known price is below the stop-loss, but it isn't live, so nothing sells
+        let quotes = quotes_at(&portfolio, 1.0, false);
+        let value = portfolio
+            .check_portfolio(
+                &ctx,
+                &quotes,
+                &NullSentimentProvider,
+                &sentiment_config(),
+                &ChangeBaseline::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(value, 10.0);
+        assert_eq!(portfolio.holdings[0].quantity, 10.0);
+
+        // With no quote at all, the cycle fails rather than valuing SUI at 0
+        let checked = portfolio
+            .check_portfolio(
+                &ctx,
+                &HashMap::new(),
+                &NullSentimentProvider,
+                &sentiment_config(),
+                &ChangeBaseline::default(),
+            )
+            .await;
+        assert!(checked.is_err());
+    }
+
+    #[tokio::test]
+    async fn the_notified_value_is_the_cycle_value() {
+        let (url, mut requests) = mock_server(|_| (202, String::new())).await;
+        let mut notification = notification_config();
+        notification.email_enabled = true;
+        notification.sendgrid_api_url = url;
+        let notifier = Notifier::new(notification);
+        let mut portfolio =
+            Portfolio::from_csv(test_config(), "SUI,10,1.5,1.2\nPHA,100,0.1,0.05\n").unwrap();
+        let db = Database::offline(&trade_log_path("notified-value"));
+        // The exchange now quotes something else; the cycle's prices are what count
+        let ctx = TradeContext {
+            exchange: &StubExchange { price: Some(9.0) },
+            orders: None,
+            db: &db,
+            notifier: &notifier,
+        };
+        let mut quotes = quotes_at(&portfolio, 2.0, true);
+        quotes.get_mut("PHA").unwrap().price = to_decimal(0.5).unwrap();
+        let cycle_value = portfolio.value_at(&HashMap::from([
+            ("SUI".to_string(), 2.0),
+            ("PHA".to_string(), 0.5),
+        ]));
+        let baseline = ChangeBaseline {
+            value: 100.0,
+            ..Default::default()
+        };
+        let value = portfolio
+            .check_portfolio(
+                &ctx,
+                &quotes,
+                &NullSentimentProvider,
+                &sentiment_config(),
+                &baseline,
+            )
+            .await
+            .unwrap();
+        assert_eq!((value, cycle_value), (70.0, 70.0));
+        let alert = requests.recv().await.unwrap();
+        assert!(alert.contains("Current $70.00"), "{}", alert);
+    }
+
+    #[tokio::test]
+    async fn sells_settle_on_what_filled() {
+        let mut portfolio = Portfolio::from_csv(test_config(), "SUI,10.05,1.5,1.2\n").unwrap();
+        let db = Database::offline(&trade_log_path("partial-sell"));
+        let exchange = StubExchange { price: Some(2.0) };
+        // LOT_SIZE rounding sold 10 of the 10.05
+        let notifier = quiet_notifier();
+        let orders = stub_orders(10.0, 19.9);
+        let ctx = TradeContext {
+            exchange: &exchange,
+            orders: Some(&orders),
+            db: &db,
+            notifier: &notifier,
+        };
+        let proceeds = portfolio
+            .sell_holding("SUI", f64::INFINITY, TRIGGER_MANUAL, "test", &ctx)
+            .await
+            .unwrap();
+        assert_eq!(proceeds, 19.9);
+        assert_eq!(portfolio.cash, 19.9);
+        assert_eq!(portfolio.holdings[0].quantity, 0.05);
+
+        let empty = stub_orders(0.0, 0.0);
+        let ctx = TradeContext {
+            orders: Some(&empty),
+            ..ctx
+        };
+        let error = portfolio
+            .sell_holding("SUI", f64::INFINITY, TRIGGER_MANUAL, "test", &ctx)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("filled nothing"), "{}", error);
+        assert_eq!(portfolio.cash, 19.9);
+        assert_eq!(portfolio.holdings[0].quantity, 0.05);
+    }
+
+    #[tokio::test]
+    async fn buys_add_what_filled() {
+        let mut portfolio = Portfolio::from_csv(test_config(), "SUI,10,1.5,1.2\n").unwrap();
+        portfolio.cash = 100.0;
+        let db = Database::offline(&trade_log_path("partial-buy"));
+        let request = BuyRequest {
+            symbol: "SUI",
+            amount_usd: 10.0,
+            trigger: TRIGGER_MANUAL,
+            reason: "test",
+        };
+        let orders = stub_orders(4.0, 8.2);
+        let ctx = TradeContext {
+            exchange: &StubExchange { price: Some(2.0) },
+            orders: Some(&orders),
+            db: &db,
+            notifier: &quiet_notifier(),
+        };
+        let cost = portfolio.buy_holding(&request, &ctx).await.unwrap();
+        assert_eq!(cost, 8.2);
+        assert_eq!(portfolio.cash, 91.8);
+        assert_eq!(portfolio.holdings[0].quantity, 14.0);
+    }
+
+    #[tokio::test]
+    async fn the_cost_basis_counts_each_buy_once() {
+        let mut config = test_config();
+        config.holdings = vec![HoldingConfig {
+            symbol: "SUI".to_string(),
+            quantity: 10.0,
+            purchase_price: 1.5,
+            stop_loss: 1.2,
+            stop_loss_enabled: true,
+        }];
+        let mut portfolio = Portfolio::new(config.clone());
+        portfolio.cash = 100.0;
+        let db = Database::offline(&trade_log_path("cost-basis"));
+        let request = BuyRequest {
+            symbol: "SUI",
+            amount_usd: 10.0,
+            trigger: TRIGGER_MANUAL,
+            reason: "test",
+        };
+        let orders = stub_orders(4.0, 8.2);
+        let ctx = TradeContext {
+            exchange: &StubExchange { price: Some(2.0) },
+            orders: Some(&orders),
+            db: &db,
+            notifier: &quiet_notifier(),
+        };
+        portfolio.buy_holding(&request, &ctx).await.unwrap();
+
+        let trades = db.fetch_trades(None, None).await.unwrap();
+        let report = crate::report::compute_report(
+            &Portfolio::opening_holdings(&config),
+            &trades,
+            &HashMap::new(),
+            None,
+            None,
+            &config.rounding_mode,
+        );
+        assert_eq!(report.symbols[0].quantity, portfolio.holdings[0].quantity);
+        assert_eq!(report.invested, 23.2);
+    }
+
+    #[tokio::test]
+    async fn simulated_buys_leave_room_for_the_fee() {
+        let mut portfolio = Portfolio::from_csv(test_config(), "SUI,10,1.5,1.2\n").unwrap();
+        portfolio.cash = 200.0;
+        let db = Database::offline(&trade_log_path("simulated-buy"));
+        let notifier = quiet_notifier();
+        let ctx = TradeContext {
+            exchange: &FeeExchange,
+            orders: None,
+            db: &db,
+            notifier: &notifier,
+        };
+        let request = BuyRequest {
+            symbol: "PHA",
+            amount_usd: 100.1,
+            trigger: TRIGGER_MANUAL,
+            reason: "test",
+        };
+        assert_eq!(portfolio.buy_holding(&request, &ctx).await.unwrap(), 100.1);
+        assert_eq!(portfolio.cash, 99.9);
+        let pha = &portfolio.holdings[1];
+        assert_eq!((pha.symbol.as_str(), pha.quantity), ("PHA", 50.0));
+    }
+
+    #[tokio::test]
+    async fn buys_stop_at_the_cash_reserve() {
+        let mut config = test_config();
+        config.min_cash_reserve_usd = 100.0;
+        let mut portfolio = Portfolio::from_csv(config, "SUI,10,1.5,1.2\n").unwrap();
+        portfolio.cash = 150.0;
+        let db = Database::offline(&trade_log_path("cash-reserve"));
+        let notifier = quiet_notifier();
+        let ctx = TradeContext {
+            exchange: &StubExchange { price: Some(2.0) },
+            orders: None,
+            db: &db,
+            notifier: &notifier,
+        };
+        let buy = |amount_usd| BuyRequest {
+            symbol: "SUI",
+            amount_usd,
+            trigger: TRIGGER_MANUAL,
+            reason: "test",
+        };
+        let error = portfolio.buy_holding(&buy(60.0), &ctx).await.unwrap_err();
+        assert!(error.to_string().contains("Insufficient cash"), "{}", error);
+        assert_eq!(portfolio.cash, 150.0);
+
+        assert_eq!(portfolio.buy_holding(&buy(50.0), &ctx).await.unwrap(), 50.0);
+        assert_eq!(portfolio.cash, 100.0);
+        assert_eq!(portfolio.holdings[0].quantity, 35.0);
+    }
+
+    #[tokio::test]
+    async fn new_symbols_past_max_holdings_are_rejected() {
+        let mut config = test_config();
+        config.max_holdings = 2;
+        let mut portfolio =
+            Portfolio::from_csv(config, "SUI,10,1.5,1.2\nPHA,50,0.1,0.08\n").unwrap();
+        portfolio.cash = 100.0;
+        let db = Database::offline(&trade_log_path("max-holdings"));
+        let notifier = quiet_notifier();
+        let ctx = TradeContext {
+            exchange: &StubExchange { price: Some(2.0) },
+            orders: None,
+            db: &db,
+            notifier: &notifier,
+        };
+        let buy = |symbol| BuyRequest {
+            symbol,
+            amount_usd: 20.0,
+            trigger: TRIGGER_MANUAL,
+            reason: "test",
+        };
+
+        let error = portfolio.buy_holding(&buy("DUSK"), &ctx).await.unwrap_err();
+        assert!(error.to_string().contains("max_holdings"), "{}", error);
+        portfolio.buy_holding(&buy("SUI"), &ctx).await.unwrap();
+        assert_eq!(portfolio.holdings[0].quantity, 20.0);
+        assert_eq!(portfolio.holdings.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn sell_fees_come_off_the_proceeds() {
+        let notifier = quiet_notifier();
+        for (exchange, proceeds) in [
+            (&FeeExchange as &dyn Exchange, 19.98),
+            (&StubExchange { price: Some(2.0) }, 10.0 * 2.0),
+        ] {
+            let mut portfolio = Portfolio::from_csv(test_config(), "SUI,10,1.5,1.2\n").unwrap();
+            let db = Database::offline(&trade_log_path("sell-fee"));
+            let ctx = TradeContext {
+                exchange,
+                orders: None,
+                db: &db,
+                notifier: &notifier,
+            };
+            let sold = portfolio
+                .sell_holding("SUI", f64::INFINITY, TRIGGER_MANUAL, "test", &ctx)
+                .await
+                .unwrap();
+            assert_eq!((sold, portfolio.cash), (proceeds, proceeds));
+        }
+    }
+
+    #[tokio::test]
+    async fn disabled_sentiment_is_neutral_and_never_sells() {
+        let provider = create_sentiment_provider(&sentiment_config());
+        assert!(!provider.is_enabled());
+        assert_eq!(
+            provider.fetch_sentiment("SUI").await.unwrap(),
+            crate::exchange::NEUTRAL_SENTIMENT
+        );
+
+        // Above the stop-loss, so only sentiment could sell
+        let mut config = test_config();
+        config.trading_enabled = true;
+        let csv = "SUI,10,1.5,1.2\n";
+        let db = Database::offline(&trade_log_path("disabled-sentiment"));
+        let bearish = |enabled| StubSentiment {
+            score: 0.1,
+            enabled,
+        };
+        let mut portfolio = Portfolio::from_csv(config.clone(), csv).unwrap();
+        check_with(
+            &mut portfolio,
+            1.5,
+            &bearish(false),
+            &sentiment_config(),
+            &db,
+        )
+        .await;
+        assert_eq!(portfolio.holdings.len(), 1);
+        let mut portfolio = Portfolio::from_csv(config, csv).unwrap();
+        check_with(
+            &mut portfolio,
+            1.5,
+            &bearish(true),
+            &sentiment_config(),
+            &db,
+        )
+        .await;
+        assert!(portfolio.holdings.is_empty());
+    }
+
+    #[test]
+    fn snapshots_parse_back() {
+        let mut portfolio =
+            Portfolio::from_csv(test_config(), "SUI,10,1.5,1.2\nPHA,100,0.1,0.05\n").unwrap();
+        portfolio.cash = 5.0;
+        let prices = HashMap::from([("SUI".to_string(), 2.0), ("PHA".to_string(), 0.5)]);
+        let sentiments = HashMap::from([("SUI".to_string(), 0.7)]);
+        let path = std::env::temp_dir().join(format!("snapshot-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        portfolio
+            .write_snapshot(path, 75.0, &prices, &sentiments)
+            .unwrap();
+
+        let snapshot: PortfolioSnapshot =
+            serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!((snapshot.total_value, snapshot.cash), (75.0, 5.0));
+        let holdings: Vec<(&str, f64, f64, f64, Option<f64>)> = snapshot
+            .holdings
+            .iter()
+            .map(|h| (h.symbol.as_str(), h.quantity, h.price, h.value, h.sentiment))
+            .collect();
+        assert_eq!(
+            holdings,
+            vec![
+                ("SUI", 10.0, 2.0, 20.0, Some(0.7)),
+                ("PHA", 100.0, 0.5, 50.0, None)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn per_symbol_thresholds_override_the_global_ones() {
+        let mut config = test_config();
+        config.trading_enabled = true;
+        let mut portfolio =
+            Portfolio::from_csv(config, "SUI,10,1.5,1.2\nPHA,100,1.5,1.2\n").unwrap();
+        let sentiment: SentimentConfig = toml::from_str(
+            "enabled = true\napi_key = \"\"\ncache_ttl_secs = 60\npositive_threshold = 0.7\n\
+             negative_threshold = 0.3\n\
+             [per_symbol_thresholds.PHA]\npositive_threshold = 0.5\nnegative_threshold = 0.1",
+        )
+        .unwrap();
+        let bearish = StubSentiment {
+            score: 0.2,
+            enabled: true,
+        };
+        let db = Database::offline(&trade_log_path("per-symbol-thresholds"));
+        check_with(&mut portfolio, 1.5, &bearish, &sentiment, &db).await;
+        // 0.2 is under SUI's global 0.3 but above PHA's own 0.1
+        let held: Vec<&str> = portfolio
+            .holdings
+            .iter()
+            .map(|h| h.symbol.as_str())
+            .collect();
+        assert_eq!(held, vec!["PHA"]);
+    }
+
+    #[tokio::test]
+    async fn dust_holdings_are_kept_despite_a_trigger() {
+        let mut config = test_config();
+        config.trading_enabled = true;
+        config.min_trade_value_usd = 5.0;
+        let mut portfolio =
+            Portfolio::from_csv(config, "SUI,1,1.5,1.2\nPHA,100,1.5,1.2\n").unwrap();
+        let db = Database::offline(&trade_log_path("dust"));
+        // Both are under their stop-loss; only PHA is worth selling
+        check_at(&mut portfolio, 1.0, &db).await;
+        let held: Vec<(&str, f64)> = portfolio
+            .holdings
+            .iter()
+            .map(|h| (h.symbol.as_str(), h.quantity))
+            .collect();
+        assert_eq!(held, vec![("SUI", 1.0)]);
+        assert_eq!(portfolio.cash, 100.0);
+    }
+
+    #[tokio::test]
+    async fn paused_trading_never_sells() {
+        let mut config = test_config();
+        config.trading_enabled = false;
+        let mut portfolio = Portfolio::from_csv(config, "SUI,10,1.5,1.2\n").unwrap();
+        let db = Database::offline(&trade_log_path("paused"));
+        let provider = StubSentiment {
+            score: 0.1,
+            enabled: true,
+        };
+        // Both the stop-loss and the sentiment threshold are crossed
+        let value = check_with(&mut portfolio, 1.0, &provider, &sentiment_config(), &db).await;
+        assert_eq!(value, 10.0);
+        assert_eq!(portfolio.holdings[0].quantity, 10.0);
+
+        portfolio.config.trading_enabled = true;
+        check_with(&mut portfolio, 1.0, &provider, &sentiment_config(), &db).await;
+        assert!(portfolio.holdings.is_empty());
+    }
+
+    // Every fetch fails, as when the provider has no data for the symbol
+    struct MissingSentiment;
+
+    #[async_trait]
+    impl SentimentProvider for MissingSentiment {
+        async fn fetch_sentiment(&self, symbol: &str) -> Result<f64, PortfolioError> {
+            Err(PortfolioError::ApiError(format!("no data for {}", symbol)))
+        }
+
+        async fn fetch_detailed_sentiment(
+            &self,
+            symbol: &str,
+        ) -> Result<DetailedSentiment, PortfolioError> {
+            Err(PortfolioError::ApiError(format!("no data for {}", symbol)))
+        }
+
+        fn name(&self) -> &str {
+            "missing"
+        }
+    }
+
+    #[tokio::test]
+    async fn missing_sentiment_never_sells_and_shows_na() {
+        let mut config = test_config();
+        config.trading_enabled = true;
+        let mut portfolio = Portfolio::from_csv(config, "SUI,10,1.5,1.2\n").unwrap();
+        let db = Database::offline(&trade_log_path("missing-sentiment"));
+        // A neutral stand-in of 0.5 would be under this threshold if it counted as a real score
+        let mut sentiment_config = sentiment_config();
+        sentiment_config.negative_threshold = 0.6;
+        for on_missing in ["skip", "neutral"] {
+            sentiment_config.on_missing = on_missing.to_string();
+            check_with(
+                &mut portfolio,
+                1.3,
+                &MissingSentiment,
+                &sentiment_config,
+                &db,
+            )
+            .await;
+            assert_eq!(portfolio.holdings[0].quantity, 10.0, "{}", on_missing);
+        }
+        assert_eq!(format_sentiment(None), "N/A");
+    }
+
+    #[tokio::test]
+    async fn readded_holdings_are_not_resold_within_the_cooldown() {
+        let mut config = test_config();
+        config.trading_enabled = true;
+        config.sell_cooldown_secs = 3600;
+        let db = Database::offline(&trade_log_path("cooldown")).with_redis(&mock_redis().await);
+        let mut portfolio = Portfolio::from_csv(config.clone(), "SUI,10,1.5,1.2\n").unwrap();
+        check_at(&mut portfolio, 1.0, &db).await;
+        assert!(portfolio.holdings.is_empty());
+
+        // Bought back and still under its stop: SUI waits out the cooldown, PHA was never sold
+        let mut portfolio =
+            Portfolio::from_csv(config, "SUI,10,1.5,1.2\nPHA,10,1.5,1.2\n").unwrap();
+        check_at(&mut portfolio, 1.0, &db).await;
+        let held: Vec<&str> = portfolio
+            .holdings
+            .iter()
+            .map(|h| h.symbol.as_str())
+            .collect();
+        assert_eq!(held, vec!["SUI"]);
+    }
+
+    #[tokio::test]
+    async fn a_brief_dip_waits_out_the_grace_period() {
+        let mut config = test_config();
+        config.trading_enabled = true;
+        config.stop_loss_grace_secs = 1;
+        let mut portfolio = Portfolio::from_csv(config, "SUI,10,1.5,1.2\n").unwrap();
+        let db = Database::offline(&trade_log_path("grace")).with_redis(&mock_redis().await);
+
+        // One cycle under the stop, then a recovery: the breach is forgotten
+        check_at(&mut portfolio, 1.0, &db).await;
+        assert_eq!(portfolio.holdings[0].quantity, 10.0);
+        check_at(&mut portfolio, 1.3, &db).await;
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+        check_at(&mut portfolio, 1.0, &db).await;
+        assert_eq!(portfolio.holdings[0].quantity, 10.0);
+
+        // Still under the stop once the grace period has passed
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+        check_at(&mut portfolio, 1.0, &db).await;
+        assert!(portfolio.holdings.is_empty());
+    }
+
+    #[tokio::test]
+    async fn hodl_holdings_are_never_sold() {
+        let mut config = test_config();
+        config.trading_enabled = true;
+        let mut portfolio =
+            Portfolio::from_csv(config, "SUI,10,1.5,1.2\nPHA,10,1.5,1.2\n").unwrap();
+        portfolio.holdings[0].stop_loss_enabled = false;
+        let db = Database::offline(&trade_log_path("hodl"));
+        let provider = StubSentiment {
+            score: 0.1,
+            enabled: true,
+        };
+        // Both are under their nominal stop with bearish sentiment; only PHA sells
+        let value = check_with(&mut portfolio, 1.0, &provider, &sentiment_config(), &db).await;
+        let held: Vec<(&str, f64)> = portfolio
+            .holdings
+            .iter()
+            .map(|h| (h.symbol.as_str(), h.quantity))
+            .collect();
+        assert_eq!(held, vec![("SUI", 10.0)]);
+        // Still priced and counted like any other holding
+        assert_eq!(value, portfolio.cash + 10.0);
+    }
 }
@@ -0,0 +1,384 @@
+use crate::config::DisplayConfig;
+use crate::database::Trade;
+use crate::display::{format_price, format_value, new_table};
+use crate::errors::PortfolioError;
+use crate::portfolio::{round_money_f64, Holding};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+
+#[derive(Debug, Clone, Default)]
+struct Position {
+    quantity: f64,
+    average_cost: f64,
+    invested: f64,
+}
+
+// Average-cost basis per symbol, seeded from the opening holdings (Portfolio::opening_holdings)
+// and updated by trades in order
+#[derive(Debug, Clone, Default)]
+pub struct CostBasis {
+    positions: HashMap<String, Position>,
+    rounding_mode: String, // portfolio.rounding_mode, applied to each realized P&L
+}
+
+impl CostBasis {
+    pub fn new(holdings: &[Holding], rounding_mode: &str) -> Self {
+        let positions = holdings
+            .iter()
+            .map(|holding| {
+                (
+                    holding.symbol.clone(),
+                    Position {
+                        quantity: holding.quantity,
+                        average_cost: holding.purchase_price,
+                        invested: holding.quantity * holding.purchase_price,
+                    },
+                )
+            })
+            .collect();
+        CostBasis {
+            positions,
+            rounding_mode: rounding_mode.to_string(),
+        }
+    }
+
+    // Applies a trade and returns the P&L it realized (zero for buys)
+    pub fn apply(&mut self, trade: &Trade) -> f64 {
+        let position = self.positions.entry(trade.symbol.clone()).or_default();
+        match trade.action.as_str() {
+            "buy" => {
+                let trade_cost = trade.quantity * trade.price + trade.fee;
+                let cost = position.quantity * position.average_cost + trade_cost;
+                position.quantity += trade.quantity;
+                position.invested += trade_cost;
+                if position.quantity > 0.0 {
+                    position.average_cost = cost / position.quantity;
+                }
+                0.0
+            }
+            "sell" => {
+                let quantity = trade.quantity.min(position.quantity);
+                // Selling more than the basis knows of only realizes the known part, and its share of the fee
+                let fee = if trade.quantity > 0.0 {
+                    trade.fee * quantity / trade.quantity
+                } else {
+                    trade.fee
+                };
+                let realized = quantity * (trade.price - position.average_cost) - fee;
+                position.quantity -= quantity;
+                round_money_f64(realized, &self.rounding_mode)
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SymbolPerformance {
+    pub symbol: String,
+    pub quantity: f64,
+    pub average_cost: f64,
+    pub last_price: Option<f64>,
+    pub realized_pnl: f64,
+    pub unrealized_pnl: f64,
+}
+
+impl SymbolPerformance {
+    pub fn total_pnl(&self) -> f64 {
+        self.realized_pnl + self.unrealized_pnl
+    }
+}
+
+// Sells in the window grouped by the trigger tag they were logged with
+#[derive(Debug, Clone, Default)]
+pub struct TriggerPerformance {
+    pub sells: usize,
+    pub realized_pnl: f64,
+}
+
+// Label for sells logged before trades carried a reason
+const UNTAGGED_TRIGGER: &str = "untagged";
+
+#[derive(Debug, Clone)]
+pub struct PerformanceReport {
+    pub symbols: Vec<SymbolPerformance>,
+    pub triggers: BTreeMap<String, TriggerPerformance>, // Keyed by trigger tag, e.g., "stop-loss"
+
+    pub realized_pnl: f64,
+    pub unrealized_pnl: f64,
+    pub invested: f64,
+    pub total_return_percent: f64,
+}
+
+impl PerformanceReport {
+    pub fn best(&self) -> Option<&SymbolPerformance> {
+        self.symbols
+            .iter()
+            .max_by(|a, b| a.total_pnl().total_cmp(&b.total_pnl()))
+    }
+
+    pub fn worst(&self) -> Option<&SymbolPerformance> {
+        self.symbols
+            .iter()
+            .min_by(|a, b| a.total_pnl().total_cmp(&b.total_pnl()))
+    }
+}
+
+// All trades feed the cost basis, but only sells inside [from, to) count as realized P&L
+pub fn compute_report(
+    holdings: &[Holding],
+    trades: &[Trade],
+    latest_prices: &HashMap<String, f64>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    rounding_mode: &str,
+) -> PerformanceReport {
+    let mut cost_basis = CostBasis::new(holdings, rounding_mode);
+    let mut realized_in_window: HashMap<String, f64> = HashMap::new();
+    let mut triggers: BTreeMap<String, TriggerPerformance> = BTreeMap::new();
+    for trade in trades {
+        let realized = cost_basis.apply(trade);
+        let in_window = from.is_none_or(|from| trade.timestamp >= from)
+            && to.is_none_or(|to| trade.timestamp < to);
+        if in_window {
+            *realized_in_window.entry(trade.symbol.clone()).or_default() += realized;
+            if trade.action == "sell" {
+                let trigger = if trade.reason.is_empty() {
+                    UNTAGGED_TRIGGER
+                } else {
+                    trade.reason.as_str()
+                };
+                let performance = triggers.entry(trigger.to_string()).or_default();
+                performance.sells += 1;
+                performance.realized_pnl += realized;
+            }
+        }
+    }
+
+    let mut symbols: Vec<SymbolPerformance> = cost_basis
+        .positions
+        .iter()
+        .map(|(symbol, position)| {
+            let last_price = latest_prices.get(symbol).copied();
+            let unrealized_pnl = last_price
+                .map(|price| position.quantity * (price - position.average_cost))
+                .unwrap_or(0.0);
+            SymbolPerformance {
+                symbol: symbol.clone(),
+                quantity: position.quantity,
+                average_cost: position.average_cost,
+                last_price,
+                realized_pnl: realized_in_window.get(symbol).copied().unwrap_or(0.0),
+                unrealized_pnl,
+            }
+        })
+        .collect();
+    symbols.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+
+    let invested: f64 = cost_basis.positions.values().map(|p| p.invested).sum();
+    let realized_pnl: f64 = symbols.iter().map(|s| s.realized_pnl).sum();
+    let unrealized_pnl: f64 = symbols.iter().map(|s| s.unrealized_pnl).sum();
+    let total_return_percent = if invested > 0.0 {
+        (realized_pnl + unrealized_pnl) / invested * 100.0
+    } else {
+        0.0
+    };
+
+    PerformanceReport {
+        symbols,
+        triggers,
+        realized_pnl,
+        unrealized_pnl,
+        invested,
+        total_return_percent,
+    }
+}
+
+pub fn display_report(report: &PerformanceReport, display: &DisplayConfig) {
+    let mut table = new_table(display);
+    table.set_header(vec![
+        "Symbol",
+        "Quantity",
+        "Avg Cost",
+        "Last Price",
+        "Realized P&L",
+        "Unrealized P&L",
+        "Total P&L",
+    ]);
+    for performance in &report.symbols {
+        table.add_row(vec![
+            performance.symbol.clone(),
+            format!("{:.2}", performance.quantity),
+            format_price(performance.average_cost, display),
+            performance
+                .last_price
+                .map(|price| format_price(price, display))
+                .unwrap_or_else(|| "N/A".to_string()),
+            format_value(performance.realized_pnl, display),
+            format_value(performance.unrealized_pnl, display),
+            format_value(performance.total_pnl(), display),
+        ]);
+    }
+
+    println!("=== Performance Report ===\n{}", table);
+    if !report.triggers.is_empty() {
+        let mut table = new_table(display);
+        table.set_header(vec!["Trigger", "Sells", "Realized P&L"]);
+        for (trigger, performance) in &report.triggers {
+            table.add_row(vec![
+                trigger.clone(),
+                performance.sells.to_string(),
+                format_value(performance.realized_pnl, display),
+            ]);
+        }
+        println!("=== By Trigger ===\n{}", table);
+    }
+    println!("Invested:       {}", format_value(report.invested, display));
+    println!(
+        "Realized P&L:   {}",
+        format_value(report.realized_pnl, display)
+    );
+    println!(
+        "Unrealized P&L: {}",
+        format_value(report.unrealized_pnl, display)
+    );
+    println!("Total Return:   {:.2}%", report.total_return_percent);
+    if let (Some(best), Some(worst)) = (report.best(), report.worst()) {
+        println!(
+            "Best Holding:   {} ({})",
+            best.symbol,
+            format_value(best.total_pnl(), display)
+        );
+        println!(
+            "Worst Holding:  {} ({})",
+            worst.symbol,
+            format_value(worst.total_pnl(), display)
+        );
+    }
+}
+
+// One CSV row per trade: the Trade columns plus the P&L the trade realized
+#[derive(Serialize)]
+struct LedgerRow<'a> {
+    id: i32,
+    symbol: &'a str,
+    quantity: f64,
+    price: f64,
+    action: &'a str,
+    timestamp: String, // RFC 3339, UTC
+    fee: f64,
+    reason: &'a str,
+    realized_pnl: f64,
+}
+
+// Writes the trades inside [from, to) (optionally one symbol) in chronological order and returns
+// how many were written; every trade still feeds the cost basis, as in compute_report
+pub fn write_trade_ledger<W: Write>(
+    writer: W,
+    holdings: &[Holding],
+    trades: &[Trade],
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    symbol: Option<&str>,
+    rounding_mode: &str,
+) -> Result<usize, PortfolioError> {
+    let mut csv = csv::Writer::from_writer(writer);
+    let mut cost_basis = CostBasis::new(holdings, rounding_mode);
+    let mut written = 0;
+    for trade in trades {
+        let realized_pnl = cost_basis.apply(trade);
+        let in_window = from.is_none_or(|from| trade.timestamp >= from)
+            && to.is_none_or(|to| trade.timestamp < to);
+        let matches = symbol.is_none_or(|symbol| trade.symbol.eq_ignore_ascii_case(symbol));
+        if !in_window || !matches {
+            continue;
+        }
+        csv.serialize(LedgerRow {
+            id: trade.id,
+            symbol: &trade.symbol,
+            quantity: trade.quantity,
+            price: trade.price,
+            action: &trade.action,
+            timestamp: trade.timestamp.to_rfc3339(),
+            fee: trade.fee,
+            reason: &trade.reason,
+            realized_pnl,
+        })
+        .map_err(|e| PortfolioError::IoError(e.to_string()))?;
+        written += 1;
+    }
+    csv.flush()
+        .map_err(|e| PortfolioError::IoError(e.to_string()))?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn trade(action: &str, quantity: f64, price: f64, fee: f64) -> Trade {
+        Trade {
+            id: 0,
+            symbol: "SUI".to_string(),
+            quantity,
+            price,
+            action: action.to_string(),
+            timestamp: Utc::now(),
+            fee,
+            reason: String::new(),
+        }
+    }
+
+    #[test]
+    fn oversized_sells_realize_their_share_of_the_fee() {
+        let mut cost_basis = CostBasis::new(&[], "half_even");
+        assert_eq!(cost_basis.apply(&trade("buy", 10.0, 1.0, 0.0)), 0.0);
+        // Only 10 of the 20 sold were known, so only half the fee counts against them
+        assert_eq!(cost_basis.apply(&trade("sell", 20.0, 2.0, 0.4)), 9.8);
+    }
+
+    #[test]
+    fn ledger_rows_follow_the_fixture_trades() {
+        let at = |day: u32| Utc.with_ymd_and_hms(2024, 3, day, 12, 0, 0).unwrap();
+        let trades = [
+            Trade {
+                id: 1,
+                timestamp: at(1),
+                ..trade("buy", 10.0, 1.0, 0.01)
+            },
+            Trade {
+                id: 2,
+                timestamp: at(2),
+                reason: "stop-loss".to_string(),
+                ..trade("sell", 4.0, 2.0, 0.01)
+            },
+            Trade {
+                id: 3,
+                symbol: "PHA".to_string(),
+                timestamp: at(3),
+                ..trade("buy", 100.0, 0.1, 0.0)
+            },
+        ];
+        let mut ledger = Vec::new();
+        let written = write_trade_ledger(
+            &mut ledger,
+            &[],
+            &trades,
+            Some(at(2)),
+            None,
+            Some("sui"),
+            "half_even",
+        )
+        .unwrap();
+        assert_eq!(written, 1);
+        // The buy outside the window still sets the cost basis for the sell
+        assert_eq!(
+            String::from_utf8(ledger).unwrap(),
+            "id,symbol,quantity,price,action,timestamp,fee,reason,realized_pnl\n\
+             2,SUI,4.0,2.0,sell,2024-03-02T12:00:00+00:00,0.01,stop-loss,3.99\n"
+        );
+    }
+}
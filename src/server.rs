@@ -0,0 +1,177 @@
+use crate::errors::PortfolioError;
+use crate::logger::log_action;
+use crate::portfolio::PortfolioSnapshot;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::OnceLock;
+use tokio::net::TcpListener;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+// Events buffered per client before a slow one starts skipping the oldest
+const EVENT_BUFFER: usize = 256;
+
+// Pushed to every /ws client as JSON, e.g., {"type":"trade","symbol":"SUI",...}
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    Cycle(PortfolioSnapshot), // One per completed portfolio check
+    Trade {
+        symbol: String,
+        action: String, // "buy" or "sell"
+        quantity: f64,
+        price: f64,
+        value: f64, // Proceeds for sells, cost for buys, after fees
+        reason: String,
+        timestamp: DateTime<Utc>,
+    },
+    Alert {
+        severity: String,
+        subject: String,
+        message: String,
+        timestamp: DateTime<Utc>,
+    },
+}
+
+static EVENTS: OnceLock<broadcast::Sender<Event>> = OnceLock::new();
+
+fn events() -> &'static broadcast::Sender<Event> {
+    EVENTS.get_or_init(|| broadcast::channel(EVENT_BUFFER).0)
+}
+
+// Fire-and-forget; dropped when no client is connected (or the server is off)
+pub fn publish(event: Event) {
+    let _ = events().send(event);
+}
+
+// Serves GET /ws until the process exits; started by the portfolio screen when server.enabled is set
+pub async fn serve(bind: &str) -> Result<(), PortfolioError> {
+    let listener = TcpListener::bind(bind)
+        .await
+        .map_err(|e| PortfolioError::IoError(format!("Failed to bind {}: {}", bind, e)))?;
+    let _ = log_action(&format!("Push server listening on ws://{}/ws", bind), None);
+    let app = Router::new().route("/ws", get(upgrade));
+    axum::serve(listener, app)
+        .await
+        .map_err(|e| PortfolioError::IoError(e.to_string()))
+}
+
+async fn upgrade(ws: WebSocketUpgrade) -> impl IntoResponse {
+    ws.on_upgrade(stream_events)
+}
+
+// Forwards events until the client disconnects; a lagging client skips what it missed
+async fn stream_events(mut socket: WebSocket) {
+    let mut receiver = events().subscribe();
+    loop {
+        tokio::select! {
+            event = receiver.recv() => match event {
+                Ok(event) => {
+                    let Ok(json) = serde_json::to_string(&event) else {
+                        continue;
+                    };
+                    if socket.send(Message::Text(json)).await.is_err() {
+                        break;
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    let _ = log_action(
+                        &format!("Push client fell behind, skipped {} events", skipped),
+                        None,
+                    );
+                }
+                Err(RecvError::Closed) => break,
+            },
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    // Reads one unmasked server frame and returns its payload
+    async fn read_frame(stream: &mut TcpStream) -> Vec<u8> {
+        let mut head = [0; 2];
+        stream.read_exact(&mut head).await.unwrap();
+        let length = match head[1] & 0x7f {
+            126 => {
+                let mut length = [0; 2];
+                stream.read_exact(&mut length).await.unwrap();
+                u16::from_be_bytes(length) as usize
+            }
+            127 => {
+                let mut length = [0; 8];
+                stream.read_exact(&mut length).await.unwrap();
+                u64::from_be_bytes(length) as usize
+            }
+            length => length as usize,
+        };
+        let mut payload = vec![0; length];
+        stream.read_exact(&mut payload).await.unwrap();
+        payload
+    }
+
+    #[tokio::test]
+    async fn clients_receive_cycle_updates() {
+        let bind = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap().to_string()
+        };
+        let server = bind.clone();
+        tokio::spawn(async move { serve(&server).await });
+        let mut stream = loop {
+            match TcpStream::connect(&bind).await {
+                Ok(stream) => break stream,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+        let handshake = format!(
+            "GET /ws HTTP/1.1\r\nHost: {}\r\nConnection: Upgrade\r\nUpgrade: websocket\r\n\
+             Sec-WebSocket-Version: 13\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n",
+            bind
+        );
+        stream.write_all(handshake.as_bytes()).await.unwrap();
+        let mut response = Vec::new();
+        while !response.ends_with(b"\r\n\r\n") {
+            let mut byte = [0; 1];
+            stream.read_exact(&mut byte).await.unwrap();
+            response.push(byte[0]);
+        }
+        assert!(response.starts_with(b"HTTP/1.1 101"));
+
+        // The client subscribes just after the upgrade, so keep publishing until it hears one
+        let publisher = tokio::spawn(async {
+            loop {
+                publish(Event::Cycle(PortfolioSnapshot {
+                    timestamp: Utc::now(),
+                    total_value: 20.0,
+                    cash: 5.0,
+                    holdings: Vec::new(),
+                }));
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        });
+        // Alerts published by other tests share the channel
+        let cycle = loop {
+            let event: serde_json::Value =
+                serde_json::from_slice(&read_frame(&mut stream).await).unwrap();
+            if event["type"] == "cycle" {
+                break event;
+            }
+        };
+        publisher.abort();
+        assert_eq!(cycle["total_value"], 20.0);
+        assert_eq!(cycle["cash"], 5.0);
+    }
+}
@@ -0,0 +1,155 @@
+use crate::errors::PortfolioError;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+// One counters file per running process, so the `stats` subcommand can read them from outside
+pub const STATS_DIR: &str = "stats";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProviderStats {
+    pub calls: u64,
+    pub total_latency_ms: u64,
+}
+
+impl ProviderStats {
+    pub fn average_latency_ms(&self) -> f64 {
+        if self.calls == 0 {
+            0.0
+        } else {
+            self.total_latency_ms as f64 / self.calls as f64
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stats {
+    pub process: String, // Screen name, or "screens" when they share one process
+    pub started_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub providers: BTreeMap<String, ProviderStats>, // Keyed by provider, e.g., "binance"
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+    pub sells: u64,
+}
+
+impl Stats {
+    fn new(process: &str) -> Self {
+        Stats {
+            process: process.to_string(),
+            started_at: Utc::now(),
+            updated_at: Utc::now(),
+            providers: BTreeMap::new(),
+            cache_hits: 0,
+            cache_misses: 0,
+            sells: 0,
+        }
+    }
+
+    pub fn api_calls(&self) -> u64 {
+        self.providers.values().map(|provider| provider.calls).sum()
+    }
+
+    fn record_cache_lookup(&mut self, hit: bool) {
+        if hit {
+            self.cache_hits += 1;
+        } else {
+            self.cache_misses += 1;
+        }
+    }
+
+    // Share of cache lookups that hit; None before the first lookup
+    pub fn cache_hit_ratio(&self) -> Option<f64> {
+        let lookups = self.cache_hits + self.cache_misses;
+        (lookups > 0).then(|| self.cache_hits as f64 / lookups as f64)
+    }
+}
+
+static STATS: OnceLock<Mutex<Stats>> = OnceLock::new();
+
+fn stats() -> &'static Mutex<Stats> {
+    STATS.get_or_init(|| Mutex::new(Stats::new("main")))
+}
+
+// Names this process's stats file; called once before the screens start
+pub fn set_process_name(name: &str) {
+    stats().lock().unwrap().process = name.to_string();
+}
+
+// Counted next to log_elapsed for every external request
+pub fn record_api_call(provider: &str, started: Instant) {
+    let elapsed = started.elapsed().as_millis() as u64;
+    let mut stats = stats().lock().unwrap();
+    let provider = stats.providers.entry(provider.to_string()).or_default();
+    provider.calls += 1;
+    provider.total_latency_ms += elapsed;
+}
+
+pub fn record_cache_lookup(hit: bool) {
+    stats().lock().unwrap().record_cache_lookup(hit);
+}
+
+pub fn record_sell() {
+    stats().lock().unwrap().sells += 1;
+}
+
+// Rewrites stats/<process>.json after each refresh; temp file + rename like the portfolio snapshot
+pub fn persist() -> Result<(), PortfolioError> {
+    let snapshot = {
+        let mut stats = stats().lock().unwrap();
+        stats.updated_at = Utc::now();
+        stats.clone()
+    };
+    fs::create_dir_all(STATS_DIR).map_err(|e| PortfolioError::IoError(e.to_string()))?;
+    let json = serde_json::to_string_pretty(&snapshot)
+        .map_err(|e| PortfolioError::IoError(e.to_string()))?;
+    let path = Path::new(STATS_DIR).join(format!("{}.json", snapshot.process));
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, json).map_err(|e| PortfolioError::IoError(e.to_string()))?;
+    fs::rename(&tmp_path, &path).map_err(|e| PortfolioError::IoError(e.to_string()))?;
+    Ok(())
+}
+
+// Every persisted process, oldest start first; empty when nothing has run yet
+pub fn load_all() -> Result<Vec<Stats>, PortfolioError> {
+    let entries = match fs::read_dir(STATS_DIR) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(Vec::new()),
+    };
+    let mut all = Vec::new();
+    for entry in entries {
+        let path = entry
+            .map_err(|e| PortfolioError::IoError(e.to_string()))?
+            .path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue;
+        }
+        let json = fs::read_to_string(&path).map_err(|e| PortfolioError::IoError(e.to_string()))?;
+        let stats: Stats = serde_json::from_str(&json).map_err(|e| {
+            PortfolioError::IoError(format!("Invalid stats file {}: {}", path.display(), e))
+        })?;
+        all.push(stats);
+    }
+    all.sort_by_key(|stats| stats.started_at);
+    Ok(all)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_lookups_are_counted_as_hits_and_misses() {
+        let mut stats = Stats::new("test");
+        assert_eq!(stats.cache_hit_ratio(), None);
+        for hit in [true, false, true, true] {
+            stats.record_cache_lookup(hit);
+        }
+        assert_eq!((stats.cache_hits, stats.cache_misses), (3, 1));
+        assert_eq!(stats.cache_hit_ratio(), Some(0.75));
+    }
+}
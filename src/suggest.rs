@@ -0,0 +1,237 @@
+use crate::config::{DisplayConfig, SuggestConfig};
+use crate::display::{format_percent, format_price, new_table};
+use crate::portfolio::format_sentiment;
+
+// Suggested sizes snap to this many percent of the position
+const SIZE_STEP_PCT: f64 = 5.0;
+
+// Week-over-week sentiment moves smaller than this are left out of the reasons
+const TREND_EPSILON: f64 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Trim,
+    Hold,
+    Add,
+}
+
+impl Action {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::Trim => "trim",
+            Action::Hold => "hold",
+            Action::Add => "add",
+        }
+    }
+}
+
+// What one holding looked like when the suggestion was made; None where the source had no data
+#[derive(Debug, Clone)]
+pub struct SuggestInput {
+    pub symbol: String,
+    pub price: Option<f64>,
+    pub gain_pct: Option<f64>,    // Unrealized, against the average cost
+    pub sentiment: Option<f64>,   // Blended, 0..1
+    pub week_change: Option<f64>, // One-week sentiment change as a fraction, e.g., -0.12
+}
+
+// Each signal is -1..1: positive leans towards adding, negative towards trimming
+#[derive(Debug, Clone, Copy)]
+pub struct Signals {
+    pub sentiment: f64,
+    pub trend: f64,
+    pub gain: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Suggestion {
+    pub input: SuggestInput,
+    pub signals: Signals,
+    pub score: f64,
+    pub action: Action,
+    pub size_pct: f64, // Share of the position to trim or add; 0 for hold
+    pub reasons: Vec<String>,
+}
+
+impl Suggestion {
+    // e.g., "trim 20%: sentiment falling, +35% gain"
+    pub fn summary(&self) -> String {
+        let action = match self.action {
+            Action::Hold => self.action.label().to_string(),
+            _ => format!("{} {:.0}%", self.action.label(), self.size_pct),
+        };
+        format!("{}: {}", action, self.reasons.join(", "))
+    }
+}
+
+// Missing data contributes a neutral 0 rather than skewing the score
+pub fn signals(input: &SuggestInput) -> Signals {
+    Signals {
+        sentiment: input
+            .sentiment
+            .map_or(0.0, |sentiment| ((sentiment - 0.5) * 2.0).clamp(-1.0, 1.0)),
+        trend: input
+            .week_change
+            .map_or(0.0, |change| change.clamp(-1.0, 1.0)),
+        // A 100% gain is a full trim signal; a 100% loss a full add signal
+        gain: input
+            .gain_pct
+            .map_or(0.0, |gain| (-gain / 100.0).clamp(-1.0, 1.0)),
+    }
+}
+
+// Weighted average of the signals, so the score stays in -1..1 whatever the weights add up to
+pub fn score(signals: &Signals, weights: &SuggestConfig) -> f64 {
+    let total = weights.sentiment_weight + weights.trend_weight + weights.gain_weight;
+    if total <= 0.0 {
+        return 0.0;
+    }
+    (signals.sentiment * weights.sentiment_weight
+        + signals.trend * weights.trend_weight
+        + signals.gain * weights.gain_weight)
+        / total
+}
+
+// Hold inside ±hold_band; beyond it the size grows linearly to max_trade_pct at ±1
+pub fn sizing(score: f64, weights: &SuggestConfig) -> (Action, f64) {
+    let band = weights.hold_band.clamp(0.0, 0.99);
+    if score.abs() <= band {
+        return (Action::Hold, 0.0);
+    }
+    let strength = ((score.abs() - band) / (1.0 - band)).min(1.0);
+    let size = (strength * weights.max_trade_pct / SIZE_STEP_PCT).round() * SIZE_STEP_PCT;
+    if size <= 0.0 {
+        return (Action::Hold, 0.0);
+    }
+    let action = if score > 0.0 {
+        Action::Add
+    } else {
+        Action::Trim
+    };
+    (action, size.min(weights.max_trade_pct))
+}
+
+fn reasons(input: &SuggestInput) -> Vec<String> {
+    let mut reasons = Vec::new();
+    match input.sentiment {
+        Some(sentiment) if sentiment >= 0.6 => reasons.push("sentiment high".to_string()),
+        Some(sentiment) if sentiment <= 0.4 => reasons.push("sentiment low".to_string()),
+        Some(_) => reasons.push("sentiment neutral".to_string()),
+        None => reasons.push("no sentiment".to_string()),
+    }
+    match input.week_change {
+        Some(change) if change > TREND_EPSILON => reasons.push("sentiment rising".to_string()),
+        Some(change) if change < -TREND_EPSILON => reasons.push("sentiment falling".to_string()),
+        _ => {}
+    }
+    match input.gain_pct {
+        Some(gain) if gain >= 0.0 => reasons.push(format!("{} gain", format_percent(gain, 0))),
+        Some(gain) => reasons.push(format!("{} loss", format_percent(gain, 0))),
+        None => reasons.push("no price".to_string()),
+    }
+    reasons
+}
+
+pub fn suggest(input: SuggestInput, weights: &SuggestConfig) -> Suggestion {
+    let signals = signals(&input);
+    let score = score(&signals, weights);
+    let (action, size_pct) = sizing(score, weights);
+    let reasons = reasons(&input);
+    Suggestion {
+        input,
+        signals,
+        score,
+        action,
+        size_pct,
+        reasons,
+    }
+}
+
+pub fn display_suggestions(
+    suggestions: &[Suggestion],
+    weights: &SuggestConfig,
+    display: &DisplayConfig,
+) {
+    let mut table = new_table(display);
+    table.set_header(vec![
+        "Symbol",
+        "Price",
+        "Gain",
+        "Sentiment",
+        "1-Week",
+        "Signals (S/T/G)",
+        "Score",
+        "Suggestion",
+    ]);
+    for suggestion in suggestions {
+        let input = &suggestion.input;
+        table.add_row(vec![
+            input.symbol.clone(),
+            input
+                .price
+                .map(|price| format_price(price, display))
+                .unwrap_or_else(|| "N/A".to_string()),
+            input
+                .gain_pct
+                .map(|gain| format_percent(gain, 2))
+                .unwrap_or_else(|| "N/A".to_string()),
+            format_sentiment(input.sentiment),
+            input
+                .week_change
+                .map(|change| format_percent(change * 100.0, 2))
+                .unwrap_or_else(|| "N/A".to_string()),
+            format!(
+                "{:+.2} / {:+.2} / {:+.2}",
+                suggestion.signals.sentiment, suggestion.signals.trend, suggestion.signals.gain
+            ),
+            format!("{:+.2}", suggestion.score),
+            suggestion.summary(),
+        ]);
+    }
+
+    println!("=== Position Sizing Suggestions ===\n{}", table);
+    println!(
+        "Score = ({:.2}×S + {:.2}×T + {:.2}×G) / {:.2}; hold within ±{:.2}, up to {:.0}% at ±1",
+        weights.sentiment_weight,
+        weights.trend_weight,
+        weights.gain_weight,
+        weights.sentiment_weight + weights.trend_weight + weights.gain_weight,
+        weights.hold_band,
+        weights.max_trade_pct
+    );
+    println!("Suggestions only: nothing is traded.");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(sentiment: f64, gain_pct: f64) -> SuggestInput {
+        SuggestInput {
+            symbol: "SUI".to_string(),
+            price: Some(2.0),
+            gain_pct: Some(gain_pct),
+            sentiment: Some(sentiment),
+            week_change: None,
+        }
+    }
+
+    #[test]
+    fn sentiment_and_gains_size_the_suggestion() {
+        let weights = SuggestConfig::default();
+        let sized = |sentiment: f64, gain_pct: f64| {
+            let suggestion = suggest(input(sentiment, gain_pct), &weights);
+            (suggestion.action, suggestion.size_pct)
+        };
+        // Both signals agree: a clear add or trim
+        assert_eq!(sized(0.9, -40.0), (Action::Add, 20.0));
+        assert_eq!(sized(0.1, 100.0), (Action::Trim, 30.0));
+        // Bullish sentiment on a doubled position mostly cancels out
+        assert_eq!(sized(0.9, 100.0), (Action::Hold, 0.0));
+        // Bearish sentiment outweighs a moderate loss, but only just
+        assert_eq!(sized(0.1, -40.0), (Action::Trim, 5.0));
+
+        let suggestion = suggest(input(0.1, 100.0), &weights);
+        assert_eq!(suggestion.summary(), "trim 30%: sentiment low, +100% gain");
+    }
+}